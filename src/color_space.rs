@@ -0,0 +1,133 @@
+use crate::utils::{linear_rgb_to_xyz, rgb_to_yuv, srgb_decode, xyz_to_lab, Subpixel};
+use std::fmt::{Display, Formatter};
+
+/// The color space the hybrid comparison decomposes rgb images to one luma and two chroma channels in.
+/// All channels are scaled to the range of the subpixel type, chroma is centered around [`Subpixel::center`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    /// Full range Y'CbCr with the BT.601 coefficients, see <https://www.itu.int/rec/T-REC-T.871> - this is the default
+    #[default]
+    Bt601,
+    /// Full range Y'CbCr with the BT.709 coefficients for HD video content
+    Bt709,
+    /// Full range Y'CbCr with the non-constant luminance BT.2020 coefficients for UHD video content
+    Bt2020,
+    /// CIELAB of the sRGB values with D65 white. L* is scaled from `0..100` to the full range,
+    /// a* and b* are offset by the center like the 8 bit encoding `a* + 128`
+    Lab,
+    /// OKLab of the sRGB values, see <https://bottosson.github.io/posts/oklab/>.
+    /// L is scaled from `0..1` to the full range, a and b are offset by the center and scaled like L
+    OkLab,
+}
+
+impl Display for ColorSpace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ColorSpace::Bt601 => "BT.601",
+            ColorSpace::Bt709 => "BT.709",
+            ColorSpace::Bt2020 => "BT.2020",
+            ColorSpace::Lab => "CIELAB",
+            ColorSpace::OkLab => "OKLab",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl ColorSpace {
+    /// Converts the rgb values in the range of `P` to luma and chroma in the range of `P`
+    pub(crate) fn decompose<P: Subpixel>(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let range = P::RANGE as f32;
+        let center = P::center();
+        match self {
+            ColorSpace::Bt601 => rgb_to_yuv(&rgb, center),
+            ColorSpace::Bt709 => rgb_to_ycbcr(&rgb, 0.2126, 0.0722, center),
+            ColorSpace::Bt2020 => rgb_to_ycbcr(&rgb, 0.2627, 0.0593, center),
+            ColorSpace::Lab => {
+                let [l, a, b] = xyz_to_lab(linear_rgb_to_xyz(to_linear(rgb, range)));
+                let scale = range / u8::MAX as f32;
+                [
+                    l as f32 / 100. * range,
+                    center + a as f32 * scale,
+                    center + b as f32 * scale,
+                ]
+            }
+            ColorSpace::OkLab => {
+                let [l, a, b] = linear_rgb_to_oklab(to_linear(rgb, range));
+                [
+                    l as f32 * range,
+                    center + a as f32 * range,
+                    center + b as f32 * range,
+                ]
+            }
+        }
+    }
+}
+
+/// Full range Y'CbCr from the luma coefficients of red `kr` and blue `kb`
+fn rgb_to_ycbcr(rgb: &[f32; 3], kr: f32, kb: f32, center: f32) -> [f32; 3] {
+    let y = kr * rgb[0] + (1. - kr - kb) * rgb[1] + kb * rgb[2];
+    let cb = center + 0.5 * (rgb[2] - y) / (1. - kb);
+    let cr = center + 0.5 * (rgb[0] - y) / (1. - kr);
+    [y, cb, cr]
+}
+
+fn to_linear(rgb: [f32; 3], range: f32) -> [f64; 3] {
+    rgb.map(|c| srgb_decode((c / range).clamp(0., 1.) as f64))
+}
+
+/// see https://bottosson.github.io/posts/oklab/
+fn linear_rgb_to_oklab([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gray_has_centered_chroma() {
+        for color_space in [
+            ColorSpace::Bt601,
+            ColorSpace::Bt709,
+            ColorSpace::Bt2020,
+            ColorSpace::Lab,
+            ColorSpace::OkLab,
+        ] {
+            let [y, u, v] = color_space.decompose::<u8>([255., 255., 255.]);
+            assert!((y - 255.).abs() < 0.01, "{color_space}");
+            assert!((u - 128.).abs() < 0.01, "{color_space}");
+            assert!((v - 128.).abs() < 0.01, "{color_space}");
+            let [y, u, v] = color_space.decompose::<u16>([0., 0., 0.]);
+            assert!(y.abs() < 0.01, "{color_space}");
+            assert!((u - 32768.).abs() < 0.01, "{color_space}");
+            assert!((v - 32768.).abs() < 0.01, "{color_space}");
+        }
+    }
+
+    #[test]
+    fn luma_coefficients() {
+        let [y, _, v] = ColorSpace::Bt709.decompose::<u8>([255., 0., 0.]);
+        assert!((y - 0.2126 * 255.).abs() < 1e-3);
+        assert!((v - 255.5).abs() < 1e-3);
+        let [y, u, _] = ColorSpace::Bt2020.decompose::<f32>([0., 0., 1.]);
+        assert!((y - 0.0593).abs() < 1e-6);
+        assert!((u - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn oklab_reference_values() {
+        // see https://bottosson.github.io/posts/oklab/ for reference values
+        let [l, a, b] = linear_rgb_to_oklab([1., 0., 0.]);
+        assert!((l - 0.627955).abs() < 1e-5);
+        assert!((a - 0.224863).abs() < 1e-5);
+        assert!((b - 0.125846).abs() < 1e-5);
+        assert_eq!(ColorSpace::OkLab.to_string(), "OKLab");
+    }
+}
@@ -1,4 +1,8 @@
-use image::{DynamicImage, GrayImage, ImageBuffer, Luma, Rgb, RgbImage, Rgba, RgbaImage};
+use crate::flip::flip_color_map;
+use image::{
+    DynamicImage, GrayAlphaImage, GrayImage, ImageBuffer, Luma, LumaA, Rgb, RgbImage, Rgba,
+    RgbaImage,
+};
 
 /// a single-channel f32 typed image containing a result-score for each pixel
 pub type GraySimilarityImage = ImageBuffer<Luma<f32>, Vec<f32>>;
@@ -31,7 +35,23 @@ impl From<RGBSimilarityImage> for SimilarityImage {
     }
 }
 
+/// Masked pixels are `NaN` in the similarity images, see [`crate::Mask`]
+fn has_masked_pixels<P: image::Pixel<Subpixel = f32>>(img: &ImageBuffer<P, Vec<f32>>) -> bool {
+    img.iter().any(|c| c.is_nan())
+}
+
 fn gray_map(img: &GraySimilarityImage) -> DynamicImage {
+    if has_masked_pixels(img) {
+        return GrayAlphaImage::from_fn(img.width(), img.height(), |col, row| {
+            let value = img.get_pixel(col, row)[0];
+            if value.is_nan() {
+                LumaA([0, 0])
+            } else {
+                LumaA([(value.clamp(0., 1.) * 255.) as u8, 255])
+            }
+        })
+        .into();
+    }
     let mut img_gray = GrayImage::new(img.width(), img.height());
     for row in 0..img.height() {
         for col in 0..img.width() {
@@ -43,6 +63,18 @@ fn gray_map(img: &GraySimilarityImage) -> DynamicImage {
 }
 
 fn to_color_map(img: &RGBSimilarityImage) -> DynamicImage {
+    if has_masked_pixels(img) {
+        return RgbaImage::from_fn(img.width(), img.height(), |col, row| {
+            let pixel = img.get_pixel(col, row);
+            if pixel.0.iter().any(|c| c.is_nan()) {
+                Rgba([0; 4])
+            } else {
+                let [r, g, b] = pixel.0.map(|c| (c.clamp(0., 1.) * 255.) as u8);
+                Rgba([r, g, b, 255])
+            }
+        })
+        .into();
+    }
     let mut img_rgb = RgbImage::new(img.width(), img.height());
     for row in 0..img.height() {
         for col in 0..img.width() {
@@ -73,6 +105,8 @@ fn to_color_map_rgba(img: &RGBASimilarityImage) -> DynamicImage {
 }
 
 impl SimilarityImage {
+    /// Converts the map to an 8 bit image. Masked pixels are transparent, so maps with masked pixels
+    /// get an alpha channel - gray maps become gray with alpha, rgb maps rgba.
     pub fn to_color_map(&self) -> DynamicImage {
         match self {
             SimilarityImage::Gray(gray) => gray_map(gray),
@@ -80,6 +114,27 @@ impl SimilarityImage {
             SimilarityImage::RGBA(rgba) => to_color_map_rgba(rgba),
         }
     }
+
+    /// Visualizes the map like the FLIP reference using the magma colormap: identical pixels are black,
+    /// growing differences go from purple over orange to a light yellow. Intended for maps where 1.0 means identical,
+    /// e.g. of [`crate::rgb_similarity_flip`] - for multichannel maps the lowest similarity of each pixel is shown.
+    pub fn to_flip_color_map(&self) -> DynamicImage {
+        let worst = |pixels: &[f32]| pixels.iter().copied().fold(f32::INFINITY, f32::min);
+        let gray = match self {
+            SimilarityImage::Gray(gray) => gray.clone(),
+            SimilarityImage::RGB(rgb) => {
+                GraySimilarityImage::from_fn(rgb.width(), rgb.height(), |col, row| {
+                    Luma([worst(&rgb.get_pixel(col, row).0)])
+                })
+            }
+            SimilarityImage::RGBA(rgba) => {
+                GraySimilarityImage::from_fn(rgba.width(), rgba.height(), |col, row| {
+                    Luma([worst(&rgba.get_pixel(col, row).0)])
+                })
+            }
+        };
+        flip_color_map(&gray).into()
+    }
 }
 
 #[derive(Debug)]
@@ -94,3 +149,18 @@ pub struct Similarity {
     /// the average score of the image
     pub score: f64,
 }
+
+#[derive(Debug)]
+/// The result of comparing multichannel images channel by channel
+pub struct ChannelSimilarity {
+    /// The combined score of all channels:
+    /// - MSE is calculated over the samples of all channels, PSNR from this combined MSE
+    /// - MAE is calculated over the samples of all channels
+    /// - The peak absolute error is the largest of all channels
+    /// - Pixels over tolerance count a pixel if any of its channels exceeds the tolerance,
+    ///   for planes of different dimensions every sample over the tolerance counts instead
+    /// - For all other algorithms the worst channel score is used
+    pub score: f64,
+    /// The results of the individual channels in channel order
+    pub channels: Vec<Similarity>,
+}
@@ -0,0 +1,226 @@
+use crate::prelude::*;
+use crate::utils::{
+    gabor_subband, to_float_image, Complex, GrayBuffer, Subpixel, SummedAreaTable, Window,
+};
+use rayon::prelude::*;
+use std::f64::consts::PI;
+
+const DEFAULT_ORIENTATIONS: u32 = 4;
+const DEFAULT_SCALES: u32 = 2;
+const DEFAULT_WINDOW_SIZE: u32 = 7;
+const DEFAULT_K: f64 = 0.01;
+/// Standard deviation of the gaussian envelope of the finest filters in pixels
+const FINEST_SIGMA: f64 = 2.;
+/// Wavelength of the finest filters in pixels
+const FINEST_WAVELENGTH: f64 = 4.;
+
+/// Parameters for complex wavelet SSIM, see [`Algorithm::CWSSIM`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CwSsimConfig {
+    /// Number of filter orientations per scale, evenly spread over 180°, 4 by default
+    pub orientations: u32,
+    /// Number of scales, every scale doubles wavelength and envelope of the filters, 2 by default
+    pub scales: u32,
+    /// Edge length of the square window the coefficients are correlated over, 7 by default
+    pub window_size: u32,
+    /// Stabilization constant relative to the dynamic range of the pixel type, 0.01 by default - needs to be positive and finite
+    pub k: f64,
+}
+
+impl Default for CwSsimConfig {
+    fn default() -> Self {
+        CwSsimConfig {
+            orientations: DEFAULT_ORIENTATIONS,
+            scales: DEFAULT_SCALES,
+            window_size: DEFAULT_WINDOW_SIZE,
+            k: DEFAULT_K,
+        }
+    }
+}
+
+impl CwSsimConfig {
+    fn validate(&self) -> Result<(), CompareError> {
+        if self.orientations > 0
+            && self.scales > 0
+            && self.window_size > 0
+            && self.k > 0.
+            && self.k.is_finite()
+        {
+            Ok(())
+        } else {
+            Err(CompareError::CalculationFailed(format!(
+                "Invalid CW-SSIM configuration: {self:?} - orientations, scales, window size and k need to be positive, k finite"
+            )))
+        }
+    }
+}
+
+/// Complex coefficients of the oriented subband at the given scale
+fn subband(
+    image: &[Complex],
+    dimensions: (usize, usize),
+    scale: u32,
+    orientation: f64,
+) -> Vec<Complex> {
+    let factor = (1 << scale) as f64;
+    gabor_subband(
+        image,
+        dimensions,
+        FINEST_SIGMA * factor,
+        FINEST_WAVELENGTH * factor,
+        orientation,
+    )
+}
+
+/// CW-SSIM as proposed by Wang and Simoncelli: the images are decomposed into oriented complex subbands
+/// and for every subband the coefficients are compared within a window around each pixel:
+/// `(2|sum(c_x * conj(c_y))| + K) / (sum(|c_x|^2) + sum(|c_y|^2) + K)`.
+/// Small translations only rotate the phase of the coefficients consistently, which this index ignores.
+/// The map holds the mean over all subbands per pixel, the score is the mean of the map.
+pub(crate) fn cw_ssim<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+    config: &CwSsimConfig,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    config.validate()?;
+    let (width, height) = first.dimensions();
+    let dimensions = (width as usize, height as usize);
+    let to_complex = |image: &GrayBuffer<P>| {
+        to_float_image(image)
+            .pixels()
+            .map(|p| Complex { re: p[0], im: 0. })
+            .collect::<Vec<_>>()
+    };
+    let (first, second) = (to_complex(first), to_complex(second));
+    let bounds = Window::new((0, 0), (width - 1, height - 1));
+    let stabilizer = (config.k * P::RANGE).powi(2);
+
+    let mut map = vec![0.; dimensions.0 * dimensions.1];
+    for scale in 0..config.scales {
+        for orientation in 0..config.orientations {
+            let orientation = PI * orientation as f64 / config.orientations as f64;
+            let x = subband(&first, dimensions, scale, orientation);
+            let y = subband(&second, dimensions, scale, orientation);
+            let index = |col: u32, row: u32| row as usize * dimensions.0 + col as usize;
+            let correlation = |col, row| x[index(col, row)].mul(y[index(col, row)].conj());
+            let correlation_re =
+                SummedAreaTable::from_fn(width, height, |c, r| correlation(c, r).re);
+            let correlation_im =
+                SummedAreaTable::from_fn(width, height, |c, r| correlation(c, r).im);
+            let energy_x =
+                SummedAreaTable::from_fn(width, height, |c, r| x[index(c, r)].norm_sqr());
+            let energy_y =
+                SummedAreaTable::from_fn(width, height, |c, r| y[index(c, r)].norm_sqr());
+
+            map.par_chunks_mut(dimensions.0)
+                .enumerate()
+                .for_each(|(row, output)| {
+                    output.iter_mut().enumerate().for_each(|(col, out)| {
+                        let window =
+                            Window::around((col as u32, row as u32), config.window_size, &bounds);
+                        let k = stabilizer * window.area() as f64;
+                        let correlation = correlation_re
+                            .sum(&window)
+                            .hypot(correlation_im.sum(&window));
+                        let energy = energy_x.sum(&window) + energy_y.sum(&window);
+                        *out += (2. * correlation + k) / (energy + k);
+                    })
+                });
+        }
+    }
+
+    let subbands = (config.scales * config.orientations) as f64;
+    let mut image = GraySimilarityImage::new(width, height);
+    image
+        .pixels_mut()
+        .zip(&map)
+        .for_each(|(p, m)| *p = Luma([(m / subbands) as f32]));
+    let score = map.iter().sum::<f64>() / subbands / map.len() as f64;
+    Ok((score, image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssim::ssim;
+    use crate::SsimConfig;
+
+    fn pattern(offset: f64) -> GrayImage {
+        let mut image = GrayImage::new(48, 48);
+        image.enumerate_pixels_mut().for_each(|(col, row, p)| {
+            let (x, y) = (col as f64 + offset, row as f64);
+            let value =
+                128. + 60. * (x / 3.).sin() + 40. * (y / 5.).cos() + 20. * ((x + y) / 4.).sin();
+            *p = Luma([value.round() as u8]);
+        });
+        image
+    }
+
+    #[test]
+    fn identity() {
+        let image = pattern(0.);
+        let (score, map) = cw_ssim(&image, &image, &CwSsimConfig::default()).unwrap();
+        assert!((score - 1.).abs() < 1e-9);
+        assert!(map.pixels().all(|p| (p[0] - 1.).abs() < 1e-5));
+    }
+
+    #[test]
+    fn flat_images_are_similar() {
+        let first = GrayImage::from_pixel(16, 16, Luma([10]));
+        let second = GrayImage::from_pixel(16, 16, Luma([200]));
+        let (score, _) = cw_ssim(&first, &second, &CwSsimConfig::default()).unwrap();
+        assert!((score - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn tolerates_small_translations() {
+        let first = pattern(0.);
+        let shifted = pattern(0.7);
+        let (cw_score, _) = cw_ssim(&first, &shifted, &CwSsimConfig::default()).unwrap();
+        let (ssim_score, _) = ssim(&first, &shifted, &SsimConfig::default()).unwrap();
+        assert!(cw_score > 0.9);
+        assert!(cw_score > ssim_score);
+    }
+
+    #[test]
+    fn detects_structural_changes() {
+        let first = pattern(0.);
+        let mut second = first.clone();
+        for col in 16..32 {
+            for row in 16..32 {
+                second.put_pixel(col, row, Luma([255 - first.get_pixel(col, row)[0]]));
+            }
+        }
+        let (score, map) = cw_ssim(&first, &second, &CwSsimConfig::default()).unwrap();
+        assert!(score < 0.9);
+        assert!(map.get_pixel(24, 24)[0] < map.get_pixel(2, 2)[0]);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let image = GrayImage::new(8, 8);
+        let config = CwSsimConfig {
+            orientations: 0,
+            ..Default::default()
+        };
+        assert!(cw_ssim(&image, &image, &config).is_err());
+        for k in [0., -0.01, f64::NAN, f64::INFINITY] {
+            let config = CwSsimConfig {
+                k,
+                ..Default::default()
+            };
+            assert!(cw_ssim(&image, &image, &config).is_err(), "{k}");
+        }
+    }
+
+    #[test]
+    fn zero_k_is_rejected_for_flat_images() {
+        // flat subbands would divide 0 by 0 without the stabilization constant
+        let image = GrayImage::from_pixel(16, 16, Luma([100]));
+        let config = CwSsimConfig {
+            k: 0.,
+            ..Default::default()
+        };
+        assert!(cw_ssim(&image, &image, &config).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use crate::prelude::*;
+use crate::utils::rgb_to_lab;
+use itertools::izip;
+use std::fmt::{Debug, Formatter};
+
+/// The formula used to calculate the color difference ΔE of two colors in CIELAB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeltaE {
+    /// The euclidean distance in Lab, a just noticeable difference is about 2.3
+    CIE76,
+    /// Weights chroma and hue differences by the chroma of the first color, using the graphic arts constants
+    CIE94,
+    /// The current CIE standard with corrections for blue hues, neutral colors, lightness, chroma and hue
+    CIEDE2000,
+}
+
+/// The result of a ΔE comparison
+pub struct ColorDifference {
+    /// The raw ΔE per pixel - unlike for the other comparisons 0.0 means identical
+    pub image: GraySimilarityImage,
+    /// The mean ΔE of all pixels
+    pub mean: f64,
+    /// The largest ΔE of a single pixel
+    pub max: f64,
+    sorted: Vec<f32>,
+}
+
+impl Debug for ColorDifference {
+    /// Only the dimensions and summary statistics, the per pixel values would be megabytes for a screenshot
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ColorDifference")
+            .field("dimensions", &self.image.dimensions())
+            .field("mean", &self.mean)
+            .field("max", &self.max)
+            .field("median", &self.percentile(50.))
+            .field("percentile_95", &self.percentile(95.))
+            .field("percentile_99", &self.percentile(99.))
+            .finish_non_exhaustive()
+    }
+}
+
+impl ColorDifference {
+    /// The ΔE that `percentile` percent of the pixels do not exceed (nearest rank), e.g. 95.0 for the 95th percentile
+    pub fn percentile(&self, percentile: f64) -> f64 {
+        if self.sorted.is_empty() {
+            return 0.;
+        }
+        let rank = (percentile.clamp(0., 100.) / 100. * self.sorted.len() as f64).ceil() as usize;
+        self.sorted[rank.saturating_sub(1)] as f64
+    }
+}
+
+fn cie76(first: &[f64; 3], second: &[f64; 3]) -> f64 {
+    first
+        .iter()
+        .zip(second)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn cie94(first: &[f64; 3], second: &[f64; 3]) -> f64 {
+    const K1: f64 = 0.045;
+    const K2: f64 = 0.015;
+    let chroma_first = first[1].hypot(first[2]);
+    let chroma_second = second[1].hypot(second[2]);
+    let delta_l = first[0] - second[0];
+    let delta_c = chroma_first - chroma_second;
+    let delta_h_squared = ((first[1] - second[1]).powi(2) + (first[2] - second[2]).powi(2)
+        - delta_c * delta_c)
+        .max(0.);
+    let s_c = 1. + K1 * chroma_first;
+    let s_h = 1. + K2 * chroma_first;
+    (delta_l.powi(2) + (delta_c / s_c).powi(2) + delta_h_squared / (s_h * s_h)).sqrt()
+}
+
+/// see Sharma, Wu and Dalal: "The CIEDE2000 Color-Difference Formula: Implementation Notes,
+/// Supplementary Test Data, and Mathematical Observations"
+fn ciede2000(first: &[f64; 3], second: &[f64; 3]) -> f64 {
+    let [l1, a1, b1] = *first;
+    let [l2, a2, b2] = *second;
+    let chroma_mean = (a1.hypot(b1) + a2.hypot(b2)) / 2.;
+    let g = 0.5 * (1. - (chroma_mean.powi(7) / (chroma_mean.powi(7) + 25f64.powi(7))).sqrt());
+    let (a1, a2) = ((1. + g) * a1, (1. + g) * a2);
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+    let hue = |a: f64, b: f64| {
+        if a == 0. && b == 0. {
+            0.
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.)
+        }
+    };
+    let (h1, h2) = (hue(a1, b1), hue(a2, b2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0. {
+        0.
+    } else if (h2 - h1).abs() <= 180. {
+        h2 - h1
+    } else if h2 - h1 > 180. {
+        h2 - h1 - 360.
+    } else {
+        h2 - h1 + 360.
+    };
+    let delta_h = 2. * (c1 * c2).sqrt() * (delta_h / 2.).to_radians().sin();
+
+    let l_mean = (l1 + l2) / 2.;
+    let c_mean = (c1 + c2) / 2.;
+    let h_mean = if c1 * c2 == 0. {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180. {
+        (h1 + h2) / 2.
+    } else if h1 + h2 < 360. {
+        (h1 + h2 + 360.) / 2.
+    } else {
+        (h1 + h2 - 360.) / 2.
+    };
+
+    let t = 1. - 0.17 * (h_mean - 30.).to_radians().cos()
+        + 0.24 * (2. * h_mean).to_radians().cos()
+        + 0.32 * (3. * h_mean + 6.).to_radians().cos()
+        - 0.20 * (4. * h_mean - 63.).to_radians().cos();
+    let delta_theta = 30. * (-((h_mean - 275.) / 25.).powi(2)).exp();
+    let r_c = 2. * (c_mean.powi(7) / (c_mean.powi(7) + 25f64.powi(7))).sqrt();
+    let s_l = 1. + 0.015 * (l_mean - 50.).powi(2) / (20. + (l_mean - 50.).powi(2)).sqrt();
+    let s_c = 1. + 0.045 * c_mean;
+    let s_h = 1. + 0.015 * c_mean * t;
+    let r_t = -(2. * delta_theta).to_radians().sin() * r_c;
+
+    ((delta_l / s_l).powi(2)
+        + (delta_c / s_c).powi(2)
+        + (delta_h / s_h).powi(2)
+        + r_t * (delta_c / s_c) * (delta_h / s_h))
+        .sqrt()
+}
+
+impl DeltaE {
+    fn calculate(&self, first: &[f64; 3], second: &[f64; 3]) -> f64 {
+        match self {
+            DeltaE::CIE76 => cie76(first, second),
+            DeltaE::CIE94 => cie94(first, second),
+            DeltaE::CIEDE2000 => ciede2000(first, second),
+        }
+    }
+}
+
+pub(crate) fn color_difference(
+    formula: DeltaE,
+    first: &RgbImage,
+    second: &RgbImage,
+) -> Result<ColorDifference, CompareError> {
+    let mut image = GraySimilarityImage::new(first.width(), first.height());
+    let mut sum = 0.;
+    let mut max = 0f64;
+    izip!(image.pixels_mut(), first.pixels(), second.pixels()).for_each(|(d, a, b)| {
+        let delta = formula.calculate(&rgb_to_lab(&a.0), &rgb_to_lab(&b.0));
+        sum += delta;
+        max = max.max(delta);
+        *d = Luma([delta as f32]);
+    });
+    let mut sorted = image.pixels().map(|p| p[0]).collect::<Vec<_>>();
+    sorted.sort_unstable_by(f32::total_cmp);
+    Ok(ColorDifference {
+        mean: sum / sorted.len().max(1) as f64,
+        max,
+        image,
+        sorted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ciede2000_reference_data() {
+        // pairs from the test data by Sharma et al.
+        let pairs = [
+            ([50., 2.6772, -79.7751], [50., 0., -82.7485], 2.0425),
+            ([50., 0., 0.], [50., -1., 2.], 2.3669),
+            ([50., 2.5, 0.], [73., 25., -18.], 27.1492),
+            ([50., 2.5, 0.], [50., 0., -2.5], 4.3065),
+            (
+                [22.7233, 20.0904, -46.694],
+                [23.0331, 14.973, -42.5619],
+                2.0373,
+            ),
+        ];
+        for (first, second, expected) in pairs {
+            assert!((ciede2000(&first, &second) - expected).abs() < 1e-4);
+            assert!((ciede2000(&second, &first) - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn cie76_and_cie94() {
+        let first = [50., 10., 0.];
+        let second = [50., 0., 0.];
+        assert_eq!(cie76(&first, &second), 10.);
+        // a pure chroma difference is scaled by 1 + 0.045 * C1
+        assert!((cie94(&first, &second) - 10. / 1.45).abs() < 1e-12);
+        assert_eq!(cie94(&second, &second), 0.);
+    }
+
+    #[test]
+    fn statistics() {
+        let first = RgbImage::from_pixel(10, 1, Rgb([100, 100, 100]));
+        let mut second = first.clone();
+        second.put_pixel(9, 0, Rgb([100, 100, 200]));
+        let result = color_difference(DeltaE::CIE76, &first, &second).unwrap();
+        assert_eq!(result.image.get_pixel(0, 0)[0], 0.);
+        assert!(result.max > 10.);
+        assert!((result.mean - result.max / 10.).abs() < 1e-12);
+        assert_eq!(result.percentile(90.), 0.);
+        assert_eq!(result.percentile(100.), result.max as f32 as f64);
+    }
+
+    #[test]
+    fn debug_prints_only_statistics() {
+        let first = RgbImage::from_pixel(100, 100, Rgb([100, 100, 100]));
+        let result = color_difference(DeltaE::CIE76, &first, &first).unwrap();
+        assert_eq!(
+            format!("{result:?}"),
+            "ColorDifference { dimensions: (100, 100), mean: 0.0, max: 0.0, median: 0.0, \
+             percentile_95: 0.0, percentile_99: 0.0, .. }"
+        );
+    }
+}
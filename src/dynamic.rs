@@ -0,0 +1,215 @@
+use crate::prelude::*;
+use crate::{
+    gray_similarity_structure_masked, rgb_hybrid_compare_with_config,
+    rgba_hybrid_compare_with_config, HybridConfig,
+};
+use image::{ColorType, DynamicImage};
+use std::fmt::{Display, Formatter};
+
+/// Options for comparing [`DynamicImage`]s with [`crate::compare`]
+#[derive(Debug, Clone)]
+pub struct CompareOptions {
+    /// The structure comparison used for gray images, defaults to [`Algorithm::MSSIMSimple`]
+    pub gray: Algorithm,
+    /// The configuration of the hybrid comparison used for color images,
+    /// its [`HybridConfig::mask`] is applied to gray images as well
+    pub hybrid: HybridConfig,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        CompareOptions {
+            gray: Algorithm::MSSIMSimple,
+            hybrid: HybridConfig::default(),
+        }
+    }
+}
+
+/// The comparison that was used, ordered from the fewest to the most channels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ComparisonMode {
+    /// Both images were gray, see [`crate::gray_similarity_structure`]
+    GrayStructure,
+    /// At least one image had colors but neither had alpha, see [`crate::rgb_hybrid_compare`]
+    RgbHybrid,
+    /// At least one image had an alpha channel, see [`crate::rgba_hybrid_compare`]
+    RgbaHybrid,
+}
+
+/// The subpixel type the images were compared with, ordered from the smallest to the largest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SampleDepth {
+    /// 8 bit integers
+    Eight,
+    /// 16 bit integers
+    Sixteen,
+    /// 32 bit floats
+    Float,
+}
+
+/// The path [`crate::compare`] took for a pair of images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComparisonPath {
+    /// The comparison that was used
+    pub mode: ComparisonMode,
+    /// The subpixel type both images were converted to
+    pub depth: SampleDepth,
+    /// Whether the color types of the images differed, so the lower one was promoted
+    pub promoted: bool,
+}
+
+impl ComparisonPath {
+    fn of(color: ColorType) -> ComparisonPath {
+        let mode = if color.has_alpha() {
+            ComparisonMode::RgbaHybrid
+        } else if color.has_color() {
+            ComparisonMode::RgbHybrid
+        } else {
+            ComparisonMode::GrayStructure
+        };
+        let depth = match color.bytes_per_pixel() / color.channel_count() {
+            1 => SampleDepth::Eight,
+            2 => SampleDepth::Sixteen,
+            _ => SampleDepth::Float,
+        };
+        ComparisonPath {
+            mode,
+            depth,
+            promoted: false,
+        }
+    }
+
+    /// Gray images with alpha are compared as rgba, the mode and depth are the larger ones of both images
+    fn select(first: ColorType, second: ColorType) -> ComparisonPath {
+        let (first, second) = (ComparisonPath::of(first), ComparisonPath::of(second));
+        ComparisonPath {
+            mode: first.mode.max(second.mode),
+            depth: first.depth.max(second.depth),
+            promoted: first != second,
+        }
+    }
+}
+
+impl Display for ComparisonPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mode = match self.mode {
+            ComparisonMode::GrayStructure => "gray structure",
+            ComparisonMode::RgbHybrid => "rgb hybrid",
+            ComparisonMode::RgbaHybrid => "rgba hybrid",
+        };
+        let depth = match self.depth {
+            SampleDepth::Eight => "8 bit",
+            SampleDepth::Sixteen => "16 bit",
+            SampleDepth::Float => "float",
+        };
+        write!(f, "{mode}, {depth}")?;
+        if self.promoted {
+            write!(f, ", promoted")?;
+        }
+        Ok(())
+    }
+}
+
+/// The result of [`crate::compare`]
+#[derive(Debug)]
+pub struct DynamicSimilarity {
+    /// The result of the comparison
+    pub similarity: Similarity,
+    /// The comparison that was used
+    pub path: ComparisonPath,
+}
+
+pub(crate) fn compare(
+    first: &DynamicImage,
+    second: &DynamicImage,
+    options: &CompareOptions,
+) -> Result<DynamicSimilarity, CompareError> {
+    let path = ComparisonPath::select(first.color(), second.color());
+    let similarity = match (path.mode, path.depth) {
+        (ComparisonMode::GrayStructure, SampleDepth::Eight) => gray_similarity_structure_masked(
+            &options.gray,
+            &first.to_luma8(),
+            &second.to_luma8(),
+            &options.hybrid.mask,
+        ),
+        (ComparisonMode::GrayStructure, SampleDepth::Sixteen) => gray_similarity_structure_masked(
+            &options.gray,
+            &first.to_luma16(),
+            &second.to_luma16(),
+            &options.hybrid.mask,
+        ),
+        (ComparisonMode::GrayStructure, SampleDepth::Float) => gray_similarity_structure_masked(
+            &options.gray,
+            &first.to_luma32f(),
+            &second.to_luma32f(),
+            &options.hybrid.mask,
+        ),
+        (ComparisonMode::RgbHybrid, SampleDepth::Eight) => {
+            rgb_hybrid_compare_with_config(&first.to_rgb8(), &second.to_rgb8(), &options.hybrid)
+                .map(|result| result.similarity)
+        }
+        (ComparisonMode::RgbHybrid, SampleDepth::Sixteen) => {
+            rgb_hybrid_compare_with_config(&first.to_rgb16(), &second.to_rgb16(), &options.hybrid)
+                .map(|result| result.similarity)
+        }
+        (ComparisonMode::RgbHybrid, SampleDepth::Float) => {
+            rgb_hybrid_compare_with_config(&first.to_rgb32f(), &second.to_rgb32f(), &options.hybrid)
+                .map(|result| result.similarity)
+        }
+        (ComparisonMode::RgbaHybrid, SampleDepth::Eight) => {
+            rgba_hybrid_compare_with_config(&first.to_rgba8(), &second.to_rgba8(), &options.hybrid)
+                .map(|result| result.similarity)
+        }
+        (ComparisonMode::RgbaHybrid, SampleDepth::Sixteen) => rgba_hybrid_compare_with_config(
+            &first.to_rgba16(),
+            &second.to_rgba16(),
+            &options.hybrid,
+        )
+        .map(|result| result.similarity),
+        (ComparisonMode::RgbaHybrid, SampleDepth::Float) => rgba_hybrid_compare_with_config(
+            &first.to_rgba32f(),
+            &second.to_rgba32f(),
+            &options.hybrid,
+        )
+        .map(|result| result.similarity),
+    }?;
+    Ok(DynamicSimilarity { similarity, path })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_of_same_color_types() {
+        let path = ComparisonPath::select(ColorType::L8, ColorType::L8);
+        assert_eq!(path.mode, ComparisonMode::GrayStructure);
+        assert_eq!(path.depth, SampleDepth::Eight);
+        assert!(!path.promoted);
+        let path = ComparisonPath::select(ColorType::Rgb32F, ColorType::Rgb32F);
+        assert_eq!(path.mode, ComparisonMode::RgbHybrid);
+        assert_eq!(path.depth, SampleDepth::Float);
+        assert_eq!(path.to_string(), "rgb hybrid, float");
+    }
+
+    #[test]
+    fn path_promotes_lower_color_type() {
+        let path = ComparisonPath::select(ColorType::L16, ColorType::Rgb8);
+        assert_eq!(path.mode, ComparisonMode::RgbHybrid);
+        assert_eq!(path.depth, SampleDepth::Sixteen);
+        assert!(path.promoted);
+        // gray with alpha is compared as rgba
+        let path = ComparisonPath::select(ColorType::La8, ColorType::L8);
+        assert_eq!(path.mode, ComparisonMode::RgbaHybrid);
+        assert_eq!(path.to_string(), "rgba hybrid, 8 bit, promoted");
+    }
+
+    #[test]
+    fn compares_promoted_images() {
+        let gray = DynamicImage::ImageLuma8(GrayImage::from_pixel(8, 8, Luma([100])));
+        let rgb = DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 8, Rgb([100, 100, 100])));
+        let result = compare(&gray, &rgb, &CompareOptions::default()).unwrap();
+        assert_eq!(result.path.mode, ComparisonMode::RgbHybrid);
+        assert_eq!(result.similarity.score, 1.);
+    }
+}
@@ -0,0 +1,360 @@
+use crate::prelude::*;
+use crate::utils::{
+    convolve_separable, filter_separable, gaussian_kernel, linear_rgb_to_xyz, srgb_to_linear,
+    xyz_to_lab, xyz_to_linear_rgb, FloatImage, D65_WHITE,
+};
+use itertools::izip;
+use rayon::prelude::*;
+use std::f64::consts::PI;
+
+/// Exponent of the color difference
+const QC: f64 = 0.7;
+/// Exponent of the feature difference
+const QF: f64 = 0.5;
+/// Fraction of the maximum color difference that is mapped to `PT`
+const PC: f64 = 0.4;
+const PT: f64 = 0.95;
+/// Width of the edge and point detection filters in degrees of visual angle
+const FEATURE_WIDTH: f64 = 0.082;
+/// Pixels per degree of a 0.7 m wide 4K monitor watched from 0.7 m
+const DEFAULT_PIXELS_PER_DEGREE: f64 = 67.02064327658226;
+
+/// Parameters for the FLIP comparison, see [`crate::rgb_similarity_flip`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlipConfig {
+    /// Pixels per degree of visual angle at the assumed viewing conditions:
+    /// `distance * horizontal_resolution / monitor_width * PI / 180`.
+    /// By default 67.02 for a 0.7 m wide 4K monitor watched from 0.7 m
+    pub pixels_per_degree: f64,
+}
+
+impl Default for FlipConfig {
+    fn default() -> Self {
+        FlipConfig {
+            pixels_per_degree: DEFAULT_PIXELS_PER_DEGREE,
+        }
+    }
+}
+
+impl FlipConfig {
+    fn validate(&self) -> Result<(), CompareError> {
+        if self.pixels_per_degree > 0. {
+            Ok(())
+        } else {
+            Err(CompareError::CalculationFailed(format!(
+                "Invalid FLIP configuration: {self:?} - pixels per degree need to be positive"
+            )))
+        }
+    }
+}
+
+/// Linearized CIELAB: Y, Cx (red-green) and Cz (blue-yellow) opponent channels
+fn xyz_to_ycxcz(xyz: [f64; 3]) -> [f64; 3] {
+    let [x, y, z] = [0, 1, 2].map(|i| xyz[i] / D65_WHITE[i]);
+    [116. * y - 16., 500. * (x - y), 200. * (y - z)]
+}
+
+fn ycxcz_to_xyz([y, cx, cz]: [f64; 3]) -> [f64; 3] {
+    let y = (y + 16.) / 116.;
+    [
+        (cx / 500. + y) * D65_WHITE[0],
+        y * D65_WHITE[1],
+        (y - cz / 200.) * D65_WHITE[2],
+    ]
+}
+
+/// CIELAB with the chroma scaled by the lightness, modeling the Hunt effect
+fn hunt_adjusted_lab(linear_rgb: [f64; 3]) -> [f64; 3] {
+    let [l, a, b] = xyz_to_lab(linear_rgb_to_xyz(linear_rgb));
+    [l, 0.01 * l * a, 0.01 * l * b]
+}
+
+/// The HyAB distance: city block distance in lightness, euclidean distance in chroma
+fn hyab(first: &[f64; 3], second: &[f64; 3]) -> f64 {
+    (first[0] - second[0]).abs() + (first[1] - second[1]).hypot(first[2] - second[2])
+}
+
+/// One term `a * sqrt(PI / b) * exp(-PI^2 * x^2 / b)` of a contrast sensitivity function, `x` in degrees
+struct CsfTerm {
+    a: f64,
+    b: f64,
+}
+
+const CSF_ACHROMATIC: [CsfTerm; 1] = [CsfTerm { a: 1., b: 0.0047 }];
+const CSF_RED_GREEN: [CsfTerm; 1] = [CsfTerm { a: 1., b: 0.0053 }];
+const CSF_BLUE_YELLOW: [CsfTerm; 2] = [CsfTerm { a: 34.1, b: 0.04 }, CsfTerm { a: 13.5, b: 0.025 }];
+
+/// Filters the channel with the normalized sum of gaussian CSF terms. Each term is separable,
+/// so the channel is filtered per term and the results are weighted by the share of the term in the 2D kernel.
+/// The radius covers three standard deviations of the widest filter of all channels, like in the reference.
+fn csf_filter(channel: &FloatImage, terms: &[CsfTerm], pixels_per_degree: f64) -> FloatImage {
+    let widest = CSF_BLUE_YELLOW[0].b;
+    let radius = (3. * (widest / (2. * PI * PI)).sqrt() * pixels_per_degree).ceil() as u32;
+    let weighted = terms
+        .iter()
+        .map(|term| {
+            let sigma = pixels_per_degree * (term.b / (2. * PI * PI)).sqrt();
+            let sum = (-(radius as i64)..=radius as i64)
+                .map(|x| (-((x * x) as f64) / (2. * sigma * sigma)).exp())
+                .sum::<f64>();
+            let weight = term.a * (PI / term.b).sqrt() * sum * sum;
+            (
+                weight,
+                filter_separable(channel, &gaussian_kernel(sigma, radius)),
+            )
+        })
+        .collect::<Vec<_>>();
+    let total = weighted.iter().map(|(weight, _)| weight).sum::<f64>();
+    let mut result = FloatImage::new(channel.width(), channel.height());
+    for (weight, filtered) in weighted {
+        result
+            .pixels_mut()
+            .zip(filtered.pixels())
+            .for_each(|(r, f)| r[0] += weight / total * f[0]);
+    }
+    result
+}
+
+/// Normalizes positive and negative weights separately to sum up to 1 and -1
+fn normalize_signed(kernel: Vec<f64>) -> Vec<f64> {
+    let positive = kernel.iter().filter(|k| **k > 0.).sum::<f64>();
+    let negative = -kernel.iter().filter(|k| **k < 0.).sum::<f64>();
+    kernel
+        .into_iter()
+        .map(|k| if k < 0. { k / negative } else { k / positive })
+        .collect()
+}
+
+/// Magnitude of the edge (first derivative of gaussian) and point (second derivative) responses.
+/// The 2D kernels of the reference are separable into the derivative across and a gaussian along the direction.
+fn features(luminance: &FloatImage, pixels_per_degree: f64) -> (FloatImage, FloatImage) {
+    let sigma = 0.5 * FEATURE_WIDTH * pixels_per_degree;
+    let radius = (3. * sigma).ceil() as i64;
+    let envelope = |x: i64| (-((x * x) as f64) / (2. * sigma * sigma)).exp();
+    let gaussian = gaussian_kernel(sigma, radius as u32);
+    let edge = normalize_signed(
+        (-radius..=radius)
+            .map(|x| -x as f64 * envelope(x))
+            .collect(),
+    );
+    let point = normalize_signed(
+        (-radius..=radius)
+            .map(|x| ((x * x) as f64 / (sigma * sigma) - 1.) * envelope(x))
+            .collect(),
+    );
+    let magnitude = |kernel: &[f64]| {
+        let horizontal = convolve_separable(luminance, kernel, &gaussian);
+        let vertical = convolve_separable(luminance, &gaussian, kernel);
+        let mut result = FloatImage::new(luminance.width(), luminance.height());
+        izip!(result.pixels_mut(), horizontal.pixels(), vertical.pixels())
+            .for_each(|(r, h, v)| *r = Luma([h[0].hypot(v[0])]));
+        result
+    };
+    (magnitude(&edge), magnitude(&point))
+}
+
+struct Prepared {
+    /// Hunt adjusted CIELAB of the CSF filtered image
+    lab: Vec<[f64; 3]>,
+    /// Edge and point feature magnitudes of the unfiltered luminance
+    edges: FloatImage,
+    points: FloatImage,
+}
+
+fn prepare(image: &RgbImage, pixels_per_degree: f64) -> Prepared {
+    let (width, height) = image.dimensions();
+    let mut channels = [0, 1, 2].map(|_| FloatImage::new(width, height));
+    for (col, row, pixel) in image.enumerate_pixels() {
+        let ycxcz = xyz_to_ycxcz(linear_rgb_to_xyz(pixel.0.map(srgb_to_linear)));
+        for (channel, value) in channels.iter_mut().zip(ycxcz) {
+            channel.put_pixel(col, row, Luma([value]));
+        }
+    }
+    let luminance = FloatImage::from_fn(width, height, |col, row| {
+        Luma([(channels[0].get_pixel(col, row)[0] + 16.) / 116.])
+    });
+    let [y, cx, cz] = &channels;
+    let filtered = [
+        csf_filter(y, &CSF_ACHROMATIC, pixels_per_degree),
+        csf_filter(cx, &CSF_RED_GREEN, pixels_per_degree),
+        csf_filter(cz, &CSF_BLUE_YELLOW, pixels_per_degree),
+    ];
+    let lab = izip!(
+        filtered[0].pixels(),
+        filtered[1].pixels(),
+        filtered[2].pixels()
+    )
+    .collect::<Vec<_>>()
+    .par_iter()
+    .map(|(y, cx, cz)| {
+        let rgb = xyz_to_linear_rgb(ycxcz_to_xyz([y[0], cx[0], cz[0]]));
+        hunt_adjusted_lab(rgb.map(|c| c.clamp(0., 1.)))
+    })
+    .collect();
+    let (edges, points) = features(&luminance, pixels_per_degree);
+    Prepared { lab, edges, points }
+}
+
+/// LDR-FLIP by Andersson et al.: the images are filtered with contrast sensitivity functions in the opponent
+/// color space YCxCz and compared by the HyAB distance of the hunt adjusted CIELAB colors, which is remapped
+/// so that 95% of the error range cover the differences up to 40% of the maximum (green vs. blue).
+/// Differences in edges and points of the luminance amplify this error: `error = color^(1 - feature)`.
+/// The map contains `1 - error`, the score is `1 - mean(error)` so identical images score 1 like for all other algorithms.
+pub(crate) fn flip(
+    first: &RgbImage,
+    second: &RgbImage,
+    config: &FlipConfig,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    config.validate()?;
+    let first = prepare(first, config.pixels_per_degree);
+    let second = prepare(second, config.pixels_per_degree);
+
+    let c_max = hyab(
+        &hunt_adjusted_lab([0., 1., 0.]),
+        &hunt_adjusted_lab([0., 0., 1.]),
+    )
+    .powf(QC);
+    let pc_max = PC * c_max;
+
+    let mut map = GraySimilarityImage::new(first.edges.width(), first.edges.height());
+    let mut error_sum = 0.;
+    izip!(
+        map.pixels_mut(),
+        &first.lab,
+        &second.lab,
+        izip!(first.edges.pixels(), second.edges.pixels()),
+        izip!(first.points.pixels(), second.points.pixels())
+    )
+    .for_each(|(m, lab_x, lab_y, (edge_x, edge_y), (point_x, point_y))| {
+        let color = hyab(lab_x, lab_y).powf(QC);
+        let color = if color < pc_max {
+            color * PT / pc_max
+        } else {
+            PT + (color - pc_max) / (c_max - pc_max) * (1. - PT)
+        };
+        let edge = (edge_x[0] - edge_y[0]).abs();
+        let point = (point_x[0] - point_y[0]).abs();
+        let feature = (edge.max(point) / 2f64.sqrt()).powf(QF);
+        let error = color.powf(1. - feature);
+        error_sum += error;
+        *m = Luma([(1. - error) as f32]);
+    });
+
+    let score = 1. - error_sum / first.lab.len() as f64;
+    Ok((score, map))
+}
+
+/// Polynomial approximation of the magma colormap
+fn magma(t: f64) -> [f64; 3] {
+    const COEFFICIENTS: [[f64; 3]; 7] = [
+        [
+            -0.002136485053939582,
+            -0.000749655052795221,
+            -0.005386127855323933,
+        ],
+        [0.2516605407371642, 0.6775232436837668, 2.494026599312351],
+        [8.353717279216625, -3.577719514958484, 0.3144679030132573],
+        [-27.66873308576866, 14.26473078096533, -13.64921318813922],
+        [52.17613981234068, -27.94360607168351, 12.94416944238394],
+        [-50.76852536473588, 29.04658282127291, 4.23415299384598],
+        [18.65570506591883, -11.48977351997711, -5.601961508734096],
+    ];
+    [0, 1, 2].map(|c| {
+        COEFFICIENTS
+            .iter()
+            .rev()
+            .fold(0., |sum, coefficients| sum * t + coefficients[c])
+    })
+}
+
+/// Renders the errors `1 - similarity` using the magma colormap like the reference
+pub(crate) fn flip_color_map(map: &GraySimilarityImage) -> RgbImage {
+    let mut image = RgbImage::new(map.width(), map.height());
+    image.pixels_mut().zip(map.pixels()).for_each(|(c, m)| {
+        let error = (1. - m[0] as f64).clamp(0., 1.);
+        *c = Rgb(magma(error).map(|v| (v.clamp(0., 1.) * 255.).round() as u8));
+    });
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern() -> RgbImage {
+        RgbImage::from_fn(32, 32, |col, row| {
+            if (col / 8 + row / 8) % 2 == 0 {
+                Rgb([200, 40, 40])
+            } else {
+                Rgb([30, 90, 220])
+            }
+        })
+    }
+
+    #[test]
+    fn identity() {
+        let image = pattern();
+        let (score, map) = flip(&image, &image, &FlipConfig::default()).unwrap();
+        assert_eq!(score, 1.);
+        assert!(map.pixels().all(|p| p[0] == 1.));
+    }
+
+    #[test]
+    fn ycxcz_round_trip() {
+        let xyz = linear_rgb_to_xyz([0.2, 0.5, 0.9]);
+        let back = ycxcz_to_xyz(xyz_to_ycxcz(xyz));
+        xyz.iter()
+            .zip(back)
+            .for_each(|(a, b)| assert!((a - b).abs() < 1e-12));
+        let rgb = xyz_to_linear_rgb(xyz);
+        assert!((rgb[0] - 0.2).abs() < 1e-6);
+        assert!((rgb[2] - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn feature_kernels_are_normalized() {
+        let kernel = normalize_signed(vec![-2., -1., 0., 3., 1.]);
+        assert_eq!(kernel, vec![-2. / 3., -1. / 3., 0., 0.75, 0.25]);
+    }
+
+    #[test]
+    fn csf_keeps_flat_channels() {
+        let channel = FloatImage::from_pixel(24, 24, Luma([42.]));
+        let filtered = csf_filter(&channel, &CSF_BLUE_YELLOW, DEFAULT_PIXELS_PER_DEGREE);
+        assert!(filtered.pixels().all(|p| (p[0] - 42.).abs() < 1e-9));
+    }
+
+    #[test]
+    fn differences_are_detected() {
+        let first = pattern();
+        let mut second = first.clone();
+        for col in 20..28 {
+            for row in 4..12 {
+                second.put_pixel(col, row, Rgb([250, 250, 250]));
+            }
+        }
+        let (score, map) = flip(&first, &second, &FlipConfig::default()).unwrap();
+        assert!(score < 1.);
+        assert!(map.get_pixel(24, 8)[0] < 0.5);
+        assert!(map.get_pixel(24, 8)[0] < map.get_pixel(4, 28)[0]);
+        assert!(map.pixels().all(|p| (0. ..=1.).contains(&p[0])));
+    }
+
+    #[test]
+    fn color_map() {
+        let map = GraySimilarityImage::from_fn(2, 1, |col, _| Luma([col as f32]));
+        let colors = flip_color_map(&map);
+        assert_eq!(colors.get_pixel(1, 0), &Rgb([0, 0, 0]));
+        let error = colors.get_pixel(0, 0);
+        assert!(error[0] > 240 && error[2] > 170);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let image = pattern();
+        let config = FlipConfig {
+            pixels_per_degree: 0.,
+        };
+        assert!(flip(&image, &image, &config).is_err());
+    }
+}
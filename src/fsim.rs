@@ -0,0 +1,567 @@
+use crate::gmsd::SCHARR;
+use crate::prelude::*;
+use crate::utils::{
+    downsample, fft2, to_8_bit_float_image, Complex, FloatImage, GrayBuffer, Subpixel,
+};
+use itertools::izip;
+use std::f64::consts::PI;
+
+/// The parameters of Kovesi's `phasecong2` as used by the reference implementation
+const SCALES: usize = 4;
+const ORIENTATIONS: usize = 4;
+/// Wavelength of the finest log-gabor filter in pixels
+const MIN_WAVELENGTH: f64 = 6.;
+/// Factor between the wavelengths of successive scales
+const MULT: f64 = 2.;
+/// Ratio of the standard deviation of the log-gabor gaussian on the log frequency axis to the center frequency
+const SIGMA_ON_F: f64 = 0.55;
+/// Ratio of the angle between the orientations to the standard deviation of the angular gaussian
+const D_THETA_ON_SIGMA: f64 = 1.2;
+/// Number of standard deviations of the noise energy above its mean that are rejected
+const NOISE_K: f64 = 2.;
+/// Kovesi's empirical rescaling of the noise threshold for this phase congruency measure
+const NOISE_RESCALE: f64 = 1.7;
+const EPSILON: f64 = 1e-4;
+/// Cutoff frequency and order of the butterworth low-pass applied to all filters
+const LOW_PASS_CUTOFF: f64 = 0.45;
+const LOW_PASS_ORDER: i32 = 15;
+/// Stabilization constants of the reference implementation for 8 bit images, other images are rescaled to this range
+const T1: f64 = 0.85;
+const T2: f64 = 160.;
+const T3: f64 = 200.;
+const LAMBDA: f64 = 0.03;
+
+fn similarity(x: f64, y: f64, t: f64) -> f64 {
+    (2. * x * y + t) / (x * x + y * y + t)
+}
+
+/// The median like MATLAB computes it: the mean of both middle values for an even count
+fn median(mut values: Vec<f64>) -> f64 {
+    let count = values.len();
+    let (lower, upper, _) = values.select_nth_unstable_by(count / 2, f64::total_cmp);
+    if count % 2 == 1 {
+        *upper
+    } else {
+        (lower.iter().copied().fold(f64::MIN, f64::max) + *upper) / 2.
+    }
+}
+
+/// The frequencies of the DFT coefficients in FFT order, normalized to +-0.5 like in the reference
+fn frequencies(len: usize) -> Vec<f64> {
+    let norm = if len % 2 == 1 { len - 1 } else { len }.max(1) as f64;
+    (0..len)
+        .map(|i| {
+            let frequency = if i <= (len - 1) / 2 {
+                i as f64
+            } else {
+                i as f64 - len as f64
+            };
+            frequency / norm
+        })
+        .collect()
+}
+
+/// The log-gabor filters of one orientation over all scales and the constants of their noise estimation
+struct Orientation {
+    /// The real filters in the frequency domain, finest scale first
+    filters: Vec<Vec<f64>>,
+    /// The energy of the finest filter, the median response of the finest scale is divided by it for the noise power
+    finest_energy: f64,
+    /// The expected squared noise energy per unit of noise power
+    noise_energy: f64,
+}
+
+/// The filter bank of Kovesi's `phasecong2` for one image size, built in the frequency domain
+struct LogGaborFilters {
+    dimensions: (usize, usize),
+    orientations: Vec<Orientation>,
+}
+
+impl LogGaborFilters {
+    fn new(dimensions: (usize, usize)) -> LogGaborFilters {
+        let (width, height) = dimensions;
+        let (xs, ys) = (frequencies(width), frequencies(height));
+        let points = ys
+            .iter()
+            .flat_map(|y| xs.iter().map(move |x| (*x, *y)))
+            .collect::<Vec<_>>();
+        let low_pass = points
+            .iter()
+            .map(|(x, y)| 1. / (1. + (x.hypot(*y) / LOW_PASS_CUTOFF).powi(2 * LOW_PASS_ORDER)))
+            .collect::<Vec<_>>();
+        let radial = (0..SCALES)
+            .map(|scale| {
+                let center_frequency = 1. / (MIN_WAVELENGTH * MULT.powi(scale as i32));
+                izip!(&points, &low_pass)
+                    .enumerate()
+                    .map(|(index, ((x, y), low_pass))| {
+                        if index == 0 {
+                            // no response to the mean
+                            return 0.;
+                        }
+                        let log_ratio = (x.hypot(*y) / center_frequency).ln();
+                        (-log_ratio.powi(2) / (2. * SIGMA_ON_F.ln().powi(2))).exp() * low_pass
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // the angle of every frequency, with the y axis pointing up
+        let angles = points
+            .iter()
+            .map(|(x, y)| (-y).atan2(*x))
+            .collect::<Vec<_>>();
+        let theta_sigma = PI / ORIENTATIONS as f64 / D_THETA_ON_SIGMA;
+        let negated = |index: usize| {
+            let (col, row) = (index % width, index / width);
+            ((height - row) % height) * width + (width - col) % width
+        };
+        let orientations = (0..ORIENTATIONS)
+            .map(|orientation| {
+                let angle = orientation as f64 * PI / ORIENTATIONS as f64;
+                let spread = angles.iter().map(|theta| {
+                    let sin_difference = theta.sin() * angle.cos() - theta.cos() * angle.sin();
+                    let cos_difference = theta.cos() * angle.cos() + theta.sin() * angle.sin();
+                    let distance = sin_difference.atan2(cos_difference).abs();
+                    (-distance.powi(2) / (2. * theta_sigma.powi(2))).exp()
+                });
+                let spread = spread.collect::<Vec<_>>();
+                let filters = radial
+                    .iter()
+                    .map(|radial| radial.iter().zip(&spread).map(|(r, s)| r * s).collect())
+                    .collect::<Vec<Vec<f64>>>();
+                // The reference sums products of the real parts of the spatial filters, scaled by sqrt(n).
+                // By Parseval's theorem these are the sums of products of the hermitian parts of the filters.
+                let hermitian = filters
+                    .iter()
+                    .map(|filter| {
+                        (0..filter.len())
+                            .map(|index| (filter[index] + filter[negated(index)]) / 2.)
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                let product_sum =
+                    |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+                let mut noise_energy = 0.;
+                for (i, first) in hermitian.iter().enumerate() {
+                    noise_energy += 2. * product_sum(first, first);
+                    for second in &hermitian[i + 1..] {
+                        noise_energy += 4. * product_sum(first, second);
+                    }
+                }
+                Orientation {
+                    finest_energy: filters[0].iter().map(|f| f * f).sum(),
+                    filters,
+                    noise_energy,
+                }
+            })
+            .collect();
+        LogGaborFilters {
+            dimensions,
+            orientations,
+        }
+    }
+
+    /// Phase congruency like `phasecong2` by Kovesi: the even and odd filter responses are summed over the scales
+    /// per orientation, the energy is the sum of the responses projected onto the direction of this mean phase
+    /// minus their deviation from it. The noise energy is estimated from the median squared response of the
+    /// finest scale and subtracted per orientation: `PC = sum_o max(E_o - T_o, 0) / sum_o sum_s |c_so|`.
+    /// Pixels without any response, like in flat images, have no phase congruency.
+    fn phase_congruency(&self, image: &FloatImage) -> FloatImage {
+        let mut spectrum = image
+            .pixels()
+            .map(|p| Complex { re: p[0], im: 0. })
+            .collect::<Vec<_>>();
+        fft2(&mut spectrum, self.dimensions, false);
+        let mut energy_sum = vec![0.; spectrum.len()];
+        let mut amplitude_sum = vec![0.; spectrum.len()];
+
+        for orientation in &self.orientations {
+            let responses = orientation
+                .filters
+                .iter()
+                .map(|filter| {
+                    let mut response = spectrum
+                        .iter()
+                        .zip(filter)
+                        .map(|(s, f)| s.scale(*f))
+                        .collect::<Vec<_>>();
+                    fft2(&mut response, self.dimensions, true);
+                    response
+                })
+                .collect::<Vec<_>>();
+
+            let median_energy = median(responses[0].iter().map(|r| r.norm_sqr()).collect());
+            let noise_power = -median_energy / 0.5f64.ln() / orientation.finest_energy;
+            // the noise energy follows a rayleigh distribution
+            let tau = (noise_power * orientation.noise_energy / 2.).sqrt();
+            let threshold = (tau * (PI / 2.).sqrt()
+                + NOISE_K * ((2. - PI / 2.) * tau * tau).sqrt())
+                / NOISE_RESCALE;
+
+            izip!(energy_sum.iter_mut(), amplitude_sum.iter_mut())
+                .enumerate()
+                .for_each(|(index, (energy_sum, amplitude_sum))| {
+                    let sum = responses
+                        .iter()
+                        .fold(Complex::default(), |sum, r| sum.add(r[index]));
+                    let mean = sum.scale(1. / (sum.norm_sqr().sqrt() + EPSILON));
+                    let energy = responses
+                        .iter()
+                        .map(|r| {
+                            let r = r[index];
+                            r.re * mean.re + r.im * mean.im
+                                - (r.re * mean.im - r.im * mean.re).abs()
+                        })
+                        .sum::<f64>();
+                    *energy_sum += (energy - threshold).max(0.);
+                    *amplitude_sum += responses
+                        .iter()
+                        .map(|r| r[index].norm_sqr().sqrt())
+                        .sum::<f64>();
+                });
+        }
+
+        let (width, height) = self.dimensions;
+        let mut result = FloatImage::new(width as u32, height as u32);
+        izip!(result.pixels_mut(), energy_sum, amplitude_sum).for_each(|(p, e, a)| {
+            *p = Luma([if a > 0. { e / a } else { 0. }]);
+        });
+        result
+    }
+}
+
+/// The reference averages blocks of this size before the calculation, which keeps the filters
+/// tuned to the usual viewing distance and the effort low
+fn downsample_factor((width, height): (u32, u32)) -> u32 {
+    ((width.min(height) as f64 / 256.).round() as u32).max(1)
+}
+
+/// The downsampling of the reference: a `factor` x `factor` box filter, convolved like MATLAB's `conv2(.., 'same')`
+/// with zeros outside of the image, and every `factor`th pixel of the result
+fn box_downsample(image: &FloatImage, factor: u32) -> FloatImage {
+    if factor == 1 {
+        return image.clone();
+    }
+    let (width, height) = image.dimensions();
+    let offset = factor / 2;
+    FloatImage::from_fn(
+        width.div_ceil(factor),
+        height.div_ceil(factor),
+        |col, row| {
+            let (last_col, last_row) = (col * factor + offset, row * factor + offset);
+            let sum = (0..factor)
+                .flat_map(|dy| (0..factor).map(move |dx| (dx, dy)))
+                .filter_map(|(dx, dy)| {
+                    let (x, y) = (last_col.checked_sub(dx)?, last_row.checked_sub(dy)?);
+                    (x < width && y < height).then(|| image.get_pixel(x, y)[0])
+                })
+                .sum::<f64>();
+            Luma([sum / (factor * factor) as f64])
+        },
+    )
+}
+
+/// Scharr gradient magnitude like the reference, with zeros outside of the image
+fn gradient_magnitude(image: &FloatImage) -> FloatImage {
+    let (width, height) = image.dimensions();
+    let pixel = |col: i64, row: i64| {
+        if (0..width as i64).contains(&col) && (0..height as i64).contains(&row) {
+            image.get_pixel(col as u32, row as u32)[0]
+        } else {
+            0.
+        }
+    };
+    FloatImage::from_fn(width, height, |col, row| {
+        let (col, row) = (col as i64, row as i64);
+        let (horizontal, vertical) =
+            SCHARR
+                .iter()
+                .zip(-1..=1)
+                .fold((0., 0.), |(h, v), (weight, offset)| {
+                    (
+                        h + weight * (pixel(col + 1, row + offset) - pixel(col - 1, row + offset)),
+                        v + weight * (pixel(col + offset, row + 1) - pixel(col + offset, row - 1)),
+                    )
+                });
+        Luma([horizontal.hypot(vertical)])
+    })
+}
+
+/// FSIM of the luma channels, with the chroma term of FSIMc multiplied into each pixel if given.
+/// The score is the mean of the map weighted by the maximum phase congruency of both images,
+/// images without any phase congruency fall back to the unweighted mean.
+/// The `shares` additionally weight every pixel, e.g. with its unmasked share.
+fn feature_similarity(
+    first: &FloatImage,
+    second: &FloatImage,
+    chroma: Option<&FloatImage>,
+    shares: Option<&FloatImage>,
+) -> (f64, FloatImage) {
+    let filters = LogGaborFilters::new((first.width() as usize, first.height() as usize));
+    let (pc_first, pc_second) = (
+        filters.phase_congruency(first),
+        filters.phase_congruency(second),
+    );
+    let (gradient_first, gradient_second) = (gradient_magnitude(first), gradient_magnitude(second));
+
+    let mut map = FloatImage::new(first.width(), first.height());
+    let mut weighted_sum = 0.;
+    let mut weight_sum = 0.;
+    let mut shared_sum = 0.;
+    let mut share_sum = 0.;
+    izip!(
+        map.enumerate_pixels_mut(),
+        pc_first.pixels(),
+        pc_second.pixels(),
+        gradient_first.pixels(),
+        gradient_second.pixels()
+    )
+    .for_each(|((col, row, m), pc_x, pc_y, g_x, g_y)| {
+        let mut local = similarity(pc_x[0], pc_y[0], T1) * similarity(g_x[0], g_y[0], T2);
+        if let Some(chroma) = chroma {
+            local *= chroma.get_pixel(col, row)[0];
+        }
+        let share = shares.map_or(1., |shares| shares.get_pixel(col, row)[0]);
+        let weight = pc_x[0].max(pc_y[0]) * share;
+        weighted_sum += local * weight;
+        weight_sum += weight;
+        shared_sum += local * share;
+        share_sum += share;
+        *m = Luma([local]);
+    });
+
+    let score = if weight_sum > 0. {
+        weighted_sum / weight_sum
+    } else {
+        shared_sum / share_sum
+    };
+    (score, map)
+}
+
+/// Blows the map of the downsampled images up to the original size again
+fn upscale(map: &FloatImage, factor: u32, (width, height): (u32, u32)) -> GraySimilarityImage {
+    GraySimilarityImage::from_fn(width, height, |col, row| {
+        Luma([map.get_pixel(col / factor, row / factor)[0] as f32])
+    })
+}
+
+/// FSIM by Zhang et al. following their reference implementation: phase congruency and gradient magnitude (scharr)
+/// similarities are multiplied per pixel and averaged weighted by the phase congruency.
+pub(crate) fn fsim<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    fsim_weighted(first, second, None)
+}
+
+/// [`fsim`] with the phase congruency weight of every pixel multiplied by `weights`, e.g. 0 for masked pixels
+pub(crate) fn fsim_weighted<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+    weights: Option<&FloatImage>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    let factor = downsample_factor(first.dimensions());
+    let (score, map) = feature_similarity(
+        &box_downsample(&to_8_bit_float_image(first), factor),
+        &box_downsample(&to_8_bit_float_image(second), factor),
+        None,
+        weights.map(|weights| downsample(weights, factor)).as_ref(),
+    );
+    Ok((score, upscale(&map, factor, first.dimensions())))
+}
+
+/// The YIQ decomposition of the reference
+fn split_to_yiq(image: &RgbImage) -> [FloatImage; 3] {
+    let channel = |[r, g, b]: [f64; 3]| {
+        FloatImage::from_fn(image.width(), image.height(), |col, row| {
+            let [red, green, blue] = image.get_pixel(col, row).0.map(f64::from);
+            Luma([r * red + g * green + b * blue])
+        })
+    };
+    [
+        channel([0.299, 0.587, 0.114]),
+        channel([0.596, -0.274, -0.322]),
+        channel([0.211, -0.523, 0.312]),
+    ]
+}
+
+/// FSIMc: FSIM on the Y channel of the YIQ decomposition with the I and Q similarities as additional chroma term
+/// `(S_I * S_Q)^0.03` - its real part for negative products, like in the reference
+pub(crate) fn fsimc(
+    first: &RgbImage,
+    second: &RgbImage,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    let factor = downsample_factor(first.dimensions());
+    let prepare = |image: &RgbImage| split_to_yiq(image).map(|c| box_downsample(&c, factor));
+    let [y_first, i_first, q_first] = prepare(first);
+    let [y_second, i_second, q_second] = prepare(second);
+    let mut chroma = FloatImage::new(y_first.width(), y_first.height());
+    izip!(
+        chroma.pixels_mut(),
+        i_first.pixels(),
+        i_second.pixels(),
+        q_first.pixels(),
+        q_second.pixels()
+    )
+    .for_each(|(c, i_x, i_y, q_x, q_y)| {
+        let product = similarity(i_x[0], i_y[0], T3) * similarity(q_x[0], q_y[0], T3);
+        let term = if product >= 0. {
+            product.powf(LAMBDA)
+        } else {
+            product.abs().powf(LAMBDA) * (LAMBDA * PI).cos()
+        };
+        *c = Luma([term]);
+    });
+    let (score, map) = feature_similarity(&y_first, &y_second, Some(&chroma), None);
+    Ok((score, upscale(&map, factor, first.dimensions())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{to_float_image, Decompose};
+
+    fn pattern() -> GrayImage {
+        GrayImage::from_fn(32, 32, |col, row| {
+            Luma([if (col / 8 + row / 8) % 2 == 0 {
+                40
+            } else {
+                200
+            }])
+        })
+    }
+
+    #[test]
+    fn phase_congruency_of_flat_image() {
+        let image = FloatImage::from_pixel(16, 16, Luma([100.]));
+        let pc = LogGaborFilters::new((16, 16)).phase_congruency(&image);
+        assert!(pc.pixels().all(|p| p[0] < 1e-6));
+    }
+
+    #[test]
+    fn phase_congruency_peaks_at_edges() {
+        let image = to_float_image(&GrayImage::from_fn(32, 32, |col, _| {
+            Luma([if col < 16 { 20 } else { 220 }])
+        }));
+        let pc = LogGaborFilters::new((32, 32)).phase_congruency(&image);
+        assert!(pc.pixels().all(|p| (0. ..=1.).contains(&p[0])));
+        assert!(pc.get_pixel(16, 16)[0] > 0.5);
+        assert!(pc.get_pixel(16, 16)[0] > pc.get_pixel(4, 16)[0]);
+    }
+
+    #[test]
+    fn identity() {
+        let image = pattern();
+        let (score, map) = fsim(&image, &image).unwrap();
+        assert_eq!(score, 1.);
+        assert!(map.pixels().all(|p| p[0] == 1.));
+    }
+
+    #[test]
+    fn flat_images() {
+        let first = GrayImage::from_pixel(8, 8, Luma([20]));
+        let second = GrayImage::from_pixel(8, 8, Luma([30]));
+        let (score, map) = fsim(&first, &second).unwrap();
+        // without phase congruency the plain mean is used, only the zero padded borders differ
+        assert!(score > 0.9 && score < 1., "{score}");
+        assert!((1..7).all(|col| (1..7).all(|row| map.get_pixel(col, row)[0] == 1.)));
+    }
+
+    #[test]
+    fn structural_change_lowers_score() {
+        let first = pattern();
+        let mut second = first.clone();
+        for col in 10..14 {
+            for row in 2..30 {
+                second.put_pixel(col, row, Luma([120]));
+            }
+        }
+        let (score, map) = fsim(&first, &second).unwrap();
+        assert!(score < 0.95);
+        assert!(map.get_pixel(12, 16)[0] < map.get_pixel(28, 28)[0]);
+    }
+
+    #[test]
+    fn ranks_distortion_strength() {
+        let first = pattern();
+        let scores = [10, 40, 80].map(|offset| {
+            let second = GrayImage::from_fn(32, 32, |col, row| {
+                let value = first.get_pixel(col, row)[0];
+                Luma([if (col + row) % 3 == 0 {
+                    value.saturating_add(offset)
+                } else {
+                    value.saturating_sub(offset)
+                }])
+            });
+            fsim(&first, &second).unwrap().0
+        });
+        assert!(scores[0] < 1.);
+        assert!(scores[0] > scores[1] && scores[1] > scores[2], "{scores:?}");
+    }
+
+    fn reference_gray(col: u32, row: u32) -> u8 {
+        (if (col / 6 + row / 5).is_multiple_of(2) {
+            60
+        } else {
+            180
+        }) + ((col * row) % 17) as u8
+    }
+
+    fn reference_color(col: u32, row: u32) -> Rgb<u8> {
+        Rgb([
+            ((col * 7 + row * 3) % 256) as u8,
+            reference_gray(col, row),
+            (255 - col * 5 - row * 2) as u8,
+        ])
+    }
+
+    #[test]
+    fn matches_reference_implementation() {
+        // The expected scores were computed by a line by line port of FeatureSIM.m
+        // (including phasecong2) by Zhang et al. to python, as MATLAB is not at hand.
+        let first = GrayImage::from_fn(37, 30, |col, row| Luma([reference_gray(col, row)]));
+        let second = GrayImage::from_fn(37, 30, |col, row| {
+            if (20..28).contains(&col) && (8..16).contains(&row) {
+                Luma([100])
+            } else {
+                let noise = ((col * 31 + row * 17) % 21) as i32 - 10;
+                Luma([(reference_gray(col, row) as i32 + noise).clamp(0, 255) as u8])
+            }
+        });
+        let (score, _) = fsim(&first, &second).unwrap();
+        assert!((score - 0.9504623074064891).abs() < 1e-10, "{score}");
+
+        let first = RgbImage::from_fn(37, 30, reference_color);
+        let second = RgbImage::from_fn(37, 30, |col, row| {
+            let Rgb([r, g, b]) = reference_color(col, row);
+            if col < 18 {
+                Rgb([b, g, r])
+            } else {
+                Rgb([r, g, b])
+            }
+        });
+        let (score, _) = fsimc(&first, &second).unwrap();
+        assert!((score - 0.974959554816162).abs() < 1e-10, "{score}");
+    }
+
+    #[test]
+    fn fsimc_detects_color_changes() {
+        let first = RgbImage::from_fn(32, 32, |col, _| {
+            if col < 16 {
+                Rgb([200, 30, 30])
+            } else {
+                Rgb([30, 30, 200])
+            }
+        });
+        let mut second = first.clone();
+        second
+            .pixels_mut()
+            .for_each(|p| *p = Rgb([p[2], p[1], p[0]]));
+        let (gray, _) = fsim(&first.split_to_yuv()[0], &second.split_to_yuv()[0]).unwrap();
+        let (color, _) = fsimc(&first, &second).unwrap();
+        let (identity, _) = fsimc(&first, &first).unwrap();
+        assert_eq!(identity, 1.);
+        assert!(color < gray);
+    }
+}
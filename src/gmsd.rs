@@ -0,0 +1,110 @@
+use crate::prelude::*;
+use crate::utils::{to_8_bit_float_image, to_similarity_image, FloatImage, GrayBuffer, Subpixel};
+use itertools::izip;
+
+/// Stabilization constant of the reference implementation for 8 bit images, other images are rescaled to this range
+const T: f64 = 170.;
+
+/// Weights of the prewitt operator across the derivative direction
+pub(crate) const PREWITT: [f64; 3] = [1. / 3., 1. / 3., 1. / 3.];
+/// Weights of the scharr operator across the derivative direction
+pub(crate) const SCHARR: [f64; 3] = [3. / 16., 10. / 16., 3. / 16.];
+
+/// Gradient magnitude using a 3x3 central difference operator with the given smoothing `weights`,
+/// borders are extended by repeating the outermost pixels
+pub(crate) fn gradient_magnitude(image: &FloatImage, weights: &[f64; 3]) -> FloatImage {
+    let (width, height) = image.dimensions();
+    let pixel = |col: i64, row: i64| {
+        image.get_pixel(
+            col.clamp(0, width as i64 - 1) as u32,
+            row.clamp(0, height as i64 - 1) as u32,
+        )[0]
+    };
+    FloatImage::from_fn(width, height, |col, row| {
+        let (col, row) = (col as i64, row as i64);
+        let (horizontal, vertical) =
+            weights
+                .iter()
+                .zip(-1..=1)
+                .fold((0., 0.), |(h, v), (weight, offset)| {
+                    (
+                        h + weight * (pixel(col + 1, row + offset) - pixel(col - 1, row + offset)),
+                        v + weight * (pixel(col + offset, row + 1) - pixel(col + offset, row - 1)),
+                    )
+                });
+        Luma([horizontal.hypot(vertical)])
+    })
+}
+
+/// Gradient magnitude similarity deviation by Xue et al.:
+/// the per pixel gradient magnitude similarity `(2 * m_x * m_y + T) / (m_x^2 + m_y^2 + T)` forms the map,
+/// its standard deviation is the GMSD. The score is `1 - GMSD` so identical images score 1 like for all other algorithms.
+pub(crate) fn gmsd<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    let first = gradient_magnitude(&to_8_bit_float_image(first), &PREWITT);
+    let second = gradient_magnitude(&to_8_bit_float_image(second), &PREWITT);
+    let mut map = FloatImage::new(first.width(), first.height());
+    izip!(map.pixels_mut(), first.pixels(), second.pixels()).for_each(|(m, x, y)| {
+        *m = Luma([(2. * x[0] * y[0] + T) / (x[0] * x[0] + y[0] * y[0] + T)]);
+    });
+
+    let count = map.pixels().len() as f64;
+    let mean = map.pixels().map(|p| p[0]).sum::<f64>() / count;
+    let deviation = (map.pixels().map(|p| (p[0] - mean).powi(2)).sum::<f64>() / count).sqrt();
+
+    Ok((1. - deviation, to_similarity_image(&map)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern() -> GrayImage {
+        GrayImage::from_fn(16, 16, |col, row| {
+            Luma([((col * 37 + row * 23) % 200) as u8])
+        })
+    }
+
+    #[test]
+    fn identity() {
+        let image = pattern();
+        let (score, map) = gmsd(&image, &image).unwrap();
+        assert_eq!(score, 1.);
+        assert!(map.pixels().all(|p| p[0] == 1.));
+    }
+
+    #[test]
+    fn brightness_offset_keeps_gradients() {
+        let first = pattern();
+        let mut second = first.clone();
+        second.pixels_mut().for_each(|p| p[0] += 50);
+        let (score, _) = gmsd(&first, &second).unwrap();
+        assert_eq!(score, 1.);
+    }
+
+    #[test]
+    fn gradient_of_step() {
+        let image = FloatImage::from_fn(4, 3, |col, _| Luma([if col < 2 { 0. } else { 90. }]));
+        let magnitude = gradient_magnitude(&image, &PREWITT);
+        assert_eq!(magnitude.get_pixel(0, 1)[0], 0.);
+        assert_eq!(magnitude.get_pixel(1, 1)[0], 90.);
+        assert_eq!(magnitude.get_pixel(2, 1)[0], 90.);
+    }
+
+    #[test]
+    fn local_distortion_lowers_score() {
+        let first = pattern();
+        let mut second = first.clone();
+        for col in 4..8 {
+            for row in 4..8 {
+                second.put_pixel(col, row, Luma([0]));
+            }
+        }
+        let (score, map) = gmsd(&first, &second).unwrap();
+        assert!(score < 1.);
+        assert!(map.get_pixel(5, 5)[0] < 1.);
+        assert_eq!(map.get_pixel(14, 14)[0], 1.);
+    }
+}
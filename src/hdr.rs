@@ -0,0 +1,138 @@
+use crate::prelude::*;
+use crate::utils::linear_to_srgb;
+use image::Pixel;
+
+/// Parameters of the `banding_glare` variant of PU21
+const PU21: [f32; 7] = [
+    0.353_487_9,
+    0.373_465_87,
+    8.277_049e-5,
+    0.906_256_3,
+    0.091_503_03,
+    0.909_951_7,
+    596.314_8,
+];
+/// The luminance range in cd/m² PU21 is defined for
+const PU21_MIN_LUMINANCE: f32 = 0.005;
+const PU21_MAX_LUMINANCE: f32 = 10000.;
+
+/// How the values of float images are mapped to the range `[0, 1]` the comparisons expect for floats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatEncoding {
+    /// The values are divided by the dynamic range `range` - e.g. the peak value of an HDR frame - and clipped to `[0, 1]`
+    Linear {
+        /// The value that is mapped to 1.0
+        range: f32,
+    },
+    /// Reinhard tone mapping <img src="https://render.githubusercontent.com/render/math?math=\frac{x}{1%2Bx}"> of the values multiplied by `exposure`,
+    /// followed by the sRGB transfer function so the result compares like an 8 bit image would
+    Reinhard {
+        /// Factor applied to the linear values before tone mapping
+        exposure: f32,
+    },
+    /// The perceptually uniform PU21 encoding by Mantiuk and Azimi (`banding_glare` variant) of absolute luminance.
+    /// Luminance is clamped to 0.005 - 10000 cd/m², the encoded values are divided by the encoding of 10000 cd/m².
+    /// On this scale metrics like PSNR and SSIM behave for HDR as they do for gamma encoded SDR images.
+    PerceptuallyUniform {
+        /// The luminance in cd/m² that a value of 1.0 corresponds to, e.g. the peak luminance of the display
+        luminance: f32,
+    },
+}
+
+impl FloatEncoding {
+    fn validate(&self) -> Result<(), CompareError> {
+        let parameter = match self {
+            FloatEncoding::Linear { range } => range,
+            FloatEncoding::Reinhard { exposure } => exposure,
+            FloatEncoding::PerceptuallyUniform { luminance } => luminance,
+        };
+        if *parameter > 0. {
+            Ok(())
+        } else {
+            Err(CompareError::CalculationFailed(format!(
+                "Invalid float encoding: {self:?} - the parameter needs to be positive"
+            )))
+        }
+    }
+
+    fn encode(&self, value: f32) -> f32 {
+        match self {
+            FloatEncoding::Linear { range } => (value / range).clamp(0., 1.),
+            FloatEncoding::Reinhard { exposure } => {
+                let value = (value * exposure).max(0.);
+                linear_to_srgb(value / (1. + value))
+            }
+            FloatEncoding::PerceptuallyUniform { luminance } => {
+                pu21(value * luminance) / pu21(PU21_MAX_LUMINANCE)
+            }
+        }
+    }
+}
+
+/// see Mantiuk and Azimi: "PU21: A novel perceptually uniform encoding for adapting existing quality metrics for HDR"
+fn pu21(luminance: f32) -> f32 {
+    let [p0, p1, p2, p3, p4, p5, p6] = PU21;
+    let y = luminance
+        .clamp(PU21_MIN_LUMINANCE, PU21_MAX_LUMINANCE)
+        .powf(p3);
+    (p6 * (((p0 + p1 * y) / (1. + p2 * y)).powf(p4) - p5)).max(0.)
+}
+
+/// Encodes all color channels, alpha is kept as it is
+pub(crate) fn encode_float_image<P: Pixel<Subpixel = f32>>(
+    image: &ImageBuffer<P, Vec<f32>>,
+    encoding: FloatEncoding,
+) -> Result<ImageBuffer<P, Vec<f32>>, CompareError> {
+    encoding.validate()?;
+    let mut result = image.clone();
+    result
+        .pixels_mut()
+        .for_each(|p| *p = p.map_with_alpha(|c| encoding.encode(c), |a| a));
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn pu21_reference_values() {
+        // 100 cd/m² is mapped to about 256 like for 8 bit sRGB
+        assert!((pu21(100.) - 256.38).abs() < 0.01);
+        assert!((pu21(10000.) - 595.39).abs() < 0.01);
+        assert!(pu21(0.) < 1e-3);
+        assert_eq!(pu21(20000.), pu21(10000.));
+    }
+
+    #[test]
+    fn encodings_map_to_unit_range() {
+        let image =
+            ImageBuffer::<Rgba<f32>, Vec<f32>>::from_pixel(1, 1, Rgba([0., 0.5, 1000., 0.25]));
+        let linear = encode_float_image(&image, FloatEncoding::Linear { range: 2. }).unwrap();
+        assert_eq!(linear.get_pixel(0, 0).0, [0., 0.25, 1., 0.25]);
+        let reinhard =
+            encode_float_image(&image, FloatEncoding::Reinhard { exposure: 2. }).unwrap();
+        let pixel = reinhard.get_pixel(0, 0).0;
+        assert_eq!(pixel[0], 0.);
+        assert!((pixel[1] - linear_to_srgb(0.5)).abs() < 1e-6);
+        assert!(pixel[2] < 1. && pixel[2] > 0.99);
+        assert_eq!(pixel[3], 0.25);
+        let pu = encode_float_image(
+            &image,
+            FloatEncoding::PerceptuallyUniform { luminance: 10000. },
+        )
+        .unwrap();
+        let pixel = pu.get_pixel(0, 0).0;
+        assert!(pixel[0] < 1e-6);
+        assert!(pixel[1] < pixel[2]);
+        assert_eq!(pixel[2], 1.);
+    }
+
+    #[test]
+    fn invalid_encoding() {
+        let image = ImageBuffer::<Luma<f32>, Vec<f32>>::new(1, 1);
+        assert!(encode_float_image(&image, FloatEncoding::Linear { range: 0. }).is_err());
+        assert!(encode_float_image(&image, FloatEncoding::Reinhard { exposure: -1. }).is_err());
+    }
+}
@@ -1,15 +1,12 @@
 use crate::prelude::*;
-
-const BINS: u8 = u8::MAX;
+use crate::utils::{GrayBuffer, Subpixel};
 
 fn correlation(first_hist: &Histogram, second_hist: &Histogram) -> Option<f64> {
     let first_mean = first_hist.mean();
     let second_mean = second_hist.mean();
-    let numerator = (0..=BINS)
-        .map(|i| {
-            (first_hist.get_bin_content(i) - first_mean)
-                * (second_hist.get_bin_content(i) - second_mean)
-        })
+    let numerator = first_hist
+        .bins(second_hist)
+        .map(|(first, second)| (first - first_mean) * (second - second_mean))
         .sum::<f64>();
     let denominator = (first_hist.variance() * second_hist.variance()).sqrt();
 
@@ -21,10 +18,11 @@ fn correlation(first_hist: &Histogram, second_hist: &Histogram) -> Option<f64> {
 }
 
 fn chi_square(first_hist: &Histogram, second_hist: &Histogram) -> Option<f64> {
-    let score = (0..=BINS)
-        .map(|i| {
-            let num = (first_hist.get_bin_content(i) - second_hist.get_bin_content(i)).powi(2);
-            let den = first_hist.get_bin_content(i);
+    let score = first_hist
+        .bins(second_hist)
+        .map(|(first, second)| {
+            let num = (first - second).powi(2);
+            let den = first;
             if num == 0. {
                 0.
             } else {
@@ -41,18 +39,16 @@ fn chi_square(first_hist: &Histogram, second_hist: &Histogram) -> Option<f64> {
 }
 
 fn intersection(first_hist: &Histogram, second_hist: &Histogram) -> f64 {
-    (0..=BINS)
-        .map(|i| {
-            first_hist
-                .get_bin_content(i)
-                .min(second_hist.get_bin_content(i))
-        })
+    first_hist
+        .bins(second_hist)
+        .map(|(first, second)| first.min(second))
         .sum::<f64>()
 }
 
 fn hellinger(first_hist: &Histogram, second_hist: &Histogram) -> Option<f64> {
-    let bc = (0..=BINS)
-        .map(|i| (first_hist.get_bin_content(i) * second_hist.get_bin_content(i)).sqrt())
+    let bc = first_hist
+        .bins(second_hist)
+        .map(|(first, second)| (first * second).sqrt())
         .sum::<f64>();
     let normalization = (first_hist.integral() * second_hist.integral()).sqrt();
     if normalization == 0. {
@@ -76,13 +72,14 @@ pub enum Metric {
     Hellinger,
 }
 
-pub fn img_compare(
-    first: &GrayImage,
-    second: &GrayImage,
+pub fn img_compare<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
     metric: Metric,
+    excluded: Option<&[bool]>,
 ) -> Result<f64, CompareError> {
-    let first_hist = Histogram::from_gray_image(first);
-    let second_hist = Histogram::from_gray_image(second);
+    let first_hist = Histogram::from_gray_image(first, excluded);
+    let second_hist = Histogram::from_gray_image(second, excluded);
     let score = match metric {
         Metric::Correlation => correlation(&first_hist, &second_hist).ok_or_else(|| {
             CompareError::CalculationFailed(
@@ -110,13 +107,23 @@ struct Histogram {
 }
 
 impl Histogram {
-    pub fn get_bin_content(&self, bin: u8) -> f64 {
-        self.data[bin as usize]
+    /// The contents of the same bin of both histograms
+    pub fn bins<'a>(&'a self, other: &'a Histogram) -> impl Iterator<Item = (f64, f64)> + 'a {
+        self.data.iter().copied().zip(other.data.iter().copied())
     }
 
-    pub fn from_gray_image(image: &GrayImage) -> Histogram {
-        let mut data = vec![0.; 256];
-        image.pixels().for_each(|p| data[p[0] as usize] += 1.);
+    /// One bin per possible pixel value for integers, so 256 bins for 8 bit and 65536 bins for 16 bit images.
+    /// Float images are quantized to 256 bins. The `excluded` pixels are not counted.
+    pub fn from_gray_image<P: Subpixel>(
+        image: &GrayBuffer<P>,
+        excluded: Option<&[bool]>,
+    ) -> Histogram {
+        let mut data = vec![0.; P::BINS];
+        image
+            .pixels()
+            .enumerate()
+            .filter(|(index, _)| !excluded.is_some_and(|excluded| excluded[*index]))
+            .for_each(|(_, p)| data[p[0].bin()] += 1.);
         Histogram { data }
     }
 
@@ -211,9 +218,7 @@ mod tests {
         let first_vec = vec![1.; 256];
         let second_vec = vec![10.; 256];
         let mut third_vec = vec![0.; 256];
-        for i in 0..127 {
-            third_vec[i] = 100.;
-        }
+        third_vec[..127].fill(100.);
 
         let zeros = vec![0.; 256];
         let zeros = Histogram::from_vec(zeros).unwrap();
@@ -227,4 +232,23 @@ mod tests {
         assert_eq!(hellinger(&first, &third).unwrap(), 0.5437469730039513);
         assert!(hellinger(&first, &zeros).is_none());
     }
+
+    #[test]
+    fn histogram_of_16_bit_image() {
+        let image = GrayBuffer::<u16>::from_fn(4, 1, |col, _| Luma([col as u16 * 1000]));
+        let histogram = Histogram::from_gray_image(&image, None);
+        assert_eq!(histogram.data.len(), 65536);
+        assert_eq!(histogram.integral(), 4.);
+        assert_eq!(histogram.data[3000], 1.);
+        assert_eq!(intersection(&histogram, &histogram), 4.);
+    }
+
+    #[test]
+    fn histogram_without_excluded_pixels() {
+        let image = GrayImage::from_fn(4, 1, |col, _| Luma([col as u8]));
+        let histogram = Histogram::from_gray_image(&image, Some(&[false, true, true, false]));
+        assert_eq!(histogram.integral(), 2.);
+        assert_eq!(histogram.data[1], 0.);
+        assert_eq!(histogram.data[3], 1.);
+    }
 }
@@ -1,20 +1,125 @@
+use crate::color_space::ColorSpace;
+use crate::mask::{mark_excluded, neutralize, unmasked_mean, Mask};
 use crate::prelude::*;
-use crate::squared_error::root_mean_squared_error_simple;
-use crate::ssim::ssim_simple;
-use crate::utils::{blend_alpha, split_rgba_to_yuva};
+use crate::structure_compare;
+use crate::utils::{
+    blend_alpha, blend_alpha_with, split_rgba_to_yuva, unpremultiply, GrayBuffer, RgbBuffer,
+    RgbaBuffer, Subpixel,
+};
 use crate::Decompose;
-use image::{Rgba, RgbaImage};
+use image::{Pixel, Rgba};
 use itertools::izip;
 use std::borrow::Cow;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
 
-fn merge_similarity_channels_yuva(
+/// A user defined combination of structure, color and alpha similarity, see [`ChannelCombination::Custom`]
+pub type CombinationFn = dyn Fn(f32, f32, Option<f32>) -> f32 + Send + Sync;
+
+/// How the per pixel similarities of the structure (Y), color (U and V) and alpha channels are combined to the pixel's score.
+/// All inputs are in `[0, 1]`. For [`ChannelCombination::Minimum`] color is the clamped length of the (U, V) similarity vector,
+/// which saturates at 1 as soon as one of both channels is unchanged. All other combinations get the root mean square
+/// `sqrt((u² + v²) / 2)` instead, which is only 1 for unchanged colors - so color changes move their scores.
+#[derive(Clone, Default)]
+pub enum ChannelCombination {
+    /// The minimum of all similarities, so the worst channel decides - this is the default
+    #[default]
+    Minimum,
+    /// The weighted arithmetic mean - the alpha weight is ignored for rgb images. Weights need to be non-negative with a positive sum.
+    WeightedMean {
+        /// Weight of the structure similarity
+        structure: f32,
+        /// Weight of the color similarity
+        color: f32,
+        /// Weight of the alpha similarity
+        alpha: f32,
+    },
+    /// The product of all similarities
+    Product,
+    /// A custom function of structure, color and alpha similarity (`None` for rgb images), should return a value in `[0, 1]`
+    Custom(Arc<CombinationFn>),
+}
+
+impl Debug for ChannelCombination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChannelCombination::Minimum => write!(f, "Minimum"),
+            ChannelCombination::WeightedMean {
+                structure,
+                color,
+                alpha,
+            } => f
+                .debug_struct("WeightedMean")
+                .field("structure", structure)
+                .field("color", color)
+                .field("alpha", alpha)
+                .finish(),
+            ChannelCombination::Product => write!(f, "Product"),
+            ChannelCombination::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+impl ChannelCombination {
+    fn validate(&self, with_alpha: bool) -> Result<(), CompareError> {
+        if let ChannelCombination::WeightedMean {
+            structure,
+            color,
+            alpha,
+        } = self
+        {
+            let alpha = if with_alpha { *alpha } else { 0. };
+            let weights = [*structure, *color, alpha];
+            let sum: f32 = weights.iter().sum();
+            let valid = weights.iter().all(|w| *w >= 0.) && sum > 0. && sum.is_finite();
+            if !valid {
+                return Err(CompareError::CalculationFailed(format!(
+                    "Invalid channel combination: {self:?} - weights need to be non-negative with a positive sum"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The color similarity from the similarities of the U and V channels
+    fn color_similarity(&self, u: f32, v: f32) -> f32 {
+        match self {
+            ChannelCombination::Minimum => (u.powi(2) + v.powi(2)).sqrt().clamp(0.0, 1.0),
+            _ => ((u.powi(2) + v.powi(2)) / 2.).sqrt(),
+        }
+    }
+
+    fn combine(&self, structure: f32, color: f32, alpha: Option<f32>) -> f32 {
+        match self {
+            ChannelCombination::Minimum => {
+                let min = structure.min(color);
+                alpha.map_or(min, |a| min.min(a))
+            }
+            ChannelCombination::WeightedMean {
+                structure: w_structure,
+                color: w_color,
+                alpha: w_alpha,
+            } => {
+                let (alpha, w_alpha) = alpha.map_or((0., 0.), |a| (a, *w_alpha));
+                (w_structure * structure + w_color * color + w_alpha * alpha)
+                    / (w_structure + w_color + w_alpha)
+            }
+            ChannelCombination::Product => structure * color * alpha.unwrap_or(1.),
+            ChannelCombination::Custom(combine) => combine(structure, color, alpha),
+        }
+    }
+}
+
+fn merge_similarity_channels_yuva<P: Subpixel>(
     input: &[GraySimilarityImage; 4],
-    alpha: &GrayImage,
-    alpha_second: &GrayImage,
+    alpha: &GrayBuffer<P>,
+    alpha_second: &GrayBuffer<P>,
+    combination: &ChannelCombination,
+    transparent_limit: Option<f32>,
+    excluded: Option<&[bool]>,
 ) -> Similarity {
     const ALPHA_VIS_MIN: f32 = 0.1;
-    const U8_MAX: f32 = u8::MAX as f32;
-    const A_BAR_NORM: f32 = 2. * U8_MAX;
+    let a_bar_norm = 2. * P::RANGE as f32;
 
     let mut image = RGBASimilarityImage::new(input[0].width(), input[0].height());
     let mut deviation = Vec::new();
@@ -31,19 +136,27 @@ fn merge_similarity_channels_yuva(
     )
     .for_each(
         |(rgba, y, u, v, a_d, alpha_source, alpha_source_second, deviation)| {
-            let y = y[0].clamp(0.0, 1.0);
-            let u = u[0].clamp(0.0, 1.0);
-            let v = v[0].clamp(0.0, 1.0);
+            let alpha_source: f64 = alpha_source[0].into();
+            let alpha_source_second: f64 = alpha_source_second[0].into();
+            // the colors of pixels that are transparent in both images don't matter
+            let transparent = transparent_limit.is_some_and(|limit| {
+                alpha_source as f32 <= limit && alpha_source_second as f32 <= limit
+            });
+            let [y, u, v] = if transparent {
+                [1.; 3]
+            } else {
+                [y[0], u[0], v[0]].map(|c| c.clamp(0.0, 1.0))
+            };
             let a_d = a_d[0].clamp(0.0, 1.0);
-            let alpha_bar = (alpha_source[0] as f32 + alpha_source_second[0] as f32) / A_BAR_NORM;
+            let alpha_bar = (alpha_source as f32 + alpha_source_second as f32) / a_bar_norm;
             let alpha_bar = if alpha_bar.is_finite() {
                 alpha_bar
             } else {
                 1.0
             };
 
-            let color_diff = ((u).powi(2) + (v).powi(2)).sqrt().clamp(0.0, 1.0);
-            let min_sim = y.min(color_diff).min(a_d);
+            let color_diff = combination.color_similarity(u, v);
+            let min_sim = combination.combine(y, color_diff, Some(a_d));
             //the lower the alpha the fewer differences are visible in color and structure (and alpha)
 
             let dev = if alpha_bar > 0. {
@@ -58,7 +171,7 @@ fn merge_similarity_channels_yuva(
         },
     );
 
-    let score = deviation.iter().map(|s| *s as f64).sum::<f64>() / deviation.len() as f64;
+    let score = unmasked_score(&deviation, &mut image, excluded);
 
     Similarity {
         image: image.into(),
@@ -66,7 +179,11 @@ fn merge_similarity_channels_yuva(
     }
 }
 
-fn merge_similarity_channels_yuv(input: &[GraySimilarityImage; 3]) -> Similarity {
+fn merge_similarity_channels_yuv(
+    input: &[GraySimilarityImage; 3],
+    combination: &ChannelCombination,
+    excluded: Option<&[bool]>,
+) -> Similarity {
     let mut image = RGBSimilarityImage::new(input[0].width(), input[0].height());
     let mut deviation = Vec::new();
     deviation.resize((input[0].width() * input[0].height()) as usize, 0.0);
@@ -81,87 +198,440 @@ fn merge_similarity_channels_yuv(input: &[GraySimilarityImage; 3]) -> Similarity
         let y = y[0].clamp(0.0, 1.0);
         let u = u[0].clamp(0.0, 1.0);
         let v = v[0].clamp(0.0, 1.0);
-        let color_diff = ((u).powi(2) + (v).powi(2)).sqrt().clamp(0.0, 1.0);
+        let color_diff = combination.color_similarity(u, v);
         //f32 for keeping numerical stability for hybrid compare in 0.2.-branch
-        *deviation += y.min(color_diff);
+        *deviation += combination.combine(y, color_diff, None);
         *rgb = Rgb([1. - y, 1. - u, 1. - v]);
     });
 
-    let score = deviation.iter().map(|s| *s as f64).sum::<f64>() / deviation.len() as f64;
+    let score = unmasked_score(&deviation, &mut image, excluded);
     Similarity {
         image: image.into(),
         score,
     }
 }
 
+/// The mean deviation of the unmasked pixels, the masked pixels are marked in the image
+fn unmasked_score<Px: Pixel<Subpixel = f32>>(
+    deviation: &[f32],
+    image: &mut ImageBuffer<Px, Vec<f32>>,
+    excluded: Option<&[bool]>,
+) -> f64 {
+    let deviation = deviation.iter().map(|s| *s as f64);
+    match excluded {
+        Some(excluded) => {
+            mark_excluded(image, excluded);
+            unmasked_mean(deviation, excluded)
+        }
+        None => deviation.sum::<f64>() / image.pixels().len() as f64,
+    }
+}
+
+/// Configuration of the hybrid comparison.
+/// Only the similarity maps of the channel algorithms are merged, so every [`Algorithm`] can be used on every channel.
+#[derive(Debug, Clone)]
+pub struct HybridConfig {
+    /// The structure comparison used on the luma (Y) channel, defaults to [`Algorithm::MSSIMSimple`]
+    pub luma: Algorithm,
+    /// The structure comparison used on both chroma (U and V) channels, defaults to [`Algorithm::RootMeanSquared`]
+    pub chroma: Algorithm,
+    /// The structure comparison used on the alpha channel of rgba images, defaults to [`Algorithm::RootMeanSquared`]
+    pub alpha: Algorithm,
+    /// How the channel similarities are combined per pixel, defaults to [`ChannelCombination::Minimum`]
+    pub combination: ChannelCombination,
+    /// The color space the images are decomposed to luma and chroma in, defaults to [`ColorSpace::Bt601`]
+    pub color_space: ColorSpace,
+    /// How RGBA images are blended with the background in the blended comparisons, defaults to [`Blending::Encoded`]
+    pub blending: Blending,
+    /// Whether the colors of RGBA images are premultiplied with alpha, defaults to `false`.
+    /// Only applies to [`rgba_hybrid_compare_with_config`], which converts them to straight alpha before the decomposition.
+    /// The blended comparisons fail if it is set, use [`BlendInput::Premultiplied`] there instead.
+    /// RGB images have no alpha, so it has no effect on [`rgb_hybrid_compare_with_config`].
+    pub premultiplied: bool,
+    /// Colors of pixels whose alpha is at most this fraction of the range in both images are ignored by
+    /// [`rgba_hybrid_compare_with_config`], e.g. `Some(0.)` for fully transparent pixels.
+    /// Exporters often write arbitrary colors into invisible pixels. Defaults to `None`, so all colors are compared.
+    /// The blended comparisons fail if it is set, as blending mixes the hidden colors into the result.
+    /// It has no effect on [`rgb_hybrid_compare_with_config`].
+    pub transparent_threshold: Option<f32>,
+    /// Regions excluded from all hybrid comparisons, e.g. a clock in screenshots - defaults to an empty mask.
+    /// The masked pixels don't count towards the score and are `NaN` in the similarity image, see [`Mask`].
+    pub mask: Mask,
+}
+
+/// The result of a hybrid comparison with a [`HybridConfig`]
+#[derive(Debug)]
+pub struct HybridSimilarity {
+    /// The result of the comparison
+    pub similarity: Similarity,
+    /// The color space the images were decomposed in
+    pub color_space: ColorSpace,
+}
+
+impl Default for HybridConfig {
+    fn default() -> Self {
+        HybridConfig {
+            luma: Algorithm::MSSIMSimple,
+            chroma: Algorithm::RootMeanSquared,
+            alpha: Algorithm::RootMeanSquared,
+            combination: ChannelCombination::default(),
+            color_space: ColorSpace::default(),
+            blending: Blending::default(),
+            premultiplied: false,
+            transparent_threshold: None,
+            mask: Mask::default(),
+        }
+    }
+}
+
 /// Hybrid comparison for RGBA images.
-/// Will do MSSIM on luma, then RMS on U and V and alpha channels.
-/// The calculation of the score is then pixel-wise the minimum of each pixels similarity.
+/// Will do MSSIM on luma, then RMS on U and V and alpha channels - see [`HybridConfig`] for other algorithms.
+/// The calculation of the score is then pixel-wise the minimum of each pixels similarity,
+/// other combinations can be chosen with [`HybridConfig::combination`].
 /// To account for perceived indifference in lower alpha regions, this down-weights the difference
 /// linearly with mean alpha channel.
-pub fn rgba_hybrid_compare(
-    first: &RgbaImage,
-    second: &RgbaImage,
-) -> Result<Similarity, CompareError> {
+/// Works for 8 and 16 bit images, see [`Subpixel`].
+pub fn rgba_hybrid_compare<P: Subpixel>(
+    first: &ImageBuffer<Rgba<P>, Vec<P>>,
+    second: &ImageBuffer<Rgba<P>, Vec<P>>,
+) -> Result<Similarity, CompareError>
+where
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    rgba_hybrid_compare_with_config(first, second, &HybridConfig::default())
+        .map(|result| result.similarity)
+}
+
+/// Hybrid comparison for RGBA images like [`rgba_hybrid_compare`] but with a custom [`HybridConfig`]
+pub fn rgba_hybrid_compare_with_config<P: Subpixel>(
+    first: &ImageBuffer<Rgba<P>, Vec<P>>,
+    second: &ImageBuffer<Rgba<P>, Vec<P>>,
+    config: &HybridConfig,
+) -> Result<HybridSimilarity, CompareError>
+where
+    Rgba<P>: Pixel<Subpixel = P>,
+{
     if first.dimensions() != second.dimensions() {
         return Err(CompareError::DimensionsDiffer);
     }
+    config.combination.validate(true)?;
+    let transparent_limit = config
+        .transparent_threshold
+        .map(|threshold| {
+            if (0. ..=1.).contains(&threshold) {
+                Ok(threshold * P::RANGE as f32)
+            } else {
+                Err(CompareError::CalculationFailed(format!(
+                    "Invalid transparent threshold {threshold} - needs to be in [0, 1]"
+                )))
+            }
+        })
+        .transpose()?;
 
-    let first = split_rgba_to_yuva(first);
-    let second = split_rgba_to_yuva(second);
+    let (first, mut second) = if config.premultiplied {
+        (
+            Cow::Owned(unpremultiply(first)),
+            Cow::Owned(unpremultiply(second)),
+        )
+    } else {
+        (Cow::Borrowed(first), Cow::Borrowed(second))
+    };
+    if let Some(limit) = transparent_limit {
+        ignore_transparent_colors(&first, second.to_mut(), limit);
+    }
+    let excluded = config.mask.excluded(first.dimensions())?;
+    if let Some(excluded) = &excluded {
+        second = Cow::Owned(neutralize(&first, &second, excluded));
+    }
+    let first = split_rgba_to_yuva(&first, config.color_space);
+    let second = split_rgba_to_yuva(&second, config.color_space);
 
-    let (_, mssim_result) = ssim_simple(&first[0], &second[0])?;
-    let (_, u_result) = root_mean_squared_error_simple(&first[1], &second[1])?;
-    let (_, v_result) = root_mean_squared_error_simple(&first[2], &second[2])?;
+    let (_, mssim_result) = structure_compare(&config.luma, &first[0], &second[0])?;
+    let (_, u_result) = structure_compare(&config.chroma, &first[1], &second[1])?;
+    let (_, v_result) = structure_compare(&config.chroma, &first[2], &second[2])?;
 
-    let (_, alpha_result) = root_mean_squared_error_simple(&first[3], &second[3])?;
+    let (_, alpha_result) = structure_compare(&config.alpha, &first[3], &second[3])?;
 
     let results = [mssim_result, u_result, v_result, alpha_result];
 
-    Ok(merge_similarity_channels_yuva(
-        &results, &first[3], &second[3],
-    ))
+    Ok(HybridSimilarity {
+        similarity: merge_similarity_channels_yuva(
+            &results,
+            &first[3],
+            &second[3],
+            &config.combination,
+            transparent_limit,
+            excluded.as_deref(),
+        ),
+        color_space: config.color_space,
+    })
+}
+
+/// Copies the colors of `first` to `second` where both are transparent,
+/// so hidden colors don't show up in the structure comparison of the surrounding pixels either
+fn ignore_transparent_colors<P: Subpixel>(
+    first: &RgbaBuffer<P>,
+    second: &mut RgbaBuffer<P>,
+    limit: f32,
+) where
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    let is_transparent = |p: &Rgba<P>| p[3].into() as f32 <= limit;
+    first
+        .pixels()
+        .zip(second.pixels_mut())
+        .filter(|(a, b)| is_transparent(a) && is_transparent(b))
+        .for_each(|(a, b)| b.0[..3].copy_from_slice(&a.0[..3]));
 }
 
-/// A wrapper class accepting both RgbaImage and RgbImage for the blended hybrid comparison
-pub enum BlendInput<'a> {
+/// A wrapper class accepting both RgbaImage and RgbImage for the blended hybrid comparison,
+/// `P` is the subpixel type of both - `u8` by default
+#[derive(Clone, Copy)]
+pub enum BlendInput<'a, P: Subpixel = u8>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+    Rgba<P>: Pixel<Subpixel = P>,
+{
     /// This variant means that the image is already alpha pre-blended and therefore RGB
-    PreBlended(&'a RgbImage),
+    PreBlended(&'a ImageBuffer<Rgb<P>, Vec<P>>),
     /// This variant means that the image still needs to be blended with a certain background
-    RGBA(&'a RgbaImage),
+    RGBA(&'a ImageBuffer<Rgba<P>, Vec<P>>),
+    /// Like [`BlendInput::RGBA`] but the colors are premultiplied with alpha, as many renderers output them
+    Premultiplied(&'a ImageBuffer<Rgba<P>, Vec<P>>),
+}
+
+impl<'a, P: Subpixel> BlendInput<'a, P>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    fn blended(
+        &self,
+        background: &Background<P>,
+        blending: Blending,
+    ) -> Result<Cow<'a, RgbBuffer<P>>, CompareError> {
+        match self {
+            BlendInput::PreBlended(image) => Ok(Cow::Borrowed(*image)),
+            BlendInput::RGBA(rgba) => background.blend(rgba, blending).map(Cow::Owned),
+            BlendInput::Premultiplied(rgba) => background
+                .blend(&unpremultiply(rgba), blending)
+                .map(Cow::Owned),
+        }
+    }
+}
+
+/// How the colors of RGBA images are mixed with the background in the blended hybrid comparison
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Blending {
+    /// Mixes the sRGB encoded values directly like many image libraries do - this is the default.
+    /// Semi-transparent edges come out darker than on screen.
+    #[default]
+    Encoded,
+    /// Gamma-correct blending in linear light: the values are decoded from sRGB, mixed and encoded again,
+    /// like browsers and compositors do
+    Linear,
+}
+
+/// The background the RGBA images are blended over in the blended hybrid comparison
+#[derive(Clone, Copy)]
+pub enum Background<'a, P: Subpixel = u8>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+{
+    /// A single color
+    Color(Rgb<P>),
+    /// An image of the same dimensions as the compared images, e.g. a gradient or a photo
+    Image(&'a ImageBuffer<Rgb<P>, Vec<P>>),
+    /// A checkerboard of squares with an edge length of `size` pixels, starting with `colors[0]` in the top left corner
+    Checkerboard {
+        /// The edge length of the squares in pixels
+        size: u32,
+        /// The alternating colors of the squares
+        colors: [Rgb<P>; 2],
+    },
 }
 
-impl<'a> BlendInput<'a> {
-    fn into_blended(self, background: Rgb<u8>) -> Cow<'a, RgbImage> {
+impl<'a, P: Subpixel> Background<'a, P>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    /// The common transparency checkerboard of white and light gray (`#CCCCCC`) squares of `size` pixels
+    pub fn checkerboard(size: u32) -> Self {
+        let white = Rgb([P::from_clamped(P::RANGE as f32); 3]);
+        let gray = Rgb([P::from_clamped(P::RANGE as f32 * 0.8); 3]);
+        Background::Checkerboard {
+            size,
+            colors: [white, gray],
+        }
+    }
+
+    fn blend(
+        &self,
+        image: &RgbaBuffer<P>,
+        blending: Blending,
+    ) -> Result<RgbBuffer<P>, CompareError> {
         match self {
-            BlendInput::PreBlended(image) => Cow::Borrowed(image),
-            BlendInput::RGBA(rgba) => Cow::Owned(blend_alpha(rgba, background)),
+            Background::Color(color) => Ok(blend_alpha(image, *color, blending)),
+            Background::Image(background) => {
+                if background.dimensions() != image.dimensions() {
+                    return Err(CompareError::DimensionsDiffer);
+                }
+                Ok(blend_alpha_with(
+                    image,
+                    |x, y| *background.get_pixel(x, y),
+                    blending,
+                ))
+            }
+            Background::Checkerboard { size, colors } => {
+                if *size == 0 {
+                    return Err(CompareError::CalculationFailed(
+                        "Checkerboard squares need a size of at least one pixel".to_owned(),
+                    ));
+                }
+                Ok(blend_alpha_with(
+                    image,
+                    |x, y| colors[((x / size + y / size) % 2) as usize],
+                    blending,
+                ))
+            }
         }
     }
 }
 
-impl<'a> From<&'a RgbImage> for BlendInput<'a> {
-    fn from(value: &'a RgbImage) -> Self {
+impl<'a, P: Subpixel> From<Rgb<P>> for Background<'a, P>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+{
+    fn from(value: Rgb<P>) -> Self {
+        Background::Color(value)
+    }
+}
+
+impl<'a, P: Subpixel> From<&'a ImageBuffer<Rgb<P>, Vec<P>>> for Background<'a, P>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+{
+    fn from(value: &'a ImageBuffer<Rgb<P>, Vec<P>>) -> Self {
+        Background::Image(value)
+    }
+}
+
+/// The result of the blended hybrid comparison over several backgrounds, see [`rgba_blended_hybrid_compare_worst_case`]
+#[derive(Debug)]
+pub struct BackgroundSimilarity {
+    /// The result for the background with the lowest score
+    pub worst: HybridSimilarity,
+    /// The index of the background with the lowest score
+    pub background: usize,
+    /// The scores for all backgrounds in the given order
+    pub scores: Vec<f64>,
+}
+
+impl<'a, P: Subpixel> From<&'a ImageBuffer<Rgb<P>, Vec<P>>> for BlendInput<'a, P>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    fn from(value: &'a ImageBuffer<Rgb<P>, Vec<P>>) -> Self {
         BlendInput::PreBlended(value)
     }
 }
 
-impl<'a> From<&'a RgbaImage> for BlendInput<'a> {
-    fn from(value: &'a RgbaImage) -> Self {
+impl<'a, P: Subpixel> From<&'a ImageBuffer<Rgba<P>, Vec<P>>> for BlendInput<'a, P>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    fn from(value: &'a ImageBuffer<Rgba<P>, Vec<P>>) -> Self {
         BlendInput::RGBA(value)
     }
 }
 
 /// This processes the RGBA images be pre-blending the colors with the desired background color.
 /// It's faster then the full RGBA similarity and more intuitive.
-pub fn rgba_blended_hybrid_compare(
-    first: BlendInput,
-    second: BlendInput,
-    background: Rgb<u8>,
-) -> Result<Similarity, CompareError> {
-    let first = first.into_blended(background);
-    let second = second.into_blended(background);
-    rgb_hybrid_compare(&first, &second)
+pub fn rgba_blended_hybrid_compare<P: Subpixel>(
+    first: BlendInput<P>,
+    second: BlendInput<P>,
+    background: Rgb<P>,
+) -> Result<Similarity, CompareError>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    rgba_blended_hybrid_compare_with_config(
+        first,
+        second,
+        &Background::Color(background),
+        &HybridConfig::default(),
+    )
+    .map(|result| result.similarity)
+}
+
+/// Blended hybrid comparison like [`rgba_blended_hybrid_compare`] but with a custom [`HybridConfig`]
+/// and any [`Background`] - a color, an image or a checkerboard.
+/// Fails if [`HybridConfig::premultiplied`] is set, premultiplied images are passed as [`BlendInput::Premultiplied`].
+/// Fails as well for a [`HybridConfig::transparent_threshold`], the blending can't ignore the hidden colors.
+pub fn rgba_blended_hybrid_compare_with_config<P: Subpixel>(
+    first: BlendInput<P>,
+    second: BlendInput<P>,
+    background: &Background<P>,
+    config: &HybridConfig,
+) -> Result<HybridSimilarity, CompareError>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    if config.premultiplied {
+        return Err(CompareError::CalculationFailed(
+            "HybridConfig::premultiplied is not supported by the blended comparisons - use BlendInput::Premultiplied instead"
+                .to_owned(),
+        ));
+    }
+    if config.transparent_threshold.is_some() {
+        return Err(CompareError::CalculationFailed(
+            "HybridConfig::transparent_threshold is not supported by the blended comparisons - they blend the hidden colors"
+                .to_owned(),
+        ));
+    }
+    let first = first.blended(background, config.blending)?;
+    let second = second.blended(background, config.blending)?;
+    rgb_hybrid_compare_with_config(&first, &second, config)
+}
+
+/// Blended hybrid comparison over each of the `backgrounds`, reporting the worst case.
+/// Differences hidden by one background - e.g. dark content in front of black - are found on another one.
+pub fn rgba_blended_hybrid_compare_worst_case<P: Subpixel>(
+    first: BlendInput<P>,
+    second: BlendInput<P>,
+    backgrounds: &[Background<P>],
+    config: &HybridConfig,
+) -> Result<BackgroundSimilarity, CompareError>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    let mut worst: Option<(usize, HybridSimilarity)> = None;
+    let mut scores = Vec::with_capacity(backgrounds.len());
+    for (index, background) in backgrounds.iter().enumerate() {
+        let result = rgba_blended_hybrid_compare_with_config(first, second, background, config)?;
+        scores.push(result.similarity.score);
+        let is_worse = match &worst {
+            Some((_, w)) => result.similarity.score < w.similarity.score,
+            None => true,
+        };
+        if is_worse {
+            worst = Some((index, result));
+        }
+    }
+    let (background, worst) = worst.ok_or_else(|| {
+        CompareError::CalculationFailed("At least one background is required".to_owned())
+    })?;
+    Ok(BackgroundSimilarity {
+        worst,
+        background,
+        scores,
+    })
 }
 
 /// Comparing structure via MSSIM on Y channel, comparing color-diff-vectors on U and V summing the squares
@@ -169,21 +639,353 @@ pub fn rgba_blended_hybrid_compare(
 /// - The red channel contains 1. - similarity(ssim, y)
 /// - The green channel contains 1. -  similarity(rms, u)
 /// - The blue channel contains 1. -  similarity(rms, v)
+///
 /// This leads to a nice visualization of color and structure differences - with structural differences (meaning gray mssim diffs) leading to red rectangles
 /// and and the u and v color diffs leading to color-deviations in green, blue and cyan
 /// All-black meaning no differences
-pub fn rgb_hybrid_compare(first: &RgbImage, second: &RgbImage) -> Result<Similarity, CompareError> {
+///
+/// Works for 8 and 16 bit images, see [`Subpixel`].
+pub fn rgb_hybrid_compare<P: Subpixel>(
+    first: &ImageBuffer<Rgb<P>, Vec<P>>,
+    second: &ImageBuffer<Rgb<P>, Vec<P>>,
+) -> Result<Similarity, CompareError>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+{
+    rgb_hybrid_compare_with_config(first, second, &HybridConfig::default())
+        .map(|result| result.similarity)
+}
+
+/// Hybrid comparison for RGB images like [`rgb_hybrid_compare`] but with a custom [`HybridConfig`]
+pub fn rgb_hybrid_compare_with_config<P: Subpixel>(
+    first: &ImageBuffer<Rgb<P>, Vec<P>>,
+    second: &ImageBuffer<Rgb<P>, Vec<P>>,
+    config: &HybridConfig,
+) -> Result<HybridSimilarity, CompareError>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+{
     if first.dimensions() != second.dimensions() {
         return Err(CompareError::DimensionsDiffer);
     }
+    config.combination.validate(false)?;
+    let excluded = config.mask.excluded(first.dimensions())?;
+    let second = match &excluded {
+        Some(excluded) => Cow::Owned(neutralize(first, second, excluded)),
+        None => Cow::Borrowed(second),
+    };
 
-    let first_channels = first.split_to_yuv();
-    let second_channels = second.split_to_yuv();
-    let (_, mssim_result) = ssim_simple(&first_channels[0], &second_channels[0])?;
-    let (_, u_result) = root_mean_squared_error_simple(&first_channels[1], &second_channels[1])?;
-    let (_, v_result) = root_mean_squared_error_simple(&first_channels[2], &second_channels[2])?;
+    let first_channels = first.split_to_color_space(config.color_space);
+    let second_channels = second.split_to_color_space(config.color_space);
+    let (_, mssim_result) =
+        structure_compare(&config.luma, &first_channels[0], &second_channels[0])?;
+    let (_, u_result) = structure_compare(&config.chroma, &first_channels[1], &second_channels[1])?;
+    let (_, v_result) = structure_compare(&config.chroma, &first_channels[2], &second_channels[2])?;
 
     let results = [mssim_result, u_result, v_result];
 
-    Ok(merge_similarity_channels_yuv(&results))
+    Ok(HybridSimilarity {
+        similarity: merge_similarity_channels_yuv(
+            &results,
+            &config.combination,
+            excluded.as_deref(),
+        ),
+        color_space: config.color_space,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colorization::SimilarityImage;
+    use crate::mask::Rectangle;
+    use image::RgbaImage;
+
+    #[test]
+    fn minimum_is_default_combination() {
+        let combination = HybridConfig::default().combination;
+        assert_eq!(combination.combine(0.5, 0.25, None), 0.25);
+        assert_eq!(combination.combine(0.5, 0.25, Some(0.125)), 0.125);
+    }
+
+    #[test]
+    fn combinations() {
+        let mean = ChannelCombination::WeightedMean {
+            structure: 3.,
+            color: 1.,
+            alpha: 4.,
+        };
+        assert_eq!(mean.combine(1., 0.5, None), 0.875);
+        assert_eq!(mean.combine(1., 0.5, Some(0.)), 0.4375);
+        assert_eq!(ChannelCombination::Product.combine(0.5, 0.5, None), 0.25);
+        assert_eq!(
+            ChannelCombination::Product.combine(0.5, 0.5, Some(0.5)),
+            0.125
+        );
+        let custom = ChannelCombination::Custom(Arc::new(|s, c, a| s.max(c).max(a.unwrap_or(0.))));
+        assert_eq!(custom.combine(0.5, 0.25, None), 0.5);
+        assert_eq!(format!("{custom:?}"), "Custom(..)");
+    }
+
+    #[test]
+    fn color_similarity_saturates_only_for_minimum() {
+        let minimum = ChannelCombination::Minimum;
+        assert_eq!(minimum.color_similarity(1., 0.5), 1.);
+        assert_eq!(minimum.color_similarity(0.6, 0.8), 1.);
+        assert_eq!(minimum.color_similarity(0.3, 0.4), 0.5);
+        for combination in [
+            ChannelCombination::Product,
+            ChannelCombination::WeightedMean {
+                structure: 1.,
+                color: 1.,
+                alpha: 1.,
+            },
+        ] {
+            assert_eq!(combination.color_similarity(1., 1.), 1.);
+            assert_eq!(combination.color_similarity(1., 0.), 0.5f32.sqrt());
+            assert_eq!(combination.color_similarity(0., 0.), 0.);
+        }
+    }
+
+    #[test]
+    fn checkerboard_background() {
+        let transparent = RgbaImage::new(4, 2);
+        let background = Background::checkerboard(2);
+        let blended = background.blend(&transparent, Blending::Encoded).unwrap();
+        assert_eq!(blended.get_pixel(0, 0), &Rgb([255, 255, 255]));
+        assert_eq!(blended.get_pixel(1, 1), &Rgb([255, 255, 255]));
+        assert_eq!(blended.get_pixel(2, 0), &Rgb([204, 204, 204]));
+        let background = Background::<u16>::checkerboard(1);
+        let blended = background
+            .blend(
+                &ImageBuffer::<Rgba<u16>, Vec<u16>>::new(2, 1),
+                Blending::Encoded,
+            )
+            .unwrap();
+        assert_eq!(blended.get_pixel(1, 0), &Rgb([52428; 3]));
+        let background = Background::Checkerboard {
+            size: 0,
+            colors: [Rgb([0, 0, 0]); 2],
+        };
+        assert!(background.blend(&transparent, Blending::Encoded).is_err());
+    }
+
+    #[test]
+    fn image_background() {
+        let transparent = RgbaImage::new(2, 2);
+        let gradient = RgbImage::from_fn(2, 2, |x, y| Rgb([x as u8, y as u8, 7]));
+        let blended = Background::from(&gradient)
+            .blend(&transparent, Blending::Encoded)
+            .unwrap();
+        assert_eq!(blended, gradient);
+        let small = RgbImage::new(1, 1);
+        let result = rgba_blended_hybrid_compare_with_config(
+            (&transparent).into(),
+            (&transparent).into(),
+            &Background::Image(&small),
+            &HybridConfig::default(),
+        );
+        assert!(matches!(result, Err(CompareError::DimensionsDiffer)));
+    }
+
+    #[test]
+    fn worst_case_of_backgrounds() {
+        // a transparent image is only distinguishable from an opaque black one over bright backgrounds
+        let transparent = RgbaImage::new(8, 8);
+        let black = RgbaImage::from_pixel(8, 8, Rgba([0, 0, 0, 255]));
+        let backgrounds = [
+            Background::Color(Rgb([0, 0, 0])),
+            Background::Color(Rgb([255, 255, 255])),
+            Background::checkerboard(2),
+        ];
+        let result = rgba_blended_hybrid_compare_worst_case(
+            (&transparent).into(),
+            (&black).into(),
+            &backgrounds,
+            &HybridConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(result.scores.len(), 3);
+        assert_eq!(result.scores[0], 1.);
+        assert!(result.scores[1] < 1e-3);
+        // the checkerboard adds structure differences on top
+        assert_eq!(result.background, 2);
+        assert_eq!(result.worst.similarity.score, result.scores[2]);
+        let result = rgba_blended_hybrid_compare_worst_case(
+            (&transparent).into(),
+            (&black).into(),
+            &[],
+            &HybridConfig::default(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn premultiplied_input() {
+        let premultiply = |image: &RgbaImage| {
+            let mut premultiplied = image.clone();
+            premultiplied.pixels_mut().for_each(|p| {
+                let alpha = p[3] as f32 / 255.;
+                for c in 0..3 {
+                    p[c] = (p[c] as f32 * alpha).round() as u8;
+                }
+            });
+            premultiplied
+        };
+        let straight = RgbaImage::from_fn(8, 8, |x, y| Rgba([200, 100, 50, (x * 32 + y) as u8]));
+        let premultiplied = premultiply(&straight);
+        let white = Background::Color(Rgb([255, 255, 255]));
+        let compare = |second: BlendInput| {
+            rgba_blended_hybrid_compare_with_config(
+                BlendInput::RGBA(&straight),
+                second,
+                &white,
+                &HybridConfig::default(),
+            )
+            .unwrap()
+            .similarity
+            .score
+        };
+        let as_premultiplied = compare(BlendInput::Premultiplied(&premultiplied));
+        assert!(as_premultiplied > 0.99);
+        assert!(compare(BlendInput::RGBA(&premultiplied)) < as_premultiplied);
+
+        let gradient = |x: u32, y: u32, darker: bool| {
+            let value = if darker { x * 12 } else { x * 16 } as u8;
+            Rgba([value, value, value, (64 + y * 12) as u8])
+        };
+        let first = RgbaImage::from_fn(16, 16, |x, y| gradient(x, y, false));
+        let second = RgbaImage::from_fn(16, 16, |x, y| gradient(x, y, true));
+        let expected = rgba_hybrid_compare(&first, &second).unwrap().score;
+        let config = HybridConfig {
+            premultiplied: true,
+            ..Default::default()
+        };
+        let result =
+            rgba_hybrid_compare_with_config(&premultiply(&first), &premultiply(&second), &config)
+                .unwrap();
+        assert!((result.similarity.score - expected).abs() < 1e-4);
+        let blended = rgba_blended_hybrid_compare_with_config(
+            BlendInput::RGBA(&first),
+            BlendInput::RGBA(&second),
+            &white,
+            &config,
+        );
+        assert!(matches!(blended, Err(CompareError::CalculationFailed(_))));
+    }
+
+    #[test]
+    fn transparent_colors_are_ignored() {
+        let first = RgbaImage::from_fn(8, 8, |x, _| Rgba([255, 0, 0, if x < 4 { 0 } else { 255 }]));
+        let second = RgbaImage::from_fn(8, 8, |x, _| Rgba([0, 0, 255, if x < 4 { 0 } else { 10 }]));
+        let visible_differences = |image: &SimilarityImage| match image {
+            SimilarityImage::RGBA(image) => image
+                .pixels()
+                .filter(|p| p[0] > 0. || p[1] > 0. || p[2] > 0.)
+                .count(),
+            _ => unreachable!(),
+        };
+        let result = rgba_hybrid_compare(&first, &second).unwrap();
+        assert_eq!(visible_differences(&result.image), 64);
+
+        let config = HybridConfig {
+            transparent_threshold: Some(0.),
+            ..Default::default()
+        };
+        let result = rgba_hybrid_compare_with_config(&first, &second, &config).unwrap();
+        assert_eq!(visible_differences(&result.similarity.image), 32);
+        let config = HybridConfig {
+            transparent_threshold: Some(0.1),
+            ..Default::default()
+        };
+        let result = rgba_hybrid_compare_with_config(&first, &second, &config).unwrap();
+        // the second image is almost transparent on the right but the first one is not
+        assert_eq!(visible_differences(&result.similarity.image), 32);
+        let config = HybridConfig {
+            transparent_threshold: Some(1.5),
+            ..Default::default()
+        };
+        assert!(rgba_hybrid_compare_with_config(&first, &second, &config).is_err());
+        let config = HybridConfig {
+            transparent_threshold: Some(0.),
+            ..Default::default()
+        };
+        let backgrounds = [Background::Color(Rgb([0, 0, 0]))];
+        let blended = rgba_blended_hybrid_compare_worst_case(
+            (&first).into(),
+            (&second).into(),
+            &backgrounds,
+            &config,
+        );
+        assert!(matches!(blended, Err(CompareError::CalculationFailed(_))));
+    }
+
+    #[test]
+    fn masked_regions_are_excluded() {
+        let first =
+            RgbaImage::from_fn(16, 8, |x, y| Rgba([(x * 16) as u8, (y * 32) as u8, 7, 255]));
+        let mut second = first.clone();
+        second.put_pixel(3, 3, Rgba([255, 0, 255, 0]));
+        let config = HybridConfig {
+            mask: vec![Rectangle {
+                x: 2,
+                y: 2,
+                width: 2,
+                height: 2,
+            }]
+            .into(),
+            ..Default::default()
+        };
+        assert!(rgba_hybrid_compare(&first, &second).unwrap().score < 1.);
+        let result = rgba_hybrid_compare_with_config(&first, &second, &config).unwrap();
+        assert_eq!(result.similarity.score, 1.);
+        let map = result.similarity.image.to_color_map().into_rgba8();
+        assert_eq!(map.get_pixel(3, 3)[3], 0);
+        assert!(map.get_pixel(4, 3)[3] > 0);
+
+        let background = Background::Color(Rgb([0, 0, 0]));
+        let result = rgba_blended_hybrid_compare_with_config(
+            (&first).into(),
+            (&second).into(),
+            &background,
+            &config,
+        )
+        .unwrap();
+        assert_eq!(result.similarity.score, 1.);
+        let map = result.similarity.image.to_color_map();
+        assert_eq!(map.color(), image::ColorType::Rgba8);
+        assert_eq!(map.into_rgba8().get_pixel(2, 2)[3], 0);
+
+        let config = HybridConfig {
+            mask: GrayImage::new(8, 8).into(),
+            ..Default::default()
+        };
+        assert!(rgba_hybrid_compare_with_config(&first, &second, &config).is_err());
+    }
+
+    #[test]
+    fn invalid_weights() {
+        let image = RgbImage::new(8, 8);
+        let config = HybridConfig {
+            combination: ChannelCombination::WeightedMean {
+                structure: 0.,
+                color: 0.,
+                alpha: 1.,
+            },
+            ..Default::default()
+        };
+        // the alpha weight does not count for rgb images
+        assert!(rgb_hybrid_compare_with_config(&image, &image, &config).is_err());
+        let image = RgbaImage::new(8, 8);
+        assert!(rgba_hybrid_compare_with_config(&image, &image, &config).is_ok());
+        let config = HybridConfig {
+            combination: ChannelCombination::WeightedMean {
+                structure: -1.,
+                color: 2.,
+                alpha: 1.,
+            },
+            ..Default::default()
+        };
+        assert!(rgba_hybrid_compare_with_config(&image, &image, &config).is_err());
+    }
 }
@@ -10,6 +10,32 @@
 //! ```
 //! Check the [`Algorithm`] enum for implementation details
 //!
+//! ## Tuning SSIM
+//! Window, constants and dynamic range of all SSIM based algorithms can be set using a [`SsimConfig`]
+//! ```no_run
+//! use image_compare::{Algorithm, SsimConfig, SsimWindow};
+//! let image_one = image::open("image1.png").expect("Could not find test-image").into_luma8();
+//! let image_two = image::open("image2.png").expect("Could not find test-image").into_luma8();
+//! let config = SsimConfig {
+//!     window: SsimWindow::Sliding { size: 4, stride: 1 },
+//!     ..Default::default()
+//! };
+//! let result = image_compare::gray_similarity_structure(&Algorithm::SSIM(config), &image_one, &image_two).expect("Images had different dimensions");
+//! ```
+//!
+//! ## Finding the cause of SSIM differences
+//! SSIM is the product of a luminance, a contrast and a structure term.
+//! These can be retrieved separately to tell brightness shifts from contrast changes and structural changes:
+//! ```no_run
+//! use image_compare::SsimConfig;
+//! let image_one = image::open("image1.png").expect("Could not find test-image").into_luma8();
+//! let image_two = image::open("image2.png").expect("Could not find test-image").into_luma8();
+//! let result = image_compare::gray_similarity_ssim_components(&SsimConfig::default(), &image_one, &image_two).expect("Images had different dimensions");
+//! if result.structure.score > 0.99 && result.luminance.score < 0.9 {
+//!     println!("Only the brightness changed");
+//! }
+//! ```
+//!
 //! # Comparing gray images using histogram
 //!
 //! Histogram comparisons are possible using the histogram comparison function
@@ -44,6 +70,42 @@
 //! let result = image_compare::rgba_blended_hybrid_compare((&image_one).into(), (&image_two).into(), white).expect("Images had different dimensions");
 //! ```
 //!
+//! Images can also be blended over a background image or a transparency checkerboard, see [`Background`].
+//! Gamma-correct blending in linear light can be chosen with [`HybridConfig::blending`].
+//! Images with premultiplied alpha are passed as [`BlendInput::Premultiplied`].
+//! To find differences that only show on some backgrounds, several can be compared at once and the worst case is reported:
+//!
+//! ```no_run
+//! use image::Rgb;
+//! use image_compare::{Background, HybridConfig};
+//! let image_one = image::open("image1.png").expect("Could not find test-image").into_rgba8();
+//! let image_two = image::open("image2.png").expect("Could not find test-image").into_rgba8();
+//! let backgrounds = [Background::Color(Rgb([0, 0, 0])), Background::Color(Rgb([255, 255, 255])), Background::checkerboard(8)];
+//! let result = image_compare::rgba_blended_hybrid_compare_worst_case((&image_one).into(), (&image_two).into(), &backgrounds, &HybridConfig::default()).expect("Images had different dimensions");
+//! println!("Worst score {} on background {}", result.worst.similarity.score, result.background);
+//! ```
+//!
+//! ## Configuring the hybrid mode
+//! The structure algorithms used on the luma, chroma and alpha channels can be chosen using a [`HybridConfig`].
+//! Also, the per pixel minimum of the channel similarities can be replaced by another [`ChannelCombination`],
+//! e.g. a weighted mean that favors structure over color for photos,
+//! and the color space of the luma / chroma decomposition can be switched from BT.601 to another [`ColorSpace`].
+//!
+//! ```no_run
+//! use image_compare::{Algorithm, ChannelCombination, ColorSpace, HybridConfig, SsimConfig};
+//! let image_one = image::open("image1.png").expect("Could not find test-image").into_rgb8();
+//! let image_two = image::open("image2.png").expect("Could not find test-image").into_rgb8();
+//! let config = HybridConfig {
+//!     luma: Algorithm::MultiScaleSSIM(SsimConfig::gaussian()),
+//!     chroma: Algorithm::MSSIMSimple,
+//!     combination: ChannelCombination::WeightedMean { structure: 3., color: 1., alpha: 1. },
+//!     color_space: ColorSpace::Bt709,
+//!     ..Default::default()
+//! };
+//! let result = image_compare::rgb_hybrid_compare_with_config(&image_one, &image_two, &config).expect("Images had different dimensions");
+//! println!("Score in {}: {}", result.color_space, result.similarity.score);
+//! ```
+//!
 //! # Comparing two RGBA8 images using hybrid mode
 //!
 //! hybrid mode allows to decompose the image to structure, color and alpha channels (YUVA) which
@@ -53,10 +115,106 @@
 //! let image_two = image::open("image2.png").expect("Could not find test-image").into_rgba8();
 //! let result = image_compare::rgba_hybrid_compare(&image_one, &image_two).expect("Images had different dimensions");
 //! ```
+//! Images with premultiplied alpha are converted to straight alpha first if [`HybridConfig::premultiplied`] is set.
+//! The colors of transparent pixels can be ignored using [`HybridConfig::transparent_threshold`].
+//!
+//! # Comparing rgb images by color difference
+//!
+//! The color difference ΔE in CIELAB can be calculated using CIE76, CIE94 or CIEDE2000:
+//! ```no_run
+//! use image_compare::DeltaE;
+//! let image_one = image::open("image1.png").expect("Could not find test-image").into_rgb8();
+//! let image_two = image::open("image2.png").expect("Could not find test-image").into_rgb8();
+//! let result = image_compare::rgb_color_difference(DeltaE::CIEDE2000, &image_one, &image_two).expect("Images had different dimensions");
+//! if result.percentile(99.) > 2. {
+//!   println!("More than 1% of the pixels differ by more than 2 ΔE00, the worst by {}", result.max);
+//! }
+//! ```
+//!
+//! # Comparing rendered images using FLIP
+//!
+//! LDR-FLIP models how differences are perceived at the given viewing conditions, the mean error is `1 - score`.
+//! The error map can be visualized using the magma colormap of the reference:
+//! ```no_run
+//! use image_compare::FlipConfig;
+//! let reference = image::open("reference.png").expect("Could not find test-image").into_rgb8();
+//! let rendered = image::open("rendered.png").expect("Could not find test-image").into_rgb8();
+//! let result = image_compare::rgb_similarity_flip(&reference, &rendered, &FlipConfig::default()).expect("Images had different dimensions");
+//! println!("Mean FLIP error: {}", 1. - result.score);
+//! result.image.to_flip_color_map().save("flip.png").expect("Could not save FLIP image");
+//! ```
+//!
+//! # Comparing screenshots using pixelmatch
+//!
+//! Modeled on [pixelmatch](https://github.com/mapbox/pixelmatch): pixels are compared using a perceptual color
+//! difference in YIQ space and pixels that are just anti-aliased differently are detected and discounted.
+//! The result contains the number of different and anti-aliased pixels and a diff image marking both in different colors.
+//! ```no_run
+//! use image_compare::PixelmatchConfig;
+//! let image_one = image::open("image1.png").expect("Could not find test-image").into_rgba8();
+//! let image_two = image::open("image2.png").expect("Could not find test-image").into_rgba8();
+//! let result = image_compare::rgba_pixelmatch_compare(&image_one, &image_two, &PixelmatchConfig::default()).expect("Images had different dimensions");
+//! if result.different_pixels > 0 {
+//!   result.image.save("diff_image.png").expect("Could not save diff image");
+//! }
+//! ```
+//!
+//! # Excluding regions using masks
+//!
+//! Regions that change on every run, like a clock in screenshots, can be excluded by a [`Mask`] of a gray image and/or rectangles.
+//! Masked pixels don't count towards the score and are transparent in the color map of the similarity image.
+//! The hybrid comparisons take the mask from [`HybridConfig::mask`]:
+//! ```no_run
+//! use image_compare::{Algorithm, Mask, Rectangle};
+//! let image_one = image::open("image1.png").expect("Could not find test-image").into_luma8();
+//! let image_two = image::open("image2.png").expect("Could not find test-image").into_luma8();
+//! let clock = Rectangle { x: 1800, y: 0, width: 120, height: 40 };
+//! let mask = Mask::from(vec![clock]);
+//! let result = image_compare::gray_similarity_structure_masked(&Algorithm::MSSIMSimple, &image_one, &image_two, &mask).expect("Images had different dimensions");
+//! ```
+//!
+//! # Comparing 16 bit images
+//!
+//! Structure, histogram and hybrid comparisons work on 16 bit buffers as well, see [`Subpixel`].
+//! The dynamic range, e.g. SSIM's `L` and the normalization of RMS, is derived from the subpixel type:
+//! ```no_run
+//! use image_compare::Algorithm;
+//! let image_one = image::open("image1.tif").expect("Could not find test-image").into_luma16();
+//! let image_two = image::open("image2.tif").expect("Could not find test-image").into_luma16();
+//! let result = image_compare::gray_similarity_structure(&Algorithm::MSSIMSimple, &image_one, &image_two).expect("Images had different dimensions");
+//! ```
+//!
+//! # Comparing float and HDR images
+//!
+//! Float images are compared in the range `[0, 1]`. HDR images, e.g. linear frames of a path tracer,
+//! are brought to this range by a [`FloatEncoding`] first - a dynamic range, a tone mapping or the perceptually uniform PU21:
+//! ```no_run
+//! use image_compare::{Algorithm, FloatEncoding};
+//! let reference = image::open("reference.exr").expect("Could not find test-image").into_rgb32f();
+//! let rendered = image::open("rendered.exr").expect("Could not find test-image").into_rgb32f();
+//! let encoding = FloatEncoding::PerceptuallyUniform { luminance: 1000. };
+//! let reference = image_compare::encode_float_image(&reference, encoding).expect("Invalid encoding");
+//! let rendered = image_compare::encode_float_image(&rendered, encoding).expect("Invalid encoding");
+//! let result = image_compare::rgb_similarity_structure(&Algorithm::MSSIMSimple, &reference, &rendered).expect("Images had different dimensions");
+//! ```
+//!
+//! # Comparing images of any color type
+//!
+//! [`compare`] picks the comparison from the color types of two [`DynamicImage`]s:
+//! gray structure for gray images, hybrid mode for rgb and rgba images, each in 8 bit, 16 bit or float.
+//! Differing color types are promoted to the larger one, the result reports the path that was taken.
+//! ```no_run
+//! use image_compare::CompareOptions;
+//! let image_one = image::open("image1.png").expect("Could not find test-image");
+//! let image_two = image::open("image2.png").expect("Could not find test-image");
+//! let result = image_compare::compare(&image_one, &image_two, &CompareOptions::default()).expect("Images had different dimensions");
+//! println!("Compared using {}: {}", result.path, result.similarity.score);
+//! ```
 //!
 //! # Using structure results
 //! All structural comparisons return a result struct that contains the similarity score.
 //! For the score 1.0 is perfectly similar, 0.0 is dissimilar and some algorithms even provide up to -1.0 for inverse.
+//! The raw error metrics like [`Algorithm::PeakSignalToNoiseRatio`] are the exception, their score is the plain value.
 //! Furthermore, the algorithm may produce a similarity map (MSSIM, RMS and hybrid compare do) that can be evaluated per pixel or converted to a visualization:
 //! ```no_run
 //! let image_one = image::open("image1.png").expect("Could not find test-image").into_rgba8();
@@ -72,23 +230,67 @@
 #![warn(unused_qualifications)]
 #![deny(deprecated)]
 
+mod color_space;
 mod colorization;
+mod cw_ssim;
+mod delta_e;
+mod dynamic;
+mod flip;
+mod fsim;
+mod gmsd;
+mod hdr;
 mod histogram;
 mod hybrid;
+mod mask;
+mod pixelmatch;
 mod squared_error;
 mod ssim;
 mod utils;
 
 #[doc(hidden)]
 pub mod prelude {
+    pub use crate::cw_ssim::CwSsimConfig;
+    pub use crate::ssim::SsimConfig;
+    pub use crate::utils::Subpixel;
     pub use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
     use thiserror::Error;
     /// The enum for selecting a grayscale comparison implementation
+    #[derive(Debug, Clone, PartialEq)]
     pub enum Algorithm {
         /// A simple RMSE implementation - will return: <img src="https://render.githubusercontent.com/render/math?math=1-\sqrt{\frac{(\sum_{x,y=0}^{x,y=w,h}\left(f(x,y)-g(x,y)\right)^2)}{w*h}}">
         RootMeanSquared,
-        /// a simple MSSIM implementation - will run SSIM (implemented as on wikipedia: <img src="https://render.githubusercontent.com/render/math?math=\mathrm{SSIM}(x,y)={\frac {(2\mu _{x}\mu _{y}+c_{1})(2\sigma _{xy}+c_{2})}{(\mu _{x}^{2}+\mu _{y}^{2}+c_{1})(\sigma _{x}^{2}+\sigma _{y}^{2}+c_{2})}}"> ) over 8x8 px windows and average the results
+        /// The raw mean squared error <img src="https://render.githubusercontent.com/render/math?math=\frac{1}{wh}\sum_{x,y}\left(f(x,y)-g(x,y)\right)^2"> of the pixel values. Unlike the other algorithms the score is not normalized: 0 means identical. The map contains <img src="https://render.githubusercontent.com/render/math?math=1-\left(\frac{f(x,y)-g(x,y)}{L}\right)^2"> per pixel, with the range L of the subpixel type (255 for 8 bit and 65535 for 16 bit images).
+        MeanSquaredError,
+        /// The raw peak signal to noise ratio <img src="https://render.githubusercontent.com/render/math?math=10\log_{10}\frac{L^2}{\mathrm{MSE}}"> in dB, with the range L of the subpixel type. Unlike the other algorithms the score is not normalized: identical images yield `f64::INFINITY`. The map is the same as for [`Algorithm::MeanSquaredError`].
+        PeakSignalToNoiseRatio,
+        /// The raw mean absolute error <img src="https://render.githubusercontent.com/render/math?math=\frac{1}{wh}\sum_{x,y}\left|f(x,y)-g(x,y)\right|"> in levels of the pixel values. Unlike the other algorithms the score is not normalized: 0 means identical. The map is the same as for [`Algorithm::RootMeanSquared`].
+        MeanAbsoluteError,
+        /// The largest absolute difference of a single pixel in levels of the pixel values. Unlike the other algorithms the score is not normalized: 0 means identical. The map is the same as for [`Algorithm::RootMeanSquared`].
+        PeakAbsoluteError,
+        /// The fraction of pixels that differ by more than `tolerance` levels - meant for pixel exact regression tests. The number of pixels is `score * width * height`. The map is 0 for the offending pixels and 1 for all others. For multichannel images a pixel counts if any of its channels exceeds the tolerance.
+        PixelsOverTolerance {
+            /// The largest absolute difference per channel that is still accepted
+            tolerance: f64,
+        },
+        /// a simple MSSIM implementation - will run SSIM (implemented as on wikipedia: <img src="https://render.githubusercontent.com/render/math?math=\mathrm{SSIM}(x,y)={\frac {(2\mu _{x}\mu _{y}+c_{1})(2\sigma _{xy}+c_{2})}{(\mu _{x}^{2}+\mu _{y}^{2}+c_{1})(\sigma _{x}^{2}+\sigma _{y}^{2}+c_{2})}}"> ) over 8x8 px windows and average the results. Same as `SSIM(SsimConfig::default())`.
         MSSIMSimple,
+        /// SSIM as in the reference implementation of Wang et al. - will run SSIM with an 11x11 gaussian weighted window (sigma = 1.5) centered on every pixel and average the results over all pixels where the window fits into the image. Scores are comparable to published values and other tools like scikit-image (`gaussian_weights=True, use_sample_covariance=False`). Same as `SSIM(SsimConfig::gaussian())`.
+        MSSIMGaussian,
+        /// MSSIM with overlapping 8x8 px windows - the image is split into tiles of `stride` x `stride` pixels and each tile gets the SSIM of the window centered on it. A stride of 1 yields a true per-pixel SSIM map, a stride of 8 is equivalent to [`Algorithm::MSSIMSimple`]. The stride needs to be at least 1. Same as `SSIM(SsimConfig::sliding(stride))`.
+        MSSIMSliding {
+            /// Distance between the centers of neighbouring windows in pixels
+            stride: u32,
+        },
+        /// Multi-scale SSIM (MS-SSIM) as proposed by Wang, Simoncelli and Bovik - builds a pyramid of up to five scales by repeated 2x2 downsampling, compares contrast and structure on every scale and luminance on the coarsest scale using the windows of the given [`SsimConfig`]. The reference uses [`SsimConfig::gaussian`]. The results are combined with the standard exponents (0.0448, 0.2856, 0.3001, 0.2363, 0.1333). Negative contrast-structure means are clamped to zero. Smaller images use fewer scales with renormalized exponents. The similarity map contains the per pixel product with coarser scales upsampled.
+        MultiScaleSSIM(SsimConfig),
+        /// SSIM with custom window, constants and dynamic range, see [`SsimConfig`]
+        SSIM(SsimConfig),
+        /// Complex wavelet SSIM (CW-SSIM) as proposed by Wang and Simoncelli - the images are decomposed into oriented complex gabor subbands over several scales. Per subband the coefficients are compared in a window around every pixel using <img src="https://render.githubusercontent.com/render/math?math=\frac{2|\sum c_x c_y^*|%2BK}{\sum|c_x|^2%2B\sum|c_y|^2%2BK}">. Small (sub-pixel) translations only rotate the phase of the coefficients consistently and are therefore largely ignored. Beware that a global inversion is a consistent phase shift as well and scores close to 1. The map contains the per pixel mean over all subbands, see [`CwSsimConfig`].
+        CWSSIM(CwSsimConfig),
+        /// Gradient magnitude similarity deviation (GMSD) by Xue et al. - computes prewitt gradient magnitudes of both images and the per pixel similarity <img src="https://render.githubusercontent.com/render/math?math=\frac{2m_xm_y%2BT}{m_x^2%2Bm_y^2%2BT}"> with T = 170 as map. GMSD is the standard deviation of this map, the score is `1 - GMSD` so identical images score 1. Much cheaper than SSIM while correlating well with perceived quality. Unlike the reference, the images are not downsampled before, so the map keeps the full resolution. Only gradient magnitudes are compared, so inverted images score high.
+        GMSD,
+        /// Feature similarity index (FSIM) by Zhang et al. - phase congruency (PC) of both images is computed like Kovesi's `phasecong2` in the reference implementation (frequency domain log-gabor filters over four scales and orientations, minimum wavelength 6 px, scale factor 2, `sigmaOnf` 0.55, noise estimated from the energy of the finest scale), gradient magnitudes (G) using the scharr operator. The per pixel similarity <img src="https://render.githubusercontent.com/render/math?math=S_{PC}\cdot S_G"> is averaged weighted by the maximum phase congruency of both images. Like in the reference, the images are downsampled by averaging blocks of `max(1, round(min(width, height) / 256))` pixels first, the map is scaled back to the original size. For the chroma extension FSIMc see [`crate::rgb_similarity_fsimc`]. Inverted images score high, as only magnitudes are compared.
+        FSIM,
     }
 
     #[derive(Error, Debug)]
@@ -100,23 +302,39 @@ pub mod prelude {
         CalculationFailed(String),
     }
 
+    pub use crate::colorization::ChannelSimilarity;
     pub use crate::colorization::GraySimilarityImage;
     pub use crate::colorization::RGBASimilarityImage;
     pub use crate::colorization::RGBSimilarityImage;
     pub use crate::colorization::Similarity;
 }
 
+#[doc(inline)]
+pub use cw_ssim::CwSsimConfig;
+pub use delta_e::{ColorDifference, DeltaE};
+pub use dynamic::{CompareOptions, ComparisonMode, ComparisonPath, DynamicSimilarity, SampleDepth};
+pub use flip::FlipConfig;
+pub use hdr::FloatEncoding;
 #[doc(inline)]
 pub use histogram::Metric;
+pub use mask::{Mask, Rectangle};
+pub use pixelmatch::{PixelmatchConfig, PixelmatchResult};
 #[doc(inline)]
 pub use prelude::Algorithm;
 #[doc(inline)]
+pub use prelude::ChannelSimilarity;
+#[doc(inline)]
 pub use prelude::CompareError;
 #[doc(inline)]
 pub use prelude::Similarity;
+pub use ssim::{SsimComponent, SsimComponents, SsimConfig, SsimWindow};
+#[doc(inline)]
+pub use utils::Subpixel;
 
+use image::Pixel;
+use mask::{masked_structure_compare, neutralize};
 use prelude::*;
-use utils::Decompose;
+use utils::{Decompose, GrayBuffer};
 
 /// Comparing gray images using structure.
 ///
@@ -127,26 +345,103 @@ use utils::Decompose;
 /// * `first` - The first of the images to compare
 ///
 /// * `second` - The first of the images to compare
-pub fn gray_similarity_structure(
+pub fn gray_similarity_structure<P: Subpixel>(
     algorithm: &Algorithm,
-    first: &GrayImage,
-    second: &GrayImage,
+    first: &ImageBuffer<Luma<P>, Vec<P>>,
+    second: &ImageBuffer<Luma<P>, Vec<P>>,
 ) -> Result<Similarity, CompareError> {
     if first.dimensions() != second.dimensions() {
         return Err(CompareError::DimensionsDiffer);
     }
-    match algorithm {
-        Algorithm::RootMeanSquared => root_mean_squared_error_simple(first, second),
-        Algorithm::MSSIMSimple => ssim_simple(first, second),
-    }
-    .map(|(score, i)| Similarity {
+    structure_compare(algorithm, first, second).map(|(score, i)| Similarity {
         image: i.into(),
         score,
     })
 }
 
+/// Comparing gray images using structure like [`gray_similarity_structure`], excluding the masked pixels.
+/// The masked pixels don't count towards the score and are `NaN` in the similarity image, see [`Mask`].
+///
+/// # Arguments
+///
+/// * `algorithm` - The comparison algorithm to use
+///
+/// * `first` - The first of the images to compare
+///
+/// * `second` - The first of the images to compare
+///
+/// * `mask` - The regions to exclude
+pub fn gray_similarity_structure_masked<P: Subpixel>(
+    algorithm: &Algorithm,
+    first: &ImageBuffer<Luma<P>, Vec<P>>,
+    second: &ImageBuffer<Luma<P>, Vec<P>>,
+    mask: &Mask,
+) -> Result<Similarity, CompareError> {
+    if first.dimensions() != second.dimensions() {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    let excluded = mask.excluded(first.dimensions())?;
+    masked_structure_compare(algorithm, first, second, excluded.as_deref()).map(|(score, i)| {
+        Similarity {
+            image: i.into(),
+            score,
+        }
+    })
+}
+
+/// Comparing gray images using SSIM, additionally returning the luminance, contrast and structure factors of SSIM.
+/// This helps to tell whether a difference is caused by a brightness shift, a contrast change or an actual structural change.
+///
+/// # Arguments
+///
+/// * `config` - The SSIM configuration to use, see [`SsimConfig`]
+///
+/// * `first` - The first of the images to compare
+///
+/// * `second` - The first of the images to compare
+pub fn gray_similarity_ssim_components<P: Subpixel>(
+    config: &SsimConfig,
+    first: &ImageBuffer<Luma<P>, Vec<P>>,
+    second: &ImageBuffer<Luma<P>, Vec<P>>,
+) -> Result<SsimComponents, CompareError> {
+    if first.dimensions() != second.dimensions() {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    ssim_components(first, second, config)
+}
+
+pub(crate) fn structure_compare<P: Subpixel>(
+    algorithm: &Algorithm,
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    match algorithm {
+        Algorithm::RootMeanSquared => root_mean_squared_error_simple(first, second),
+        Algorithm::MeanSquaredError => mean_squared_error(first, second),
+        Algorithm::PeakSignalToNoiseRatio => peak_signal_to_noise_ratio(first, second),
+        Algorithm::MeanAbsoluteError => mean_absolute_error(first, second),
+        Algorithm::PeakAbsoluteError => peak_absolute_error(first, second),
+        Algorithm::PixelsOverTolerance { tolerance } => {
+            pixels_over_tolerance(first, second, *tolerance)
+        }
+        Algorithm::MSSIMSimple => ssim(first, second, &SsimConfig::default()),
+        Algorithm::MSSIMGaussian => ssim(first, second, &SsimConfig::gaussian()),
+        Algorithm::MSSIMSliding { stride } => ssim(first, second, &SsimConfig::sliding(*stride)),
+        Algorithm::MultiScaleSSIM(config) => ms_ssim(first, second, config),
+        Algorithm::SSIM(config) => ssim(first, second, config),
+        Algorithm::CWSSIM(config) => cw_ssim(first, second, config),
+        Algorithm::GMSD => gmsd(first, second),
+        Algorithm::FSIM => fsim(first, second),
+    }
+}
+
 /// Comparing rgb images using structure.
-/// RGB structure similarity is performed by doing a channel split and taking the maximum deviation (minimum similarity) for the result.
+/// RGB structure similarity is performed by doing a channel split and combining the scores of the channels:
+/// - MSE, PSNR and MAE are calculated over all samples
+/// - The peak absolute error is the largest of all channels
+/// - Pixels over tolerance count a pixel if any of its channels exceeds the tolerance
+/// - For all other algorithms the maximum deviation (minimum similarity) of the channels is used
+///
 /// The image contains the complete deviations.
 /// # Arguments
 ///
@@ -158,38 +453,334 @@ pub fn gray_similarity_structure(
 ///
 /// ### Experimental:
 /// As you can see from the pinning tests in cucumber - the differences are quite small, the runtime difference is rather large though.
-pub fn rgb_similarity_structure(
+pub fn rgb_similarity_structure<P: Subpixel>(
     algorithm: &Algorithm,
-    first: &RgbImage,
-    second: &RgbImage,
-) -> Result<Similarity, CompareError> {
+    first: &ImageBuffer<Rgb<P>, Vec<P>>,
+    second: &ImageBuffer<Rgb<P>, Vec<P>>,
+) -> Result<Similarity, CompareError>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+{
     if first.dimensions() != second.dimensions() {
         return Err(CompareError::DimensionsDiffer);
     }
+    rgb_structure(algorithm, first, second, None)
+}
 
+/// Comparing rgb images using structure like [`rgb_similarity_structure`], excluding the masked pixels.
+/// The masked pixels don't count towards the score and are `NaN` in the similarity image, see [`Mask`].
+///
+/// # Arguments
+///
+/// * `algorithm` - The comparison algorithm to use
+///
+/// * `first` - The first of the images to compare
+///
+/// * `second` - The first of the images to compare
+///
+/// * `mask` - The regions to exclude
+pub fn rgb_similarity_structure_masked<P: Subpixel>(
+    algorithm: &Algorithm,
+    first: &ImageBuffer<Rgb<P>, Vec<P>>,
+    second: &ImageBuffer<Rgb<P>, Vec<P>>,
+    mask: &Mask,
+) -> Result<Similarity, CompareError>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+{
+    if first.dimensions() != second.dimensions() {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    let excluded = mask.excluded(first.dimensions())?;
+    rgb_structure(algorithm, first, second, excluded.as_deref())
+}
+
+fn rgb_structure<P: Subpixel>(
+    algorithm: &Algorithm,
+    first: &ImageBuffer<Rgb<P>, Vec<P>>,
+    second: &ImageBuffer<Rgb<P>, Vec<P>>,
+    excluded: Option<&[bool]>,
+) -> Result<Similarity, CompareError>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+{
     let first_channels = first.split_channels();
-    let second_channels = second.split_channels();
+    let second_channels = match excluded {
+        Some(excluded) => neutralize(first, second, excluded).split_channels(),
+        None => second.split_channels(),
+    };
     let mut results = Vec::new();
 
     for channel in 0..3 {
-        match algorithm {
-            Algorithm::RootMeanSquared => {
-                results.push(root_mean_squared_error_simple(
-                    &first_channels[channel],
-                    &second_channels[channel],
-                )?);
-            }
-            Algorithm::MSSIMSimple => {
-                results.push(ssim_simple(
-                    &first_channels[channel],
-                    &second_channels[channel],
-                )?);
-            }
-        }
+        results.push(masked_structure_compare(
+            algorithm,
+            &first_channels[channel],
+            &second_channels[channel],
+            excluded,
+        )?);
     }
     let input = results.iter().map(|(_, i)| i).collect::<Vec<_>>();
     let image = utils::merge_similarity_channels(&input.try_into().unwrap());
-    let score = results.iter().map(|(s, _)| *s).fold(1., f64::min);
+    let scores = results.iter().map(|(s, _)| *s).collect::<Vec<_>>();
+    let masked = excluded.map_or(0, |excluded| excluded.iter().filter(|m| **m).count());
+    let score = combine_channel_scores(
+        algorithm,
+        &first_channels,
+        &second_channels,
+        &scores,
+        masked,
+    );
+    Ok(Similarity {
+        image: image.into(),
+        score,
+    })
+}
+
+/// Combines the scores of the channels:
+/// - MSE, PSNR and MAE over all samples
+/// - The peak absolute error is the largest of all channels
+/// - Pixels over tolerance count a pixel if any channel exceeds the tolerance,
+///   for planes of different dimensions the samples are counted instead
+/// - Otherwise the worst channel
+///
+/// The `masked` pixels of every plane are identical in both images and don't count as samples.
+fn combine_channel_scores<P: Subpixel>(
+    algorithm: &Algorithm,
+    first: &[GrayBuffer<P>],
+    second: &[GrayBuffer<P>],
+    scores: &[f64],
+    masked: usize,
+) -> f64 {
+    let samples = || {
+        first
+            .iter()
+            .map(|f| f.pixels().len() - masked)
+            .sum::<usize>() as f64
+    };
+    let sum_over_channels = |error: fn(&GrayBuffer<P>, &GrayBuffer<P>) -> f64| {
+        first
+            .iter()
+            .zip(second)
+            .map(|(f, s)| error(f, s))
+            .sum::<f64>()
+    };
+    match algorithm {
+        Algorithm::MeanSquaredError => sum_over_channels(squared_error_sum) / samples(),
+        Algorithm::PeakSignalToNoiseRatio => {
+            psnr_from_mse(sum_over_channels(squared_error_sum) / samples(), P::RANGE)
+        }
+        Algorithm::MeanAbsoluteError => sum_over_channels(absolute_error_sum) / samples(),
+        Algorithm::PeakAbsoluteError => scores.iter().copied().fold(0., f64::max),
+        Algorithm::PixelsOverTolerance { tolerance } => {
+            if first
+                .iter()
+                .all(|f| f.dimensions() == first[0].dimensions())
+            {
+                let mut pixels = vec![false; first[0].pixels().len()];
+                for (f, s) in first.iter().zip(second) {
+                    pixels
+                        .iter_mut()
+                        .zip(over_tolerance(f, s, *tolerance))
+                        .for_each(|(pixel, over)| *pixel |= over);
+                }
+                pixels.iter().filter(|p| **p).count() as f64 / (pixels.len() - masked) as f64
+            } else {
+                let count = first
+                    .iter()
+                    .zip(second)
+                    .flat_map(|(f, s)| over_tolerance(f, s, *tolerance))
+                    .filter(|over| *over)
+                    .count();
+                count as f64 / samples()
+            }
+        }
+        _ => scores.iter().copied().fold(1., f64::min),
+    }
+}
+
+fn channel_similarity<P: Subpixel>(
+    algorithm: &Algorithm,
+    first: &[GrayBuffer<P>],
+    second: &[GrayBuffer<P>],
+) -> Result<ChannelSimilarity, CompareError> {
+    if first
+        .iter()
+        .zip(second)
+        .any(|(f, s)| f.dimensions() != s.dimensions())
+    {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    let channels = first
+        .iter()
+        .zip(second)
+        .map(|(f, s)| {
+            structure_compare(algorithm, f, s).map(|(score, image)| Similarity {
+                image: image.into(),
+                score,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let scores = channels.iter().map(|c| c.score).collect::<Vec<_>>();
+    Ok(ChannelSimilarity {
+        score: combine_channel_scores(algorithm, first, second, &scores, 0),
+        channels,
+    })
+}
+
+/// Comparing rgb images channel by channel, returning the score and similarity image of every channel.
+/// The combined score is the same as for [`rgb_similarity_structure`], see there how the channels are combined.
+///
+/// # Arguments
+///
+/// * `algorithm` - The comparison algorithm to use
+///
+/// * `first` - The first of the images to compare
+///
+/// * `second` - The first of the images to compare
+pub fn rgb_similarity_structure_channels<P: Subpixel>(
+    algorithm: &Algorithm,
+    first: &ImageBuffer<Rgb<P>, Vec<P>>,
+    second: &ImageBuffer<Rgb<P>, Vec<P>>,
+) -> Result<ChannelSimilarity, CompareError>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+{
+    if first.dimensions() != second.dimensions() {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    channel_similarity(algorithm, &first.split_channels(), &second.split_channels())
+}
+
+/// Comparing YUV images given as Y, U and V planes channel by channel - e.g. for the PSNR of video codecs.
+/// The planes may be subsampled (like in 4:2:0), only the respective planes of both images need to have the same dimensions.
+/// MSE, PSNR and MAE are combined over all samples, so subsampled chroma planes get a lower weight.
+/// The peak absolute error is the largest of all planes. Pixels over tolerance count a pixel if any of its planes exceeds
+/// the tolerance - for subsampled planes every sample over the tolerance counts instead.
+/// For all other algorithms the worst plane is used.
+///
+/// # Arguments
+///
+/// * `algorithm` - The comparison algorithm to use
+///
+/// * `first` - The Y, U and V planes of the first image
+///
+/// * `second` - The Y, U and V planes of the second image
+pub fn yuv_similarity_structure<P: Subpixel>(
+    algorithm: &Algorithm,
+    first: &[ImageBuffer<Luma<P>, Vec<P>>; 3],
+    second: &[ImageBuffer<Luma<P>, Vec<P>>; 3],
+) -> Result<ChannelSimilarity, CompareError> {
+    channel_similarity(algorithm, first, second)
+}
+
+/// Comparing rgb images by their color difference ΔE in CIELAB, e.g. to check design tolerances given in ΔE00.
+/// The sRGB colors are converted to CIELAB via XYZ using the D65 white point.
+/// The result contains the ΔE map as well as mean, maximum and percentiles of ΔE.
+///
+/// # Arguments
+///
+/// * `formula` - The ΔE formula to use, see [`DeltaE`]
+///
+/// * `first` - The first of the images to compare
+///
+/// * `second` - The first of the images to compare
+pub fn rgb_color_difference(
+    formula: DeltaE,
+    first: &RgbImage,
+    second: &RgbImage,
+) -> Result<ColorDifference, CompareError> {
+    if first.dimensions() != second.dimensions() {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    color_difference(formula, first, second)
+}
+
+/// Comparing rgba images like [pixelmatch](https://github.com/mapbox/pixelmatch) does:
+/// pixels whose YIQ color difference exceeds the threshold are counted as different unless they are detected as
+/// anti-aliasing artifacts. Translucent pixels are blended onto white before.
+///
+/// # Arguments
+///
+/// * `first` - The first of the images to compare
+///
+/// * `second` - The first of the images to compare
+///
+/// * `config` - Threshold, anti-aliasing handling and colors of the diff image, see [`PixelmatchConfig`]
+pub fn rgba_pixelmatch_compare(
+    first: &RgbaImage,
+    second: &RgbaImage,
+    config: &PixelmatchConfig,
+) -> Result<PixelmatchResult, CompareError> {
+    if first.dimensions() != second.dimensions() {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    pixelmatch(first, second, config)
+}
+
+/// Comparing rgb images like [pixelmatch](https://github.com/mapbox/pixelmatch) does, see [`rgba_pixelmatch_compare`]
+///
+/// # Arguments
+///
+/// * `first` - The first of the images to compare
+///
+/// * `second` - The first of the images to compare
+///
+/// * `config` - Threshold, anti-aliasing handling and colors of the diff image, see [`PixelmatchConfig`]
+pub fn rgb_pixelmatch_compare(
+    first: &RgbImage,
+    second: &RgbImage,
+    config: &PixelmatchConfig,
+) -> Result<PixelmatchResult, CompareError> {
+    let to_rgba = |image: &RgbImage| DynamicImage::ImageRgb8(image.clone()).into_rgba8();
+    rgba_pixelmatch_compare(&to_rgba(first), &to_rgba(second), config)
+}
+
+/// Comparing rgb images using FSIMc, the chroma extension of [`Algorithm::FSIM`]:
+/// FSIM is calculated on the Y channel of the YIQ decomposition, the per pixel similarities of the I and Q channels
+/// are multiplied into the map as <img src="https://render.githubusercontent.com/render/math?math=(S_I S_Q)^{0.03}"> like in the reference.
+/// The similarity image is a single channel map.
+///
+/// # Arguments
+///
+/// * `first` - The first of the images to compare
+///
+/// * `second` - The first of the images to compare
+pub fn rgb_similarity_fsimc(
+    first: &RgbImage,
+    second: &RgbImage,
+) -> Result<Similarity, CompareError> {
+    if first.dimensions() != second.dimensions() {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    let (score, image) = fsimc(first, second)?;
+    Ok(Similarity {
+        image: image.into(),
+        score,
+    })
+}
+
+/// Comparing rgb images using LDR-FLIP by Andersson et al. as used to evaluate renderers.
+/// Colors are filtered with contrast sensitivity functions for the given viewing conditions and compared in a
+/// perceptual color space, differences in edges and points amplify the color error.
+/// The map contains `1 - error` per pixel and the score is `1 - mean(error)`, so the mean FLIP value of the
+/// reference is `1 - score`. The map can be visualized with the magma colormap of the reference using `to_flip_color_map`.
+///
+/// # Arguments
+///
+/// * `first` - The first of the images to compare, usually the reference
+///
+/// * `second` - The first of the images to compare
+///
+/// * `config` - The viewing conditions, see [`FlipConfig`]
+pub fn rgb_similarity_flip(
+    first: &RgbImage,
+    second: &RgbImage,
+    config: &FlipConfig,
+) -> Result<Similarity, CompareError> {
+    if first.dimensions() != second.dimensions() {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    let (score, image) = flip(first, second, config)?;
     Ok(Similarity {
         image: image.into(),
         score,
@@ -204,28 +795,115 @@ pub fn rgb_similarity_structure(
 /// * `first` - The first of the images to compare
 ///
 /// * `second` - The first of the images to compare
-pub fn gray_similarity_histogram(
+pub fn gray_similarity_histogram<P: Subpixel>(
+    metric: Metric,
+    first: &ImageBuffer<Luma<P>, Vec<P>>,
+    second: &ImageBuffer<Luma<P>, Vec<P>>,
+) -> Result<f64, CompareError> {
+    if first.dimensions() != second.dimensions() {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    histogram::img_compare(first, second, metric, None)
+}
+
+/// Comparing gray images using histogram like [`gray_similarity_histogram`],
+/// the histograms only contain the pixels that are not masked, see [`Mask`]
+/// # Arguments
+///
+/// * `metric` - The distance metric to use
+///
+/// * `first` - The first of the images to compare
+///
+/// * `second` - The first of the images to compare
+///
+/// * `mask` - The regions to exclude
+pub fn gray_similarity_histogram_masked<P: Subpixel>(
     metric: Metric,
-    first: &GrayImage,
-    second: &GrayImage,
+    first: &ImageBuffer<Luma<P>, Vec<P>>,
+    second: &ImageBuffer<Luma<P>, Vec<P>>,
+    mask: &Mask,
 ) -> Result<f64, CompareError> {
     if first.dimensions() != second.dimensions() {
         return Err(CompareError::DimensionsDiffer);
     }
-    histogram::img_compare(first, second, metric)
+    let excluded = mask.excluded(first.dimensions())?;
+    histogram::img_compare(first, second, metric, excluded.as_deref())
+}
+
+/// Maps the color channels of a float image to `[0, 1]` using the given encoding, alpha is kept as it is.
+/// Float images are compared with a dynamic range of 1.0, so HDR images should be encoded before the comparison.
+///
+/// # Arguments
+///
+/// * `image` - The float image to encode, e.g. an `Rgb32FImage` holding linear HDR values
+///
+/// * `encoding` - The mapping to use, see [`FloatEncoding`]
+pub fn encode_float_image<Px: Pixel<Subpixel = f32>>(
+    image: &ImageBuffer<Px, Vec<f32>>,
+    encoding: FloatEncoding,
+) -> Result<ImageBuffer<Px, Vec<f32>>, CompareError> {
+    hdr::encode_float_image(image, encoding)
+}
+
+/// Comparing two images of any color type, choosing the comparison by their color types:
+/// - Gray images are compared by [`gray_similarity_structure`] with [`CompareOptions::gray`]
+/// - Rgb images are compared by [`rgb_hybrid_compare_with_config`]
+/// - Images with alpha are compared by [`rgba_hybrid_compare_with_config`], also gray images with alpha
+///
+/// 8 bit, 16 bit and float images keep their subpixel type.
+/// If the color types differ, both images are converted to the larger channel layout and subpixel type,
+/// e.g. a gray and an rgb image are compared as rgb, an 8 bit and a 16 bit image as 16 bit.
+///
+/// # Arguments
+///
+/// * `first` - The first of the images to compare
+///
+/// * `second` - The first of the images to compare
+///
+/// * `options` - The algorithms to use, see [`CompareOptions`]
+pub fn compare(
+    first: &DynamicImage,
+    second: &DynamicImage,
+    options: &CompareOptions,
+) -> Result<DynamicSimilarity, CompareError> {
+    if first.dimensions() != second.dimensions() {
+        return Err(CompareError::DimensionsDiffer);
+    }
+    dynamic::compare(first, second, options)
 }
 
 #[doc(inline)]
 pub use hybrid::rgb_hybrid_compare;
 
-use crate::squared_error::root_mean_squared_error_simple;
-use crate::ssim::ssim_simple;
+use crate::cw_ssim::cw_ssim;
+use crate::delta_e::color_difference;
+use crate::flip::flip;
+use crate::fsim::{fsim, fsimc};
+use crate::gmsd::gmsd;
+use crate::pixelmatch::pixelmatch;
+use crate::squared_error::{
+    absolute_error_sum, mean_absolute_error, mean_squared_error, over_tolerance,
+    peak_absolute_error, peak_signal_to_noise_ratio, pixels_over_tolerance, psnr_from_mse,
+    root_mean_squared_error_simple, squared_error_sum,
+};
+use crate::ssim::{ms_ssim, ssim, ssim_components};
 #[doc(inline)]
 pub use hybrid::rgba_hybrid_compare;
+use image::{DynamicImage, GenericImageView, RgbaImage};
 
 #[doc(inline)]
 pub use hybrid::rgba_blended_hybrid_compare;
 
+#[doc(inline)]
+pub use hybrid::{
+    rgb_hybrid_compare_with_config, rgba_blended_hybrid_compare_with_config,
+    rgba_blended_hybrid_compare_worst_case, rgba_hybrid_compare_with_config, Background,
+    BackgroundSimilarity, Blending, ChannelCombination, CombinationFn, HybridConfig,
+    HybridSimilarity,
+};
+
+pub use color_space::ColorSpace;
+
 pub use hybrid::BlendInput;
 
 #[cfg(test)]
@@ -240,6 +918,48 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn dimensions_differ_test_16_bit() {
+        let first = ImageBuffer::<image::Rgba<u16>, Vec<u16>>::new(1, 1);
+        let second = ImageBuffer::<image::Rgba<u16>, Vec<u16>>::new(2, 2);
+        let result = rgba_hybrid_compare(&first, &second);
+        assert!(result.is_err());
+        let first = ImageBuffer::<Luma<u16>, Vec<u16>>::new(1, 1);
+        let second = ImageBuffer::<Luma<u16>, Vec<u16>>::new(2, 2);
+        let result = gray_similarity_histogram(Metric::Intersection, &first, &second);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn float_images_compare_like_8_bit() {
+        let first = RgbImage::from_fn(16, 16, |col, row| Rgb([col as u8 * 16, row as u8 * 8, 7]));
+        let second = RgbImage::from_fn(16, 16, |col, _| Rgb([col as u8 * 15, 0, 9]));
+        let to_float = |image: &RgbImage| DynamicImage::ImageRgb8(image.clone()).into_rgb32f();
+        let expected = rgb_similarity_structure(&Algorithm::MSSIMSimple, &first, &second).unwrap();
+        let result = rgb_similarity_structure(
+            &Algorithm::MSSIMSimple,
+            &to_float(&first),
+            &to_float(&second),
+        )
+        .unwrap();
+        assert!((expected.score - result.score).abs() < 1e-5);
+        let mut hdr = to_float(&first);
+        hdr.pixels_mut().for_each(|p| p.apply(|c| c * 4.));
+        let encoded = encode_float_image(&hdr, FloatEncoding::Linear { range: 4. }).unwrap();
+        let result =
+            rgb_similarity_structure(&Algorithm::RootMeanSquared, &to_float(&first), &encoded)
+                .unwrap();
+        assert!((result.score - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn dimensions_differ_test_compare() {
+        let first = DynamicImage::new_luma8(1, 1);
+        let second = DynamicImage::new_rgba16(2, 2);
+        let result = compare(&first, &second, &CompareOptions::default());
+        assert!(result.is_err());
+    }
+
     #[test]
     fn dimensions_differ_test_rgb_structure() {
         let first = RgbImage::new(1, 1);
@@ -248,6 +968,114 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn dimensions_differ_test_gray_ssim_components() {
+        let first = GrayImage::new(1, 1);
+        let second = GrayImage::new(2, 2);
+        let result = gray_similarity_ssim_components(&SsimConfig::default(), &first, &second);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn dimensions_differ_test_rgb_fsimc() {
+        let first = RgbImage::new(1, 1);
+        let second = RgbImage::new(2, 2);
+        let result = rgb_similarity_fsimc(&first, &second);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn dimensions_differ_test_yuv_structure() {
+        let planes = |size| {
+            [
+                GrayImage::new(size, size),
+                GrayImage::new(1, 1),
+                GrayImage::new(1, 1),
+            ]
+        };
+        let result = yuv_similarity_structure(&Algorithm::RootMeanSquared, &planes(2), &planes(3));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn yuv_psnr_weights_samples() {
+        let first = [
+            GrayImage::from_pixel(2, 2, Luma([10])),
+            GrayImage::from_pixel(1, 1, Luma([0])),
+            GrayImage::from_pixel(1, 1, Luma([0])),
+        ];
+        let second = [
+            GrayImage::from_pixel(2, 2, Luma([10])),
+            GrayImage::from_pixel(1, 1, Luma([6])),
+            GrayImage::from_pixel(1, 1, Luma([0])),
+        ];
+        let mse = yuv_similarity_structure(&Algorithm::MeanSquaredError, &first, &second).unwrap();
+        assert_eq!(mse.score, 36. / 6.);
+        assert_eq!(mse.channels[0].score, 0.);
+        assert_eq!(mse.channels[1].score, 36.);
+        let psnr =
+            yuv_similarity_structure(&Algorithm::PeakSignalToNoiseRatio, &first, &second).unwrap();
+        assert_eq!(psnr.score, psnr_from_mse(6., u8::RANGE));
+        assert_eq!(psnr.channels[0].score, f64::INFINITY);
+    }
+
+    #[test]
+    fn rgb_channels_match_combined() {
+        let first = RgbImage::from_fn(4, 4, |col, row| Rgb([col as u8 * 20, row as u8 * 30, 7]));
+        let second = RgbImage::from_fn(4, 4, |col, _| Rgb([col as u8 * 20, 0, 9]));
+        for algorithm in [
+            Algorithm::RootMeanSquared,
+            Algorithm::MeanSquaredError,
+            Algorithm::PeakSignalToNoiseRatio,
+        ] {
+            let combined = rgb_similarity_structure(&algorithm, &first, &second).unwrap();
+            let channels = rgb_similarity_structure_channels(&algorithm, &first, &second).unwrap();
+            assert_eq!(combined.score, channels.score);
+            assert_eq!(channels.channels.len(), 3);
+        }
+    }
+
+    #[test]
+    fn rgb_pixels_over_tolerance_counts_pixels() {
+        let first = RgbImage::from_pixel(2, 2, Rgb([10, 10, 10]));
+        let mut second = first.clone();
+        second.put_pixel(0, 0, Rgb([20, 20, 10]));
+        second.put_pixel(1, 1, Rgb([10, 10, 13]));
+        let algorithm = Algorithm::PixelsOverTolerance { tolerance: 2. };
+        let result = rgb_similarity_structure_channels(&algorithm, &first, &second).unwrap();
+        assert_eq!(result.score, 0.5);
+        assert_eq!(result.channels[0].score, 0.25);
+        let peak =
+            rgb_similarity_structure(&Algorithm::PeakAbsoluteError, &first, &second).unwrap();
+        assert_eq!(peak.score, 10.);
+        let mae = rgb_similarity_structure(&Algorithm::MeanAbsoluteError, &first, &second).unwrap();
+        assert_eq!(mae.score, 23. / 12.);
+    }
+
+    #[test]
+    fn dimensions_differ_test_pixelmatch() {
+        let first = RgbImage::new(1, 1);
+        let second = RgbImage::new(2, 2);
+        let result = rgb_pixelmatch_compare(&first, &second, &PixelmatchConfig::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn dimensions_differ_test_flip() {
+        let first = RgbImage::new(1, 1);
+        let second = RgbImage::new(2, 2);
+        let result = rgb_similarity_flip(&first, &second, &FlipConfig::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn dimensions_differ_test_color_difference() {
+        let first = RgbImage::new(1, 1);
+        let second = RgbImage::new(2, 2);
+        let result = rgb_color_difference(DeltaE::CIEDE2000, &first, &second);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn dimensions_differ_test_gray_histos() {
         let first = GrayImage::new(1, 1);
@@ -0,0 +1,347 @@
+use crate::fsim::fsim_weighted;
+use crate::prelude::*;
+use crate::squared_error::{psnr_from_mse, squared_error_sum};
+use crate::ssim::{ms_ssim_weighted, SsimWindow};
+use crate::structure_compare;
+use crate::utils::{FloatImage, GrayBuffer, Subpixel};
+use image::Pixel;
+use itertools::izip;
+
+/// A rectangular region of the images, parts outside of the images are ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rectangle {
+    /// Column of the top left corner
+    pub x: u32,
+    /// Row of the top left corner
+    pub y: u32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+
+impl Rectangle {
+    fn contains(&self, col: u32, row: u32) -> bool {
+        (self.x..self.x.saturating_add(self.width)).contains(&col)
+            && (self.y..self.y.saturating_add(self.height)).contains(&row)
+    }
+}
+
+/// Regions that are excluded from a comparison, e.g. a clock, a cursor or an ad slot in screenshots.
+/// A pixel is masked if it is not black in [`Mask::image`] or lies within any of the [`Mask::rectangles`].
+///
+/// Masked pixels don't count towards the score at all, the score is normalized by the number of unmasked pixels.
+/// In the similarity image they are `NaN`, `to_color_map` draws them transparent.
+/// Windows and filters that partially overlap the mask, like the SSIM windows, see the pixels of the first image
+/// in both images there. So only the unmasked differences matter and each window counts with its unmasked pixels.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mask {
+    /// An image of the same dimensions as the compared images, pixels that are not black are masked
+    pub image: Option<GrayImage>,
+    /// Rectangles of masked pixels
+    pub rectangles: Vec<Rectangle>,
+}
+
+impl From<GrayImage> for Mask {
+    fn from(value: GrayImage) -> Self {
+        Mask {
+            image: Some(value),
+            rectangles: Vec::new(),
+        }
+    }
+}
+
+impl From<Vec<Rectangle>> for Mask {
+    fn from(value: Vec<Rectangle>) -> Self {
+        Mask {
+            image: None,
+            rectangles: value,
+        }
+    }
+}
+
+impl Mask {
+    /// Flags the masked pixels in row-major order, `None` if no pixel is masked
+    pub(crate) fn excluded(
+        &self,
+        (width, height): (u32, u32),
+    ) -> Result<Option<Vec<bool>>, CompareError> {
+        if let Some(image) = &self.image {
+            if image.dimensions() != (width, height) {
+                return Err(CompareError::DimensionsDiffer);
+            }
+        }
+        let is_masked = |col: u32, row: u32| {
+            self.image
+                .as_ref()
+                .is_some_and(|image| image.get_pixel(col, row)[0] > 0)
+                || self.rectangles.iter().any(|r| r.contains(col, row))
+        };
+        let excluded = (0..height)
+            .flat_map(|row| (0..width).map(move |col| (col, row)))
+            .map(|(col, row)| is_masked(col, row))
+            .collect::<Vec<_>>();
+        if !excluded.contains(&true) {
+            return Ok(None);
+        }
+        if !excluded.contains(&false) {
+            return Err(CompareError::CalculationFailed(
+                "All pixels are masked, nothing left to compare".to_owned(),
+            ));
+        }
+        Ok(Some(excluded))
+    }
+}
+
+/// Replaces the masked pixels of `second` by those of `first`,
+/// so the masked content can't leak into windows and filters around the mask
+pub(crate) fn neutralize<Px: Pixel>(
+    first: &ImageBuffer<Px, Vec<Px::Subpixel>>,
+    second: &ImageBuffer<Px, Vec<Px::Subpixel>>,
+    excluded: &[bool],
+) -> ImageBuffer<Px, Vec<Px::Subpixel>> {
+    let mut second = second.clone();
+    izip!(first.pixels(), second.pixels_mut(), excluded)
+        .filter(|(_, _, masked)| **masked)
+        .for_each(|(a, b, _)| *b = *a);
+    second
+}
+
+/// Mean of the values of the unmasked pixels
+pub(crate) fn unmasked_mean(values: impl Iterator<Item = f64>, excluded: &[bool]) -> f64 {
+    let (sum, count) = values
+        .zip(excluded)
+        .filter(|(_, masked)| !**masked)
+        .fold((0., 0usize), |(sum, count), (v, _)| (sum + v, count + 1));
+    sum / count as f64
+}
+
+/// Sets all channels of the masked pixels to `NaN`
+pub(crate) fn mark_excluded<Px: Pixel<Subpixel = f32>>(
+    image: &mut ImageBuffer<Px, Vec<f32>>,
+    excluded: &[bool],
+) {
+    image
+        .pixels_mut()
+        .zip(excluded)
+        .filter(|(_, masked)| **masked)
+        .for_each(|(p, _)| p.apply(|_| f32::NAN));
+}
+
+/// Mean of the map over the unmasked pixels at least `border` pixels away from the edges,
+/// over all unmasked pixels if there are none
+fn unmasked_map_mean(map: &GraySimilarityImage, excluded: &[bool], border: u32) -> f64 {
+    let cols = border..map.width().saturating_sub(border);
+    let rows = border..map.height().saturating_sub(border);
+    let in_border = map
+        .enumerate_pixels()
+        .zip(excluded)
+        .map(|((col, row, _), masked)| *masked || !cols.contains(&col) || !rows.contains(&row))
+        .collect::<Vec<_>>();
+    let excluded = if in_border.contains(&false) {
+        &in_border
+    } else {
+        excluded
+    };
+    unmasked_mean(map.pixels().map(|p| p[0] as f64), excluded)
+}
+
+/// The border of the gaussian windows, only pixels whose window fits into the image are averaged
+fn gaussian_border(config: &SsimConfig) -> u32 {
+    match config.window {
+        SsimWindow::Gaussian { size, .. } => size / 2,
+        _ => 0,
+    }
+}
+
+/// Structure comparison without the `excluded` pixels, a plain [`structure_compare`] for `None`:
+/// - The errors (RMS, MSE, PSNR, MAE and pixels over tolerance) are normalized by the number of unmasked pixels
+/// - GMSD is the deviation of the map over the unmasked pixels
+/// - MS-SSIM and FSIM keep their own pooling with zero weight for the masked pixels: MS-SSIM combines the
+///   means over the unmasked pixels of every scale with the scale exponents, FSIM weights the unmasked pixels by
+///   their phase congruency
+/// - All other scores, including CW-SSIM, are the mean of the map over the unmasked pixels, so every SSIM window
+///   is weighted by the number of its unmasked pixels. For gaussian windows only pixels whose window fits into the image count.
+pub(crate) fn masked_structure_compare<P: Subpixel>(
+    algorithm: &Algorithm,
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+    excluded: Option<&[bool]>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    let Some(excluded) = excluded else {
+        return structure_compare(algorithm, first, second);
+    };
+    let second = neutralize(first, second, excluded);
+    let unmasked = FloatImage::from_fn(first.width(), first.height(), |col, row| {
+        let masked = excluded[(row * first.width() + col) as usize];
+        Luma([if masked { 0. } else { 1. }])
+    });
+    let (score, mut image) = match algorithm {
+        Algorithm::MultiScaleSSIM(config) => {
+            ms_ssim_weighted(first, &second, config, Some(&unmasked))?
+        }
+        Algorithm::FSIM => fsim_weighted(first, &second, Some(&unmasked))?,
+        _ => structure_compare(algorithm, first, &second)?,
+    };
+    let unmasked = excluded.iter().filter(|masked| !**masked).count() as f64;
+    let unmasked_share = unmasked / excluded.len() as f64;
+    let score = match algorithm {
+        // the masked pixels are identical now, so only the normalization changes
+        Algorithm::MeanSquaredError
+        | Algorithm::MeanAbsoluteError
+        | Algorithm::PixelsOverTolerance { .. } => score / unmasked_share,
+        Algorithm::RootMeanSquared => 1. - (1. - score) / unmasked_share.sqrt(),
+        Algorithm::PeakSignalToNoiseRatio => {
+            psnr_from_mse(squared_error_sum(first, &second) / unmasked, P::RANGE)
+        }
+        Algorithm::PeakAbsoluteError | Algorithm::MultiScaleSSIM(_) | Algorithm::FSIM => score,
+        Algorithm::GMSD => {
+            let values = || image.pixels().map(|p| p[0] as f64);
+            let mean = unmasked_mean(values(), excluded);
+            let variance = unmasked_mean(values().map(|v| (v - mean).powi(2)), excluded);
+            1. - variance.sqrt()
+        }
+        Algorithm::MSSIMGaussian => {
+            unmasked_map_mean(&image, excluded, gaussian_border(&SsimConfig::gaussian()))
+        }
+        Algorithm::SSIM(config) => unmasked_map_mean(&image, excluded, gaussian_border(config)),
+        _ => unmasked_map_mean(&image, excluded, 0),
+    };
+    mark_excluded(&mut image, excluded);
+    Ok((score, image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(x: u32, y: u32, width: u32, height: u32) -> Mask {
+        vec![Rectangle {
+            x,
+            y,
+            width,
+            height,
+        }]
+        .into()
+    }
+
+    #[test]
+    fn excluded_pixels() {
+        assert_eq!(Mask::default().excluded((2, 2)).unwrap(), None);
+        let excluded = rectangle(1, 1, 5, 5).excluded((2, 2)).unwrap().unwrap();
+        assert_eq!(excluded, vec![false, false, false, true]);
+        let mut image = GrayImage::new(2, 2);
+        image.put_pixel(0, 0, Luma([1]));
+        let mask = Mask {
+            image: Some(image),
+            ..rectangle(1, 1, 1, 1)
+        };
+        let excluded = mask.excluded((2, 2)).unwrap().unwrap();
+        assert_eq!(excluded, vec![true, false, false, true]);
+        assert!(mask.excluded((3, 2)).is_err());
+        assert!(rectangle(0, 0, 2, 2).excluded((2, 2)).is_err());
+    }
+
+    #[test]
+    fn masked_errors_are_normalized_by_unmasked_pixels() {
+        let first = GrayImage::from_fn(4, 1, |col, _| Luma([col as u8 * 10]));
+        let second = GrayImage::from_pixel(4, 1, Luma([0]));
+        let excluded = [false, false, true, true];
+        let compare = |algorithm| {
+            masked_structure_compare(&algorithm, &first, &second, Some(&excluded)).unwrap()
+        };
+        assert_eq!(compare(Algorithm::MeanSquaredError).0, 50.);
+        assert_eq!(compare(Algorithm::MeanAbsoluteError).0, 5.);
+        assert_eq!(compare(Algorithm::PeakAbsoluteError).0, 10.);
+        assert_eq!(
+            compare(Algorithm::PeakSignalToNoiseRatio).0,
+            psnr_from_mse(50., u8::RANGE)
+        );
+        assert_eq!(
+            compare(Algorithm::PixelsOverTolerance { tolerance: 0. }).0,
+            0.5
+        );
+        let (score, map) = compare(Algorithm::RootMeanSquared);
+        assert!((score - (1. - (0.5f64 * (10. / 255f64).powi(2)).sqrt())).abs() < 1e-6);
+        assert!(map.get_pixel(2, 0)[0].is_nan());
+        assert_eq!(map.get_pixel(0, 0)[0], 1.);
+    }
+
+    #[test]
+    fn masked_content_does_not_affect_windows() {
+        let first = GrayImage::from_fn(16, 16, |col, row| {
+            Luma([((col * 37 + row * 23) % 200) as u8])
+        });
+        let mut second = first.clone();
+        second.put_pixel(3, 4, Luma([255]));
+        let excluded = rectangle(3, 4, 1, 1).excluded((16, 16)).unwrap();
+        for algorithm in [
+            Algorithm::MSSIMSimple,
+            Algorithm::MSSIMGaussian,
+            Algorithm::GMSD,
+            Algorithm::CWSSIM(CwSsimConfig::default()),
+        ] {
+            let (score, _) = structure_compare(&algorithm, &first, &second).unwrap();
+            assert!(score < 1.);
+            let (score, map) =
+                masked_structure_compare(&algorithm, &first, &second, excluded.as_deref()).unwrap();
+            assert!((score - 1.).abs() < 1e-6, "{algorithm:?}");
+            assert!(map.get_pixel(3, 4)[0].is_nan());
+        }
+    }
+
+    #[test]
+    fn masks_outside_changes_keep_the_pooling() {
+        let first = GrayImage::from_fn(64, 64, |col, row| {
+            Luma([((col * 37 + row * 23) % 200) as u8])
+        });
+        let mut second = first.clone();
+        for col in 8..16 {
+            for row in 8..16 {
+                second.put_pixel(col, row, Luma([0]));
+            }
+        }
+        let excluded = rectangle(56, 56, 2, 2).excluded((64, 64)).unwrap();
+        for algorithm in [
+            Algorithm::MultiScaleSSIM(SsimConfig::default()),
+            Algorithm::FSIM,
+            Algorithm::CWSSIM(CwSsimConfig::default()),
+        ] {
+            let (score, _) = structure_compare(&algorithm, &first, &second).unwrap();
+            let (masked, _) =
+                masked_structure_compare(&algorithm, &first, &second, excluded.as_deref()).unwrap();
+            // a few identical pixels less only change the score slightly - the plain mean of the
+            // map would be off by about 0.009 for MS-SSIM and 0.1 for FSIM
+            assert!(
+                (masked - score).abs() < 0.01 * (1. - score),
+                "{algorithm:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn partially_masked_windows_count_with_unmasked_pixels() {
+        let first = GrayImage::from_fn(16, 8, |col, row| {
+            Luma([((col * 37 + row * 23) % 200) as u8])
+        });
+        let second = GrayImage::from_fn(16, 8, |col, row| {
+            Luma([if col < 8 {
+                first.get_pixel(col, row)[0]
+            } else {
+                0
+            }])
+        });
+        let (_, map) = structure_compare(&Algorithm::MSSIMSimple, &first, &second).unwrap();
+        let different = map.get_pixel(8, 0)[0] as f64;
+        // masks half of the identical and none of the different window
+        let excluded = rectangle(0, 0, 4, 8).excluded((16, 8)).unwrap();
+        let (score, _) = masked_structure_compare(
+            &Algorithm::MSSIMSimple,
+            &first,
+            &second,
+            excluded.as_deref(),
+        )
+        .unwrap();
+        assert!((score - (1. + 2. * different) / 3.).abs() < 1e-6);
+    }
+}
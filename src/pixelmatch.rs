@@ -0,0 +1,297 @@
+use crate::prelude::*;
+use image::{Rgba, RgbaImage};
+
+/// Maximum possible value of the YIQ difference metric
+const MAX_DELTA: f64 = 35215.;
+
+/// Parameters for the pixelmatch comparison, the [`Default`] matches the defaults of pixelmatch
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelmatchConfig {
+    /// Matching threshold between 0 and 1, smaller values make the comparison more sensitive, 0.1 by default
+    pub threshold: f64,
+    /// Count anti-aliased pixels as differences instead of discounting them, false by default
+    pub include_anti_aliasing: bool,
+    /// Opacity of the original image in the unchanged parts of the diff image, 0.1 by default
+    pub alpha: f64,
+    /// Color of anti-aliased pixels in the diff image, yellow by default
+    pub anti_aliasing_color: Rgb<u8>,
+    /// Color of different pixels in the diff image, red by default
+    pub diff_color: Rgb<u8>,
+    /// Color of different pixels that got darker in the second image - `None` uses `diff_color`
+    pub diff_color_darker: Option<Rgb<u8>>,
+}
+
+impl Default for PixelmatchConfig {
+    fn default() -> Self {
+        PixelmatchConfig {
+            threshold: 0.1,
+            include_anti_aliasing: false,
+            alpha: 0.1,
+            anti_aliasing_color: Rgb([255, 255, 0]),
+            diff_color: Rgb([255, 0, 0]),
+            diff_color_darker: None,
+        }
+    }
+}
+
+/// The result of the pixelmatch comparison
+#[derive(Debug)]
+pub struct PixelmatchResult {
+    /// The diff image: different pixels in `diff_color`, anti-aliased pixels in `anti_aliasing_color`
+    /// and the rest a faded grayscale version of the first image
+    pub image: RgbImage,
+    /// Number of pixels that differ beyond the threshold and are no anti-aliasing artifacts
+    pub different_pixels: usize,
+    /// Number of pixels that differ beyond the threshold but were detected as anti-aliasing artifacts
+    pub anti_aliased_pixels: usize,
+    /// The fraction of pixels that are not different, 1.0 means no differences
+    pub score: f64,
+}
+
+fn blend(c: f64, a: f64) -> f64 {
+    255. + (c - 255.) * a
+}
+
+fn rgb_to_y(r: f64, g: f64, b: f64) -> f64 {
+    r * 0.29889531 + g * 0.58662247 + b * 0.11448223
+}
+
+fn rgb_to_i(r: f64, g: f64, b: f64) -> f64 {
+    r * 0.59597799 - g * 0.27417610 - b * 0.32180189
+}
+
+fn rgb_to_q(r: f64, g: f64, b: f64) -> f64 {
+    r * 0.21147017 - g * 0.52261711 + b * 0.31114694
+}
+
+/// The color blended onto a white background
+fn blended(pixel: &Rgba<u8>) -> [f64; 3] {
+    let [r, g, b, a] = pixel.0.map(|c| c as f64);
+    if a < 255. {
+        let a = a / 255.;
+        [blend(r, a), blend(g, a), blend(b, a)]
+    } else {
+        [r, g, b]
+    }
+}
+
+/// Perceptual color difference in YIQ space by Kotsarenko and Ramos.
+/// Negative if the first pixel is brighter, with `y_only` just the brightness difference is returned.
+fn color_delta(first: &Rgba<u8>, second: &Rgba<u8>, y_only: bool) -> f64 {
+    if first == second {
+        return 0.;
+    }
+    let [r1, g1, b1] = blended(first);
+    let [r2, g2, b2] = blended(second);
+    let (y1, y2) = (rgb_to_y(r1, g1, b1), rgb_to_y(r2, g2, b2));
+    let y = y1 - y2;
+    if y_only {
+        return y;
+    }
+    let i = rgb_to_i(r1, g1, b1) - rgb_to_i(r2, g2, b2);
+    let q = rgb_to_q(r1, g1, b1) - rgb_to_q(r2, g2, b2);
+    let delta = 0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q;
+    if y1 > y2 {
+        -delta
+    } else {
+        delta
+    }
+}
+
+/// The 3x3 neighbourhood of the pixel clipped to the image, without the pixel itself, and whether the pixel is on the border
+fn neighbours(image: &RgbaImage, col: u32, row: u32) -> (impl Iterator<Item = (u32, u32)>, bool) {
+    let (cols, rows) = (
+        col.saturating_sub(1)..=(col + 1).min(image.width() - 1),
+        row.saturating_sub(1)..=(row + 1).min(image.height() - 1),
+    );
+    let border =
+        *cols.start() == col || *cols.end() == col || *rows.start() == row || *rows.end() == row;
+    let iter = rows
+        .flat_map(move |y| cols.clone().map(move |x| (x, y)))
+        .filter(move |p| *p != (col, row));
+    (iter, border)
+}
+
+/// Whether the pixel has more than two neighbours of exactly the same color
+fn has_many_siblings(image: &RgbaImage, col: u32, row: u32) -> bool {
+    let (neighbours, border) = neighbours(image, col, row);
+    let pixel = image.get_pixel(col, row);
+    let siblings = border as usize
+        + neighbours
+            .filter(|(x, y)| image.get_pixel(*x, *y) == pixel)
+            .count();
+    siblings > 2
+}
+
+/// Anti-aliasing detection by Vysniauskas: a pixel is considered anti-aliased if it lies between a darker and a brighter
+/// neighbour, has at most two neighbours of the same brightness and the darkest or brightest neighbour is part of a
+/// flat area in both images
+fn anti_aliased(image: &RgbaImage, other: &RgbaImage, col: u32, row: u32) -> bool {
+    let (neighbours, border) = neighbours(image, col, row);
+    let pixel = image.get_pixel(col, row);
+    let mut zeroes = border as usize;
+    let (mut min, mut max) = (0., 0.);
+    let (mut min_position, mut max_position) = ((0, 0), (0, 0));
+    for (x, y) in neighbours {
+        let delta = color_delta(pixel, image.get_pixel(x, y), true);
+        if delta == 0. {
+            zeroes += 1;
+            if zeroes > 2 {
+                return false;
+            }
+        } else if delta < min {
+            min = delta;
+            min_position = (x, y);
+        } else if delta > max {
+            max = delta;
+            max_position = (x, y);
+        }
+    }
+    if min == 0. || max == 0. {
+        return false;
+    }
+    let flat =
+        |(x, y): (u32, u32)| has_many_siblings(image, x, y) && has_many_siblings(other, x, y);
+    flat(min_position) || flat(max_position)
+}
+
+pub(crate) fn pixelmatch(
+    first: &RgbaImage,
+    second: &RgbaImage,
+    config: &PixelmatchConfig,
+) -> Result<PixelmatchResult, CompareError> {
+    if !(0. ..=1.).contains(&config.threshold) {
+        return Err(CompareError::CalculationFailed(format!(
+            "Invalid pixelmatch threshold {} - needs to be between 0 and 1",
+            config.threshold
+        )));
+    }
+    let max_delta = MAX_DELTA * config.threshold * config.threshold;
+    let mut image = RgbImage::new(first.width(), first.height());
+    let mut different_pixels = 0;
+    let mut anti_aliased_pixels = 0;
+
+    for (col, row, output) in image.enumerate_pixels_mut() {
+        let (a, b) = (first.get_pixel(col, row), second.get_pixel(col, row));
+        let delta = color_delta(a, b, false);
+        *output = if delta.abs() > max_delta {
+            if !config.include_anti_aliasing
+                && (anti_aliased(first, second, col, row) || anti_aliased(second, first, col, row))
+            {
+                anti_aliased_pixels += 1;
+                config.anti_aliasing_color
+            } else {
+                different_pixels += 1;
+                match config.diff_color_darker {
+                    Some(darker) if delta < 0. => darker,
+                    _ => config.diff_color,
+                }
+            }
+        } else {
+            let [r, g, b, alpha] = a.0.map(|c| c as f64);
+            let gray = blend(rgb_to_y(r, g, b), config.alpha * alpha / 255.) as u8;
+            Rgb([gray; 3])
+        };
+    }
+
+    let pixels = first.width() as f64 * first.height() as f64;
+    Ok(PixelmatchResult {
+        image,
+        different_pixels,
+        anti_aliased_pixels,
+        score: 1. - different_pixels as f64 / pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A dark left and a bright right half with an anti-aliased edge in column 4
+    fn edge(value: u8) -> RgbaImage {
+        RgbaImage::from_fn(9, 8, |col, _| match col {
+            0..=3 => Rgba([0, 0, 0, 255]),
+            4 => Rgba([value, value, value, 255]),
+            _ => Rgba([255, 255, 255, 255]),
+        })
+    }
+
+    #[test]
+    fn identity() {
+        let image = edge(128);
+        let result = pixelmatch(&image, &image, &PixelmatchConfig::default()).unwrap();
+        assert_eq!(result.different_pixels, 0);
+        assert_eq!(result.anti_aliased_pixels, 0);
+        assert_eq!(result.score, 1.);
+        assert_eq!(result.image.get_pixel(0, 0), &Rgb([229, 229, 229]));
+    }
+
+    #[test]
+    fn color_delta_is_signed() {
+        let dark = Rgba([0, 0, 0, 255]);
+        let bright = Rgba([255, 255, 255, 255]);
+        assert!((color_delta(&dark, &bright, false) - 0.5053 * 255. * 255.).abs() < 1.);
+        assert!(color_delta(&bright, &dark, false) < 0.);
+        assert_eq!(color_delta(&dark, &dark, false), 0.);
+    }
+
+    #[test]
+    fn transparent_pixels_are_blended_on_white() {
+        let transparent = Rgba([0, 0, 0, 0]);
+        let white = Rgba([255, 255, 255, 255]);
+        assert_eq!(color_delta(&transparent, &white, false), 0.);
+    }
+
+    #[test]
+    fn anti_aliased_edge_is_discounted() {
+        let result = pixelmatch(&edge(128), &edge(60), &PixelmatchConfig::default()).unwrap();
+        assert_eq!(result.anti_aliased_pixels, 8);
+        assert_eq!(result.different_pixels, 0);
+        assert_eq!(result.image.get_pixel(4, 3), &Rgb([255, 255, 0]));
+
+        let config = PixelmatchConfig {
+            include_anti_aliasing: true,
+            ..Default::default()
+        };
+        let result = pixelmatch(&edge(128), &edge(60), &config).unwrap();
+        assert_eq!(result.different_pixels, 8);
+        assert_eq!(result.score, 1. - 8. / 72.);
+    }
+
+    #[test]
+    fn thick_edges_are_no_anti_aliasing() {
+        let first = edge(128);
+        let mut second = first.clone();
+        (0..8).for_each(|row| second.put_pixel(3, row, Rgba([128, 128, 128, 255])));
+        let result = pixelmatch(&first, &second, &PixelmatchConfig::default()).unwrap();
+        assert_eq!(result.anti_aliased_pixels, 0);
+        assert_eq!(result.different_pixels, 8);
+    }
+
+    #[test]
+    fn real_changes_are_counted() {
+        let first = edge(128);
+        let mut second = first.clone();
+        second.put_pixel(1, 4, Rgba([255, 0, 0, 255]));
+        let config = PixelmatchConfig {
+            diff_color_darker: Some(Rgb([0, 255, 0])),
+            ..Default::default()
+        };
+        let result = pixelmatch(&first, &second, &config).unwrap();
+        assert_eq!(result.different_pixels, 1);
+        assert_eq!(result.anti_aliased_pixels, 0);
+        assert_eq!(result.image.get_pixel(1, 4), &Rgb([255, 0, 0]));
+        let result = pixelmatch(&second, &first, &config).unwrap();
+        assert_eq!(result.image.get_pixel(1, 4), &Rgb([0, 255, 0]));
+    }
+
+    #[test]
+    fn invalid_threshold() {
+        let image = edge(128);
+        let config = PixelmatchConfig {
+            threshold: 2.,
+            ..Default::default()
+        };
+        assert!(pixelmatch(&image, &image, &config).is_err());
+    }
+}
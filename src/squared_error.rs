@@ -1,20 +1,91 @@
 use crate::prelude::*;
+use crate::utils::{GrayBuffer, Subpixel};
 use itertools::izip;
 
-pub(crate) fn root_mean_squared_error_simple(
-    first: &GrayImage,
-    second: &GrayImage,
-) -> Result<(f64, GraySimilarityImage), CompareError> {
+/// Per pixel map of `1 - |diff| / range`
+fn absolute_error_map<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> GraySimilarityImage {
     let dimension = first.dimensions();
     let mut image = GraySimilarityImage::new(dimension.0, dimension.1);
     let iter = izip!(first.pixels(), second.pixels(), image.pixels_mut());
 
     iter.for_each(|(a, b, c)| {
-        let diff = a[0] as i32 - b[0] as i32;
-        let normalized = diff as f32 / u8::MAX as f32;
+        let diff = a[0].into() - b[0].into();
+        let normalized = diff as f32 / P::RANGE as f32;
         let squared_root = 1. - normalized.abs();
         *c = Luma([squared_root]);
     });
+    image
+}
+
+fn absolute_errors<'a, P: Subpixel>(
+    first: &'a GrayBuffer<P>,
+    second: &'a GrayBuffer<P>,
+) -> impl Iterator<Item = f64> + 'a {
+    first
+        .pixels()
+        .zip(second.pixels())
+        .map(|(a, b)| (a[0].into() - b[0].into()).abs())
+}
+
+/// Sum of the absolute pixel differences, the base of MAE
+pub(crate) fn absolute_error_sum<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> f64 {
+    absolute_errors(first, second).sum()
+}
+
+/// Flags for every pixel whether its difference exceeds the tolerance
+pub(crate) fn over_tolerance<'a, P: Subpixel>(
+    first: &'a GrayBuffer<P>,
+    second: &'a GrayBuffer<P>,
+    tolerance: f64,
+) -> impl Iterator<Item = bool> + 'a {
+    absolute_errors(first, second).map(move |e| e > tolerance)
+}
+
+pub(crate) fn mean_absolute_error<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    let mae = absolute_error_sum(first, second) / first.pixels().len() as f64;
+    Ok((mae, absolute_error_map(first, second)))
+}
+
+pub(crate) fn peak_absolute_error<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    let peak = absolute_errors(first, second).fold(0., f64::max);
+    Ok((peak, absolute_error_map(first, second)))
+}
+
+/// The fraction of pixels that differ by more than `tolerance`, the map is 0 for these pixels and 1 otherwise
+pub(crate) fn pixels_over_tolerance<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+    tolerance: f64,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    let mut image = GraySimilarityImage::new(first.width(), first.height());
+    let mut count = 0;
+    image
+        .pixels_mut()
+        .zip(over_tolerance(first, second, tolerance))
+        .for_each(|(p, over)| {
+            count += over as usize;
+            *p = Luma([if over { 0. } else { 1. }]);
+        });
+    Ok((count as f64 / first.pixels().len() as f64, image))
+}
+
+pub(crate) fn root_mean_squared_error_simple<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    let image = absolute_error_map(first, second);
 
     let score: f64 = 1.
         - (image
@@ -26,6 +97,52 @@ pub(crate) fn root_mean_squared_error_simple(
     Ok((score, image))
 }
 
+/// Sum of the squared pixel differences, the base of MSE and PSNR
+pub(crate) fn squared_error_sum<P: Subpixel>(first: &GrayBuffer<P>, second: &GrayBuffer<P>) -> f64 {
+    first
+        .pixels()
+        .zip(second.pixels())
+        .map(|(a, b)| (a[0].into() - b[0].into()).powi(2))
+        .sum()
+}
+
+/// PSNR in dB for the given MSE and peak value `range`, infinite for identical images
+pub(crate) fn psnr_from_mse(mse: f64, range: f64) -> f64 {
+    if mse == 0. {
+        return f64::INFINITY;
+    }
+    10. * (range.powi(2) / mse).log10()
+}
+
+/// Per pixel map of `1 - (diff / range)^2`, so identical pixels are 1 like for the other algorithms
+fn squared_error_map<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> GraySimilarityImage {
+    let mut image = GraySimilarityImage::new(first.width(), first.height());
+    izip!(first.pixels(), second.pixels(), image.pixels_mut()).for_each(|(a, b, c)| {
+        let normalized = (a[0].into() as f32 - b[0].into() as f32) / P::RANGE as f32;
+        *c = Luma([1. - normalized * normalized]);
+    });
+    image
+}
+
+pub(crate) fn mean_squared_error<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    let mse = squared_error_sum(first, second) / first.pixels().len() as f64;
+    Ok((mse, squared_error_map(first, second)))
+}
+
+pub(crate) fn peak_signal_to_noise_ratio<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    let (mse, image) = mean_squared_error(first, second)?;
+    Ok((psnr_from_mse(mse, P::RANGE), image))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,4 +202,66 @@ mod tests {
         let result = 1. - ((127. / 255.0f64).powi(2) / (width * height) as f64).sqrt();
         assert!((comparison - result).abs() < 1e-5);
     }
+
+    #[test]
+    fn mse_and_psnr() {
+        let first = GrayImage::from_fn(2, 1, |col, _| Luma([col as u8 * 10]));
+        let second = GrayImage::from_pixel(2, 1, Luma([0]));
+        let (mse, map) = mean_squared_error(&first, &second).unwrap();
+        assert_eq!(mse, 50.);
+        assert_eq!(map.get_pixel(0, 0)[0], 1.);
+        assert_eq!(map.get_pixel(1, 0)[0], 1. - (10. / 255f32).powi(2));
+        let (psnr, _) = peak_signal_to_noise_ratio(&first, &second).unwrap();
+        assert!((psnr - 10. * (255. * 255. / 50f64).log10()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn range_of_16_bit_images() {
+        let first = GrayBuffer::<u16>::from_fn(2, 1, |col, _| Luma([col as u16 * 2570]));
+        let second = GrayBuffer::<u16>::from_pixel(2, 1, Luma([0]));
+        let (score, map) = root_mean_squared_error_simple(&first, &second).unwrap();
+        let (score_8_bit, map_8_bit) = root_mean_squared_error_simple(
+            &GrayImage::from_fn(2, 1, |col, _| Luma([col as u8 * 10])),
+            &GrayImage::from_pixel(2, 1, Luma([0])),
+        )
+        .unwrap();
+        assert_eq!(score, score_8_bit);
+        assert_eq!(map, map_8_bit);
+        let (psnr, _) = peak_signal_to_noise_ratio(&first, &second).unwrap();
+        assert!((psnr - 10. * (65535f64.powi(2) / (2570f64.powi(2) / 2.)).log10()).abs() < 1e-12);
+        let (mae, _) = mean_absolute_error(&first, &second).unwrap();
+        assert_eq!(mae, 1285.);
+    }
+
+    #[test]
+    fn psnr_identity_is_infinite() {
+        let image = GrayImage::from_pixel(3, 3, Luma([77]));
+        let (mse, _) = mean_squared_error(&image, &image).unwrap();
+        let (psnr, _) = peak_signal_to_noise_ratio(&image, &image).unwrap();
+        assert_eq!(mse, 0.);
+        assert_eq!(psnr, f64::INFINITY);
+    }
+
+    #[test]
+    fn absolute_errors() {
+        let first = GrayImage::from_fn(4, 1, |col, _| Luma([col as u8 * 10]));
+        let second = GrayImage::from_pixel(4, 1, Luma([10]));
+        let (mae, map) = mean_absolute_error(&first, &second).unwrap();
+        assert_eq!(mae, (10. + 0. + 10. + 20.) / 4.);
+        assert_eq!(map.get_pixel(1, 0)[0], 1.);
+        let (peak, _) = peak_absolute_error(&first, &second).unwrap();
+        assert_eq!(peak, 20.);
+    }
+
+    #[test]
+    fn pixels_over_tolerance_marks_offending_pixels() {
+        let first = GrayImage::from_fn(4, 1, |col, _| Luma([col as u8 * 10]));
+        let second = GrayImage::from_pixel(4, 1, Luma([10]));
+        let (fraction, map) = pixels_over_tolerance(&first, &second, 10.).unwrap();
+        assert_eq!(fraction, 0.25);
+        assert_eq!(map.get_pixel(3, 0)[0], 0.);
+        assert_eq!(map.get_pixel(0, 0)[0], 1.);
+        let (fraction, _) = pixels_over_tolerance(&first, &second, 0.).unwrap();
+        assert_eq!(fraction, 0.75);
+    }
 }
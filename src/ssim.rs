@@ -1,51 +1,613 @@
 use crate::prelude::*;
-use crate::utils::{draw_window_to_image, Window};
+use crate::utils::{
+    downsample, draw_window_to_image, filter_separable, gaussian_kernel, to_float_image,
+    to_similarity_image, FloatImage, GrayBuffer, Subpixel, SummedAreaTable, Window,
+};
+use image::Primitive;
 use rayon::prelude::*;
 
 const DEFAULT_WINDOW_SIZE: u32 = 8;
+const DEFAULT_GAUSSIAN_SIZE: u32 = 11;
+const DEFAULT_GAUSSIAN_SIGMA: f64 = 1.5;
 const K1: f64 = 0.01;
 const K2: f64 = 0.03;
-const L: u8 = u8::MAX;
-const C1: f64 = (K1 * L as f64) * (K1 * L as f64);
-const C2: f64 = (K2 * L as f64) * (K2 * L as f64);
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
 
-pub(crate) fn ssim_simple(
-    first: &GrayImage,
-    second: &GrayImage,
+/// The windows over which the SSIM statistics are collected.
+///
+/// Blocks and sliding windows keep the statistics of the original 8x8 px MSSIM: instead of variances and covariance
+/// they use the sums of squared deviations over an 8x8 px window, i.e. 64 times the variances. For other sizes
+/// the statistics are rescaled to this window area, so `k2` has the same effect for every size.
+/// Gaussian windows use plain variances like the reference implementation, so `c2` stabilizes them 64 times stronger.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SsimWindow {
+    /// Non-overlapping square blocks, each block gets a single SSIM value
+    Blocks {
+        /// Edge length of the blocks in pixels
+        size: u32,
+    },
+    /// Overlapping square windows - the image is split into tiles of `stride` x `stride` pixels and each tile gets the SSIM of the window centered on it
+    Sliding {
+        /// Edge length of the windows in pixels
+        size: u32,
+        /// Distance between the centers of neighbouring windows in pixels
+        stride: u32,
+    },
+    /// Gaussian weighted windows centered on every pixel, the score only averages pixels whose window fits into the image
+    Gaussian {
+        /// Edge length of the window in pixels, the kernel reaches `size / 2` pixels from the center in every direction
+        size: u32,
+        /// Standard deviation of the gaussian weights in pixels
+        sigma: f64,
+    },
+}
+
+impl SsimWindow {
+    fn validate(&self) -> Result<(), CompareError> {
+        let valid = match self {
+            SsimWindow::Blocks { size } => *size > 0,
+            SsimWindow::Sliding { size, stride } => *size > 0 && *stride > 0,
+            SsimWindow::Gaussian { size, sigma } => *size > 0 && *sigma > 0.,
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(CompareError::CalculationFailed(format!(
+                "Invalid SSIM window: {self:?} - sizes, strides and sigma need to be positive"
+            )))
+        }
+    }
+
+    /// The edge length of the area that contributes to a single SSIM value
+    fn extent(&self) -> u32 {
+        match self {
+            SsimWindow::Blocks { size } | SsimWindow::Sliding { size, .. } => *size,
+            SsimWindow::Gaussian { size, .. } => 2 * (size / 2) + 1,
+        }
+    }
+}
+
+/// Parameters for the SSIM based algorithms:
+/// <img src="https://render.githubusercontent.com/render/math?math=c_1=(k_1L)^2"> and <img src="https://render.githubusercontent.com/render/math?math=c_2=(k_2L)^2"> stabilize the division for dark and flat regions.
+///
+/// The [`Default`] is the 8x8 px block configuration of [`Algorithm::MSSIMSimple`],
+/// [`SsimConfig::gaussian`] provides the configuration of the reference implementation by Wang et al.
+/// Window sizes, `k1`, `k2` and the dynamic range need to be positive, otherwise the comparison fails with [`CompareError::CalculationFailed`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SsimConfig {
+    /// The windows the statistics are collected over
+    pub window: SsimWindow,
+    /// Constant for the luminance term, 0.01 by default
+    pub k1: f64,
+    /// Constant for the contrast and structure terms, 0.03 by default.
+    /// Its effect does not depend on the window size, but differs between gaussian windows and the others, see [`SsimWindow`]
+    pub k2: f64,
+    /// The dynamic range `L` of the pixel values - `None` uses the full range of the pixel type (255 for 8 bit and 65535 for 16 bit images)
+    pub dynamic_range: Option<f64>,
+}
+
+impl Default for SsimConfig {
+    fn default() -> Self {
+        SsimConfig {
+            window: SsimWindow::Blocks {
+                size: DEFAULT_WINDOW_SIZE,
+            },
+            k1: K1,
+            k2: K2,
+            dynamic_range: None,
+        }
+    }
+}
+
+impl SsimConfig {
+    /// The configuration of the reference implementation by Wang et al.: an 11x11 px gaussian window with sigma 1.5
+    pub fn gaussian() -> Self {
+        SsimConfig {
+            window: SsimWindow::Gaussian {
+                size: DEFAULT_GAUSSIAN_SIZE,
+                sigma: DEFAULT_GAUSSIAN_SIGMA,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Overlapping 8x8 px windows with the given stride
+    pub fn sliding(stride: u32) -> Self {
+        SsimConfig {
+            window: SsimWindow::Sliding {
+                size: DEFAULT_WINDOW_SIZE,
+                stride,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn validate(&self) -> Result<(), CompareError> {
+        self.window.validate()?;
+        let positive = |v: f64| v.is_finite() && v > 0.;
+        if positive(self.k1) && positive(self.k2) && self.dynamic_range.is_none_or(positive) {
+            Ok(())
+        } else {
+            Err(CompareError::CalculationFailed(format!(
+                "Invalid SSIM constants: k1 {}, k2 {}, dynamic range {:?} - they need to be positive and finite",
+                self.k1, self.k2, self.dynamic_range
+            )))
+        }
+    }
+
+    fn constants<P: Subpixel>(&self) -> Constants {
+        let range = self.dynamic_range.unwrap_or(P::RANGE);
+        Constants {
+            c1: (self.k1 * range) * (self.k1 * range),
+            c2: (self.k2 * range) * (self.k2 * range),
+        }
+    }
+}
+
+/// The stabilization constants c1 and c2
+struct Constants {
+    c1: f64,
+    c2: f64,
+}
+
+impl Default for Constants {
+    fn default() -> Self {
+        SsimConfig::default().constants::<u8>()
+    }
+}
+
+/// First and second order statistics of two images within a window
+struct Statistics {
+    mean_x: f64,
+    mean_y: f64,
+    variance_x: f64,
+    variance_y: f64,
+    covariance: f64,
+}
+
+impl Statistics {
+    fn ssim(&self, constants: &Constants) -> f64 {
+        let (c1, c2) = (constants.c1, constants.c2);
+        let counter = (2. * self.mean_x * self.mean_y + c1) * (2. * self.covariance + c2);
+        let denominator = (self.mean_x.powi(2) + self.mean_y.powi(2) + c1)
+            * (self.variance_x + self.variance_y + c2);
+        counter / denominator
+    }
+
+    fn luminance(&self, constants: &Constants) -> f64 {
+        (2. * self.mean_x * self.mean_y + constants.c1)
+            / (self.mean_x.powi(2) + self.mean_y.powi(2) + constants.c1)
+    }
+
+    fn contrast_structure(&self, constants: &Constants) -> f64 {
+        (2. * self.covariance + constants.c2) / (self.variance_x + self.variance_y + constants.c2)
+    }
+
+    /// Scales the second order statistics, e.g. to the area of the reference window
+    fn scaled(self, factor: f64) -> Statistics {
+        Statistics {
+            variance_x: self.variance_x * factor,
+            variance_y: self.variance_y * factor,
+            covariance: self.covariance * factor,
+            ..self
+        }
+    }
+
+    fn deviations(&self) -> (f64, f64) {
+        (
+            self.variance_x.max(0.).sqrt(),
+            self.variance_y.max(0.).sqrt(),
+        )
+    }
+
+    fn contrast(&self, constants: &Constants) -> f64 {
+        let (deviation_x, deviation_y) = self.deviations();
+        (2. * deviation_x * deviation_y + constants.c2)
+            / (self.variance_x + self.variance_y + constants.c2)
+    }
+
+    /// Uses the usual c3 = c2 / 2, so luminance * contrast * structure equals the SSIM
+    fn structure(&self, constants: &Constants) -> f64 {
+        let (deviation_x, deviation_y) = self.deviations();
+        let c3 = constants.c2 / 2.;
+        (self.covariance + c3) / (deviation_x * deviation_y + c3)
+    }
+}
+
+/// Summed-area tables of both images, their squares and their product.
+/// They provide the statistics of any window in constant time, which keeps overlapping windows linear in the image size.
+struct WindowSums {
+    sum_x: SummedAreaTable,
+    sum_y: SummedAreaTable,
+    square_x: SummedAreaTable,
+    square_y: SummedAreaTable,
+    cross: SummedAreaTable,
+}
+
+impl WindowSums {
+    fn new(first: &FloatImage, second: &FloatImage) -> WindowSums {
+        let (width, height) = first.dimensions();
+        let table = |f: fn(f64, f64) -> f64| {
+            SummedAreaTable::from_fn(width, height, |col, row| {
+                f(first.get_pixel(col, row)[0], second.get_pixel(col, row)[0])
+            })
+        };
+        WindowSums {
+            sum_x: table(|x, _| x),
+            sum_y: table(|_, y| y),
+            square_x: table(|x, _| x * x),
+            square_y: table(|_, y| y * y),
+            cross: table(|x, y| x * y),
+        }
+    }
+
+    /// Same statistics as [`statistics_for_window`] - sums of squared deviations, not normalized by the area
+    fn statistics(&self, window: &Window) -> Statistics {
+        let area = window.area() as f64;
+        let sum_x = self.sum_x.sum(window);
+        let sum_y = self.sum_y.sum(window);
+        let mean_x = sum_x / area;
+        let mean_y = sum_y / area;
+        Statistics {
+            mean_x,
+            mean_y,
+            variance_x: self.square_x.sum(window) - sum_x * mean_x,
+            variance_y: self.square_y.sum(window) - sum_y * mean_y,
+            covariance: self.cross.sum(window) - sum_x * mean_y,
+        }
+    }
+}
+
+/// Gaussian weighted first and second order statistics of two images for every pixel
+struct GaussianStatistics {
+    mean_x: FloatImage,
+    mean_y: FloatImage,
+    square_x: FloatImage,
+    square_y: FloatImage,
+    cross: FloatImage,
+}
+
+impl GaussianStatistics {
+    fn new(x: &FloatImage, y: &FloatImage, size: u32, sigma: f64) -> GaussianStatistics {
+        let kernel = gaussian_kernel(sigma, size / 2);
+        let product = |a: &FloatImage, b: &FloatImage| {
+            let mut result = a.clone();
+            result
+                .pixels_mut()
+                .zip(b.pixels())
+                .for_each(|(r, b)| r[0] *= b[0]);
+            result
+        };
+        GaussianStatistics {
+            mean_x: filter_separable(x, &kernel),
+            mean_y: filter_separable(y, &kernel),
+            square_x: filter_separable(&product(x, x), &kernel),
+            square_y: filter_separable(&product(y, y), &kernel),
+            cross: filter_separable(&product(x, y), &kernel),
+        }
+    }
+
+    fn statistics(&self, col: u32, row: u32) -> Statistics {
+        let mean_x = self.mean_x.get_pixel(col, row)[0];
+        let mean_y = self.mean_y.get_pixel(col, row)[0];
+        Statistics {
+            mean_x,
+            mean_y,
+            variance_x: self.square_x.get_pixel(col, row)[0] - mean_x.powi(2),
+            variance_y: self.square_y.get_pixel(col, row)[0] - mean_y.powi(2),
+            covariance: self.cross.get_pixel(col, row)[0] - mean_x * mean_y,
+        }
+    }
+}
+
+/// Mean over all pixels whose gaussian window lies completely inside the image.
+/// If the image is too small for that, the mean over all pixels is used.
+fn valid_region_mean(map: &FloatImage, border: u32) -> f64 {
+    let valid_cols = border..map.width().saturating_sub(border);
+    let valid_rows = border..map.height().saturating_sub(border);
+    let has_valid_region = !valid_cols.is_empty() && !valid_rows.is_empty();
+    let (sum, count) = map
+        .enumerate_pixels()
+        .filter(|(col, row, _)| {
+            !has_valid_region || (valid_cols.contains(col) && valid_rows.contains(row))
+        })
+        .fold((0., 0usize), |(sum, count), (_, _, p)| {
+            (sum + p[0], count + 1)
+        });
+    sum / count as f64
+}
+
+/// Mean of the map weighted per pixel by `weights` within the valid region like [`valid_region_mean`],
+/// over the whole map if the valid region has no weight
+fn weighted_valid_region_mean(map: &FloatImage, weights: &FloatImage, border: u32) -> f64 {
+    let valid_cols = border..map.width().saturating_sub(border);
+    let valid_rows = border..map.height().saturating_sub(border);
+    let weighted_sums = |in_region: &dyn Fn(u32, u32) -> bool| {
+        map.enumerate_pixels()
+            .zip(weights.pixels())
+            .filter(|((col, row, _), _)| in_region(*col, *row))
+            .fold((0., 0.), |(sum, weight_sum), ((_, _, p), w)| {
+                (sum + p[0] * w[0], weight_sum + w[0])
+            })
+    };
+    let (sum, weight_sum) =
+        weighted_sums(&|col, row| valid_cols.contains(&col) && valid_rows.contains(&row));
+    if weight_sum > 0. {
+        return sum / weight_sum;
+    }
+    let (sum, weight_sum) = weighted_sums(&|_, _| true);
+    sum / weight_sum
+}
+
+/// Area weighted mean of the per-window results and the map with every result drawn to its area
+fn collect_windows<const N: usize>(
+    results: &[([f64; N], &Window)],
+    width: u32,
+    height: u32,
+) -> [(f64, FloatImage); N] {
+    let area = results.iter().map(|r| r.1.area() as f64).sum::<f64>();
+    std::array::from_fn(|i| {
+        let score = results
+            .iter()
+            .map(|r| r.0[i] * r.1.area() as f64)
+            .sum::<f64>()
+            / area;
+        let mut map = FloatImage::new(width, height);
+        results
+            .iter()
+            .for_each(|r| draw_window_to_image(r.1, &mut map, r.0[i]));
+        (score, map)
+    })
+}
+
+/// Evaluates `f` on the statistics of all windows of the given kind.
+/// Returns the mean score and the per pixel map for each of the `N` outputs of `f`.
+fn evaluate<const N: usize>(
+    x: &FloatImage,
+    y: &FloatImage,
+    window: &SsimWindow,
+    f: impl Fn(&Statistics) -> [f64; N] + Sync,
+) -> [(f64, FloatImage); N] {
+    let (width, height) = x.dimensions();
+    let bounds = Window::from_image(x);
+    // sums of squared deviations are kept on the scale of the default 8x8 px window
+    let area_scale = |size: u32| (DEFAULT_WINDOW_SIZE as f64 / size as f64).powi(2);
+    match window {
+        SsimWindow::Blocks { size } => {
+            let windows = bounds.subdivide_by_offset(*size);
+            let scale = area_scale(*size);
+            let results = windows
+                .par_iter()
+                .map(|w| (f(&statistics_for_window(x, y, w).scaled(scale)), w))
+                .collect::<Vec<_>>();
+            collect_windows(&results, width, height)
+        }
+        SsimWindow::Sliding { size, stride } => {
+            let sums = WindowSums::new(x, y);
+            let tiles = bounds.subdivide_by_offset(*stride);
+            let scale = area_scale(*size);
+            let results = tiles
+                .par_iter()
+                .map(|tile| {
+                    // window sized tiles are their own windows, so the clipped tiles at the
+                    // right and bottom edges match blocks instead of reaching back into their neighbours
+                    let window = if stride == size {
+                        Window::new(tile.top_left, tile.bottom_right)
+                    } else {
+                        let center = (
+                            (tile.top_left.0 + stride / 2).min(tile.bottom_right.0),
+                            (tile.top_left.1 + stride / 2).min(tile.bottom_right.1),
+                        );
+                        Window::around(center, *size, &bounds)
+                    };
+                    (f(&sums.statistics(&window).scaled(scale)), tile)
+                })
+                .collect::<Vec<_>>();
+            collect_windows(&results, width, height)
+        }
+        SsimWindow::Gaussian { size, sigma } => {
+            let statistics = GaussianStatistics::new(x, y, *size, *sigma);
+            let mut maps: [FloatImage; N] = std::array::from_fn(|_| FloatImage::new(width, height));
+            for row in 0..height {
+                for col in 0..width {
+                    let values = f(&statistics.statistics(col, row));
+                    maps.iter_mut()
+                        .zip(values)
+                        .for_each(|(map, v)| map.put_pixel(col, row, Luma([v])));
+                }
+            }
+            maps.map(|map| (valid_region_mean(&map, size / 2), map))
+        }
+    }
+}
+
+/// SSIM over the windows configured in `config`:
+/// - Blocks: one value per block, the score is the area weighted mean
+/// - Sliding: one value per `stride` x `stride` tile from the window centered on it.
+///   With a stride of 1 each pixel contains the SSIM of the window around it,
+///   with a stride of the window size every tile is its own window, which is equivalent to blocks - also for the clipped tiles at the edges.
+///   The window statistics are taken from summed-area tables, so the runtime does not depend on the stride.
+/// - Gaussian: as in the reference implementation by Wang et al. the statistics are gaussian weighted around every pixel.
+///   The score is the mean over all pixels whose window lies completely inside the image.
+///   If the image is too small for that, the mean over all pixels with border-clipped windows is used.
+pub(crate) fn ssim<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+    config: &SsimConfig,
 ) -> Result<(f64, GraySimilarityImage), CompareError> {
-    let dimension = first.dimensions();
-    let mut image = GraySimilarityImage::new(dimension.0, dimension.1);
-    let window = Window::from_image(first);
-    let windows = window.subdivide_by_offset(DEFAULT_WINDOW_SIZE);
-    let results = windows
-        .par_iter()
-        .map(|w| (ssim_for_window(first, second, w), w))
-        .collect::<Vec<_>>();
-    let score = results.iter().map(|r| r.0 * r.1.area() as f64).sum::<f64>()
-        / results.iter().map(|r| r.1.area() as f64).sum::<f64>();
-
-    results
+    config.validate()?;
+    let constants = config.constants::<P>();
+    let [(score, map)] = evaluate(
+        &to_float_image(first),
+        &to_float_image(second),
+        &config.window,
+        |s| [s.ssim(&constants)],
+    );
+    Ok((score, to_similarity_image(&map)))
+}
+
+/// One factor of the SSIM product
+#[derive(Debug)]
+pub struct SsimComponent {
+    /// The per pixel values of the factor
+    pub image: GraySimilarityImage,
+    /// The mean of the factor, calculated like the SSIM score of the respective window type
+    pub score: f64,
+}
+
+/// SSIM split into its three factors, see [`crate::gray_similarity_ssim_components`]
+#[derive(Debug)]
+pub struct SsimComponents {
+    /// The usual SSIM result, identical to [`crate::gray_similarity_structure`] with [`Algorithm::SSIM`]
+    pub similarity: Similarity,
+    /// <img src="https://render.githubusercontent.com/render/math?math=l(x,y)=\frac{2\mu_x\mu_y%2Bc_1}{\mu_x^2%2B\mu_y^2%2Bc_1}"> - drops for brightness shifts
+    pub luminance: SsimComponent,
+    /// <img src="https://render.githubusercontent.com/render/math?math=c(x,y)=\frac{2\sigma_x\sigma_y%2Bc_2}{\sigma_x^2%2B\sigma_y^2%2Bc_2}"> - drops for contrast changes
+    pub contrast: SsimComponent,
+    /// <img src="https://render.githubusercontent.com/render/math?math=s(x,y)=\frac{\sigma_{xy}%2Bc_3}{\sigma_x\sigma_y%2Bc_3}"> with <img src="https://render.githubusercontent.com/render/math?math=c_3=c_2/2"> - drops for actual structural changes
+    pub structure: SsimComponent,
+}
+
+pub(crate) fn ssim_components<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+    config: &SsimConfig,
+) -> Result<SsimComponents, CompareError> {
+    config.validate()?;
+    let constants = config.constants::<P>();
+    let [ssim, luminance, contrast, structure] = evaluate(
+        &to_float_image(first),
+        &to_float_image(second),
+        &config.window,
+        |s| {
+            [
+                s.ssim(&constants),
+                s.luminance(&constants),
+                s.contrast(&constants),
+                s.structure(&constants),
+            ]
+        },
+    );
+    let component = |(score, map): (f64, FloatImage)| SsimComponent {
+        image: to_similarity_image(&map),
+        score,
+    };
+    let ssim = component(ssim);
+    Ok(SsimComponents {
+        similarity: Similarity {
+            image: ssim.image.into(),
+            score: ssim.score,
+        },
+        luminance: component(luminance),
+        contrast: component(contrast),
+        structure: component(structure),
+    })
+}
+
+/// Multi-scale SSIM as proposed by Wang, Simoncelli and Bovik:
+/// The images are repeatedly downsampled by a factor of two. On every scale contrast and structure are compared,
+/// luminance only on the coarsest scale, all using the configured windows.
+/// The mean values per scale are combined with the standard exponents, negative means are clamped to zero.
+/// If the images are too small for five scales, fewer scales with renormalized exponents are used.
+/// The map contains the same product evaluated per pixel with the coarser scales upsampled.
+pub(crate) fn ms_ssim<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+    config: &SsimConfig,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    ms_ssim_weighted(first, second, config, None)
+}
+
+/// [`ms_ssim`] with the pixels weighted by `weights` in the means of every scale, e.g. 0 for masked pixels.
+/// The weights are downsampled along with the images, so coarser pixels count with their share of weight.
+pub(crate) fn ms_ssim_weighted<P: Subpixel>(
+    first: &GrayBuffer<P>,
+    second: &GrayBuffer<P>,
+    config: &SsimConfig,
+    weights: Option<&FloatImage>,
+) -> Result<(f64, GraySimilarityImage), CompareError> {
+    config.validate()?;
+    let constants = config.constants::<P>();
+    let border = match config.window {
+        SsimWindow::Gaussian { size, .. } => size / 2,
+        _ => 0,
+    };
+    let mut x = to_float_image(first);
+    let mut y = to_float_image(second);
+    let mut weights = weights.cloned();
+    let mut contrast_structure = Vec::new();
+    let luminance = loop {
+        let pool = |(mean, map): (f64, FloatImage)| match &weights {
+            Some(weights) => (weighted_valid_region_mean(&map, weights, border), map),
+            None => (mean, map),
+        };
+        let [luminance, cs] = evaluate(&x, &y, &config.window, |s| {
+            [s.luminance(&constants), s.contrast_structure(&constants)]
+        })
+        .map(pool);
+        contrast_structure.push(cs);
+        let next_fits = x.width().min(x.height()).div_ceil(2) >= config.window.extent();
+        if contrast_structure.len() == MS_SSIM_WEIGHTS.len() || !next_fits {
+            break luminance;
+        }
+        x = downsample(&x, 2);
+        y = downsample(&y, 2);
+        weights = weights.map(|weights| downsample(&weights, 2));
+    };
+
+    let weights = &MS_SSIM_WEIGHTS[..contrast_structure.len()];
+    let weight_sum = weights.iter().sum::<f64>();
+    let weights = weights.iter().map(|w| w / weight_sum).collect::<Vec<_>>();
+    let luminance_weight = *weights
+        .last()
+        .expect("At least one scale is always evaluated");
+
+    let score = contrast_structure
         .iter()
-        .for_each(|r| draw_window_to_image(r.1, &mut image, r.0 as f32));
+        .zip(weights.iter())
+        .map(|((mean, _), weight)| mean.max(0.).powf(*weight))
+        .product::<f64>()
+        * luminance.0.max(0.).powf(luminance_weight);
+
+    let (width, height) = first.dimensions();
+    let mut image = GraySimilarityImage::new(width, height);
+    for (col, row, pixel) in image.enumerate_pixels_mut() {
+        let at_scale =
+            |map: &FloatImage, scale: usize| map.get_pixel(col >> scale, row >> scale)[0].max(0.);
+        let coarsest = contrast_structure.len() - 1;
+        let value = contrast_structure
+            .iter()
+            .zip(weights.iter())
+            .enumerate()
+            .map(|(scale, ((_, map), weight))| at_scale(map, scale).powf(*weight))
+            .product::<f64>()
+            * at_scale(&luminance.1, coarsest).powf(luminance_weight);
+        *pixel = Luma([value as f32]);
+    }
 
     Ok((score, image))
 }
 
-fn ssim_for_window(first: &GrayImage, second: &GrayImage, window: &Window) -> f64 {
+fn statistics_for_window<P: Primitive + Into<f64>>(
+    first: &ImageBuffer<Luma<P>, Vec<P>>,
+    second: &ImageBuffer<Luma<P>, Vec<P>>,
+    window: &Window,
+) -> Statistics {
     let mean_x = mean(first, window);
     let mean_y = mean(second, window);
-    let variance_x = covariance(first, mean_x, first, mean_x, window);
-    let variance_y = covariance(second, mean_y, second, mean_y, window);
-    let covariance = covariance(first, mean_x, second, mean_y, window);
-    let counter = (2. * mean_x * mean_y + C1) * (2. * covariance + C2);
-    let denominator = (mean_x.powi(2) + mean_y.powi(2) + C1) * (variance_x + variance_y + C2);
-    counter / denominator
+    Statistics {
+        mean_x,
+        mean_y,
+        variance_x: covariance(first, mean_x, first, mean_x, window),
+        variance_y: covariance(second, mean_y, second, mean_y, window),
+        covariance: covariance(first, mean_x, second, mean_y, window),
+    }
 }
 
-fn covariance(
-    image_x: &GrayImage,
+fn covariance<P: Primitive + Into<f64>>(
+    image_x: &ImageBuffer<Luma<P>, Vec<P>>,
     mean_x: f64,
-    image_y: &GrayImage,
+    image_y: &ImageBuffer<Luma<P>, Vec<P>>,
     mean_y: f64,
     window: &Window,
 ) -> f64 {
@@ -60,10 +622,10 @@ fn covariance(
         .sum::<f64>()
 }
 
-fn mean(image: &GrayImage, window: &Window) -> f64 {
+fn mean<P: Primitive + Into<f64>>(image: &ImageBuffer<Luma<P>, Vec<P>>, window: &Window) -> f64 {
     let sum = window
         .iter_pixels()
-        .map(|pixel| image.get_pixel(pixel.0, pixel.1)[0] as f64)
+        .map(|pixel| image.get_pixel(pixel.0, pixel.1)[0].into())
         .sum::<f64>();
 
     sum / window.area() as f64
@@ -72,6 +634,11 @@ fn mean(image: &GrayImage, window: &Window) -> f64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    fn ssim_for_window(first: &GrayImage, second: &GrayImage, window: &Window) -> f64 {
+        statistics_for_window(first, second, window).ssim(&Constants::default())
+    }
+
     #[test]
     fn conv_avg_simple() {
         let mut img = GrayImage::new(1, 1);
@@ -133,4 +700,357 @@ mod tests {
         let ssim_value = ssim_for_window(&img, &img_second, &window);
         assert_eq!(ssim_value, 0.20641218950876916);
     }
+
+    #[test]
+    fn test_ssim_sliding_zero_stride() {
+        let img = GrayImage::new(8, 8);
+        assert!(ssim(&img, &img, &SsimConfig::sliding(0)).is_err());
+    }
+
+    #[test]
+    fn test_ssim_sliding_window_sized_stride() {
+        let mut first = GrayImage::new(24, 16);
+        first.put_pixel(3, 3, Luma([200]));
+        first.put_pixel(17, 9, Luma([100]));
+        let mut second = first.clone();
+        second.put_pixel(10, 12, Luma([50]));
+        let (simple, _) = ssim(&first, &second, &SsimConfig::default()).unwrap();
+        let (sliding, _) =
+            ssim(&first, &second, &SsimConfig::sliding(DEFAULT_WINDOW_SIZE)).unwrap();
+        assert!((simple - sliding).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_ssim_sliding_window_sized_stride_partial_tiles() {
+        let first = GrayImage::from_fn(19, 13, |col, row| {
+            Luma([((col * 37 + row * 23) % 200) as u8])
+        });
+        let mut second = first.clone();
+        second.put_pixel(17, 11, Luma([255]));
+        second.put_pixel(15, 5, Luma([0]));
+        let (simple, simple_map) = ssim(&first, &second, &SsimConfig::default()).unwrap();
+        let (sliding, sliding_map) =
+            ssim(&first, &second, &SsimConfig::sliding(DEFAULT_WINDOW_SIZE)).unwrap();
+        assert!(simple < 1.);
+        assert!((simple - sliding).abs() < 1e-12);
+        simple_map
+            .pixels()
+            .zip(sliding_map.pixels())
+            .for_each(|(a, b)| assert!((a[0] - b[0]).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_window_sums_match_direct_statistics() {
+        let mut first = GrayImage::new(13, 11);
+        let mut second = first.clone();
+        first
+            .enumerate_pixels_mut()
+            .for_each(|(col, row, p)| *p = Luma([((col * 31 + row * 17) % 256) as u8]));
+        second
+            .enumerate_pixels_mut()
+            .for_each(|(col, row, p)| *p = Luma([((col * 11 + row * 41) % 256) as u8]));
+        let sums = WindowSums::new(&to_float_image(&first), &to_float_image(&second));
+        for window in Window::from_image(&first).subdivide_by_offset(4) {
+            let direct = ssim_for_window(&first, &second, &window);
+            let summed = sums.statistics(&window).ssim(&Constants::default());
+            assert!((summed - direct).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_ssim_sliding_localized() {
+        let mut first = GrayImage::new(32, 32);
+        first.fill(100);
+        let mut second = first.clone();
+        second.put_pixel(16, 16, Luma([200]));
+        let (score, map) = ssim(&first, &second, &SsimConfig::sliding(1)).unwrap();
+        assert!(score < 1.);
+        assert!(map.get_pixel(16, 16)[0] < 0.5);
+        // windows are 8 px wide, so only pixels 13..=20 see the change
+        assert!(map.get_pixel(13, 16)[0] < 1.);
+        assert!(map.get_pixel(20, 16)[0] < 1.);
+        assert_eq!(map.get_pixel(12, 16)[0], 1.);
+        assert_eq!(map.get_pixel(21, 16)[0], 1.);
+    }
+
+    #[test]
+    fn test_ssim_gaussian_identity() {
+        let mut img = GrayImage::new(20, 15);
+        img.put_pixel(7, 3, Luma([200]));
+        img.put_pixel(12, 9, Luma([50]));
+        let (score, map) = ssim(&img, &img, &SsimConfig::gaussian()).unwrap();
+        assert!((score - 1.).abs() < 1e-9);
+        assert!(map.pixels().all(|p| (p[0] - 1.).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_ssim_gaussian_localized() {
+        let mut first = GrayImage::new(40, 40);
+        first.fill(100);
+        let mut second = first.clone();
+        second.put_pixel(20, 20, Luma([200]));
+        let (score, map) = ssim(&first, &second, &SsimConfig::gaussian()).unwrap();
+        assert!(score < 1.);
+        assert!(map.get_pixel(20, 20)[0] < 0.5);
+        // outside the window radius the images are indistinguishable
+        assert_eq!(map.get_pixel(26, 20)[0], 1.);
+        assert_eq!(map.get_pixel(20, 14)[0], 1.);
+    }
+
+    #[test]
+    fn test_ms_ssim_identity() {
+        let mut img = GrayImage::new(200, 180);
+        img.enumerate_pixels_mut()
+            .for_each(|(col, row, p)| *p = Luma([((col * 7 + row * 13) % 256) as u8]));
+        let (score, map) = ms_ssim(&img, &img, &SsimConfig::gaussian()).unwrap();
+        assert!((score - 1.).abs() < 1e-9);
+        assert!(map.pixels().all(|p| (p[0] - 1.).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_ms_ssim_fewer_scales() {
+        let mut first = GrayImage::new(30, 30);
+        first.fill(100);
+        let mut second = first.clone();
+        second.put_pixel(15, 15, Luma([0]));
+        let (score, map) = ms_ssim(&first, &second, &SsimConfig::gaussian()).unwrap();
+        assert!(score < 1.);
+        assert!(score > 0.);
+        assert_eq!(map.dimensions(), (30, 30));
+        assert!(map.get_pixel(15, 15)[0] < map.get_pixel(0, 0)[0]);
+    }
+
+    #[test]
+    fn test_ms_ssim_less_sensitive_to_noise() {
+        let mut first = GrayImage::new(128, 128);
+        first
+            .enumerate_pixels_mut()
+            .for_each(|(col, _, p)| *p = Luma([if col < 64 { 50 } else { 200 }]));
+        let mut second = first.clone();
+        second
+            .enumerate_pixels_mut()
+            .filter(|(col, row, _)| (col + row) % 2 == 0)
+            .for_each(|(_, _, p)| p[0] += 20);
+        let (single_scale, _) = ssim(&first, &second, &SsimConfig::gaussian()).unwrap();
+        let (multi_scale, _) = ms_ssim(&first, &second, &SsimConfig::gaussian()).unwrap();
+        assert!(multi_scale > single_scale);
+    }
+
+    #[test]
+    fn test_ssim_gaussian_small_image() {
+        let mut first = GrayImage::new(4, 3);
+        first.fill(10);
+        let mut second = first.clone();
+        second.fill(20);
+        let (score, _) = ssim(&first, &second, &SsimConfig::gaussian()).unwrap();
+        assert!(score.is_finite());
+        assert!(score < 1.);
+    }
+
+    #[test]
+    fn test_ssim_invalid_window() {
+        let img = GrayImage::new(8, 8);
+        let config = SsimConfig {
+            window: SsimWindow::Gaussian {
+                size: 11,
+                sigma: 0.,
+            },
+            ..Default::default()
+        };
+        assert!(ssim(&img, &img, &config).is_err());
+        assert!(ms_ssim(&img, &img, &config).is_err());
+        let config = SsimConfig {
+            window: SsimWindow::Blocks { size: 0 },
+            ..Default::default()
+        };
+        assert!(ssim(&img, &img, &config).is_err());
+    }
+
+    #[test]
+    fn test_ssim_invalid_constants() {
+        let img = GrayImage::new(8, 8);
+        let configs = [
+            SsimConfig {
+                k1: 0.,
+                ..Default::default()
+            },
+            SsimConfig {
+                k2: -0.03,
+                ..Default::default()
+            },
+            SsimConfig {
+                k2: f64::NAN,
+                ..Default::default()
+            },
+            SsimConfig {
+                dynamic_range: Some(0.),
+                ..Default::default()
+            },
+            SsimConfig {
+                dynamic_range: Some(f64::INFINITY),
+                ..Default::default()
+            },
+        ];
+        for config in configs {
+            assert!(ssim(&img, &img, &config).is_err(), "{config:?}");
+            assert!(ssim_components(&img, &img, &config).is_err(), "{config:?}");
+            assert!(ms_ssim(&img, &img, &config).is_err(), "{config:?}");
+        }
+        let config = SsimConfig {
+            dynamic_range: Some(1.),
+            ..Default::default()
+        };
+        assert!(ssim(&img, &img, &config).is_ok());
+    }
+
+    #[test]
+    fn test_ssim_block_size_does_not_rescale_k2() {
+        // patterns repeating every 4 px have the same variances in blocks of 4, 8 and 16 px
+        let first = GrayImage::from_fn(16, 16, |col, row| {
+            Luma([100 + ((col % 4) * 3 + row % 4) as u8])
+        });
+        let second = GrayImage::from_fn(16, 16, |col, row| {
+            Luma([100 + ((row % 4) * 3 + col % 4) as u8])
+        });
+        let score = |size| {
+            let config = SsimConfig {
+                window: SsimWindow::Blocks { size },
+                ..Default::default()
+            };
+            ssim(&first, &second, &config).unwrap().0
+        };
+        assert!(score(8) < 1.);
+        assert!((score(4) - score(8)).abs() < 1e-12);
+        assert!((score(16) - score(8)).abs() < 1e-12);
+        let (sliding, _) = ssim(
+            &first,
+            &second,
+            &SsimConfig {
+                window: SsimWindow::Sliding { size: 4, stride: 4 },
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!((sliding - score(8)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_ssim_config_dynamic_range() {
+        let mut first = GrayImage::new(16, 16);
+        first.fill(10);
+        let mut second = first.clone();
+        second.fill(12);
+        let (default_range, _) = ssim(&first, &second, &SsimConfig::default()).unwrap();
+        let config = SsimConfig {
+            dynamic_range: Some(15.),
+            ..Default::default()
+        };
+        let (small_range, _) = ssim(&first, &second, &config).unwrap();
+        // a smaller dynamic range makes the same absolute difference more significant
+        assert!(small_range < default_range);
+    }
+
+    #[test]
+    fn test_ssim_16_bit_range() {
+        let mut first = GrayImage::new(16, 16);
+        first
+            .enumerate_pixels_mut()
+            .for_each(|(col, row, p)| *p = Luma([((col * 13 + row * 7) % 256) as u8]));
+        let mut second = first.clone();
+        second.put_pixel(5, 5, Luma([0]));
+        let widen = |image: &GrayImage| {
+            GrayBuffer::<u16>::from_fn(16, 16, |col, row| {
+                Luma([image.get_pixel(col, row)[0] as u16 * 257])
+            })
+        };
+        let (score, map) = ssim(&first, &second, &SsimConfig::default()).unwrap();
+        let (score_16_bit, map_16_bit) =
+            ssim(&widen(&first), &widen(&second), &SsimConfig::default()).unwrap();
+        // scaling pixels and L by the same factor does not change SSIM
+        assert!((score - score_16_bit).abs() < 1e-9);
+        assert!(map
+            .pixels()
+            .zip(map_16_bit.pixels())
+            .all(|(a, b)| (a[0] - b[0]).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_ssim_config_window_size() {
+        let mut first = GrayImage::new(16, 16);
+        first.fill(100);
+        let mut second = first.clone();
+        second.put_pixel(1, 1, Luma([0]));
+        let config = SsimConfig {
+            window: SsimWindow::Blocks { size: 4 },
+            ..Default::default()
+        };
+        let (_, map) = ssim(&first, &second, &config).unwrap();
+        assert!(map.get_pixel(3, 3)[0] < 1.);
+        assert_eq!(map.get_pixel(4, 4)[0], 1.);
+    }
+
+    #[test]
+    fn test_ms_ssim_with_blocks() {
+        let mut first = GrayImage::new(64, 64);
+        first.fill(100);
+        let mut second = first.clone();
+        second.put_pixel(30, 30, Luma([0]));
+        let (identity, _) = ms_ssim(&first, &first, &SsimConfig::default()).unwrap();
+        let (score, _) = ms_ssim(&first, &second, &SsimConfig::default()).unwrap();
+        assert!((identity - 1.).abs() < 1e-9);
+        assert!(score < 1.);
+    }
+
+    #[test]
+    fn test_components_brightness_shift() {
+        let mut first = GrayImage::new(16, 16);
+        first
+            .enumerate_pixels_mut()
+            .for_each(|(col, row, p)| *p = Luma([(50 + (col * 5 + row * 3) % 40) as u8]));
+        let mut second = first.clone();
+        second.pixels_mut().for_each(|p| p[0] += 100);
+        let components = ssim_components(&first, &second, &SsimConfig::default()).unwrap();
+        assert!(components.luminance.score < 0.9);
+        assert!((components.contrast.score - 1.).abs() < 1e-9);
+        assert!((components.structure.score - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_components_contrast_change() {
+        let mut first = GrayImage::new(16, 16);
+        first
+            .enumerate_pixels_mut()
+            .for_each(|(col, _, p)| *p = Luma([if col % 2 == 0 { 100 } else { 140 }]));
+        let mut second = first.clone();
+        second
+            .pixels_mut()
+            .for_each(|p| p[0] = if p[0] == 100 { 110 } else { 130 });
+        let components = ssim_components(&first, &second, &SsimConfig::gaussian()).unwrap();
+        assert!((components.luminance.score - 1.).abs() < 1e-9);
+        assert!(components.contrast.score < 0.95);
+        assert!((components.structure.score - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_components_product_is_ssim() {
+        let mut first = GrayImage::new(24, 24);
+        let mut second = first.clone();
+        first
+            .enumerate_pixels_mut()
+            .for_each(|(col, row, p)| *p = Luma([((col * 31 + row * 17) % 256) as u8]));
+        second
+            .enumerate_pixels_mut()
+            .for_each(|(col, row, p)| *p = Luma([((col * 11 + row * 41) % 256) as u8]));
+        for config in [SsimConfig::default(), SsimConfig::gaussian()] {
+            let components = ssim_components(&first, &second, &config).unwrap();
+            let (score, map) = ssim(&first, &second, &config).unwrap();
+            assert_eq!(components.similarity.score, score);
+            for (col, row, p) in map.enumerate_pixels() {
+                let product = components.luminance.image.get_pixel(col, row)[0]
+                    * components.contrast.image.get_pixel(col, row)[0]
+                    * components.structure.image.get_pixel(col, row)[0];
+                assert!((product - p[0]).abs() < 1e-4);
+            }
+        }
+    }
 }
@@ -1,12 +1,84 @@
+use crate::color_space::ColorSpace;
+use crate::hybrid::Blending;
 use crate::prelude::*;
-use image::RgbaImage;
+use image::{GenericImageView, Pixel, Primitive, Rgba};
 use itertools::izip;
+use rayon::prelude::*;
+use std::f64::consts::PI;
 
-/// see https://www.itu.int/rec/T-REC-T.871
-fn rgb_to_yuv(rgb: &[f32; 3]) -> [f32; 3] {
+/// The subpixel types the comparisons accept: 8 and 16 bit integers as well as 32 bit floats.
+/// The dynamic range of the type normalizes the results, e.g. the SSIM constants, the RMS map and the PSNR peak.
+/// Float images are expected in `[0, 1]` like in the image crate, HDR images need to be encoded first,
+/// see [`crate::encode_float_image`].
+pub trait Subpixel: Primitive + Into<f64> + Send + Sync + 'static {
+    /// The largest value of a subpixel, 255 for 8 bit, 65535 for 16 bit and 1.0 for float images
+    const RANGE: f64;
+
+    /// The number of histogram bins, one per value for integers
+    const BINS: usize;
+
+    /// The center of the range that is used as zero for chroma channels, 128 for 8 bit images
+    fn center() -> f32 {
+        ((Self::RANGE + 1.) / 2.) as f32
+    }
+
+    /// Converts a value clamped to the range of the type, truncating like an `as` cast
+    fn from_clamped(value: f32) -> Self {
+        Self::from(value.clamp(0., Self::RANGE as f32)).expect("Value is clamped to the range")
+    }
+
+    /// Converts a value clamped to the range of the type, rounding to the nearest integer
+    fn from_rounded(value: f32) -> Self {
+        Self::from_clamped(value.round())
+    }
+
+    /// The histogram bin of the value
+    fn bin(self) -> usize {
+        let value: f64 = self.into();
+        value as usize
+    }
+}
+
+impl Subpixel for u8 {
+    const RANGE: f64 = u8::MAX as f64;
+    const BINS: usize = u8::MAX as usize + 1;
+}
+
+impl Subpixel for u16 {
+    const RANGE: f64 = u16::MAX as f64;
+    const BINS: usize = u16::MAX as usize + 1;
+}
+
+/// Float images use 256 bins like 8 bit images
+impl Subpixel for f32 {
+    const RANGE: f64 = 1.;
+    const BINS: usize = 256;
+
+    fn center() -> f32 {
+        0.5
+    }
+
+    fn from_rounded(value: f32) -> Self {
+        Self::from_clamped(value)
+    }
+
+    fn bin(self) -> usize {
+        (self.clamp(0., 1.) * (Self::BINS - 1) as f32).round() as usize
+    }
+}
+
+/// A single-channel image of the subpixel type `P`
+pub(crate) type GrayBuffer<P> = ImageBuffer<Luma<P>, Vec<P>>;
+/// A three-channel image of the subpixel type `P`
+pub(crate) type RgbBuffer<P> = ImageBuffer<Rgb<P>, Vec<P>>;
+/// A four-channel image of the subpixel type `P`
+pub(crate) type RgbaBuffer<P> = ImageBuffer<Rgba<P>, Vec<P>>;
+
+/// see https://www.itu.int/rec/T-REC-T.871 - `center` is the chroma offset, 128 for 8 bit values
+pub(crate) fn rgb_to_yuv(rgb: &[f32; 3], center: f32) -> [f32; 3] {
     let py = 0. + (0.299 * rgb[0]) + (0.587 * rgb[1]) + (0.114 * rgb[2]);
-    let pu = 128. - (0.168736 * rgb[0]) - (0.331264 * rgb[1]) + (0.5 * rgb[2]);
-    let pv = 128. + (0.5 * rgb[0]) - (0.418688 * rgb[1]) - (0.081312 * rgb[2]);
+    let pu = center - (0.168736 * rgb[0]) - (0.331264 * rgb[1]) + (0.5 * rgb[2]);
+    let pv = center + (0.5 * rgb[0]) - (0.418688 * rgb[1]) - (0.081312 * rgb[2]);
     [py, pu, pv]
 }
 
@@ -19,8 +91,75 @@ fn yuv_to_rgb(yuv: &[f32; 3]) -> [f32; 3] {
     [r, b, g]
 }
 
-pub(crate) fn split_rgba_to_yuva(source: &RgbaImage) -> [GrayImage; 4] {
-    let mut y = GrayImage::new(source.width(), source.height());
+/// D65 reference white of sRGB in XYZ
+pub(crate) const D65_WHITE: [f64; 3] = [0.95047, 1., 1.08883];
+
+/// sRGB gamma decoding of an 8 bit value to linear light in `0..=1`
+pub(crate) fn srgb_to_linear(c: u8) -> f64 {
+    srgb_decode(c as f64 / u8::MAX as f64)
+}
+
+/// sRGB gamma decoding of a value in `0..=1` to linear light in `0..=1`
+pub(crate) fn srgb_decode(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// sRGB gamma encoding of linear light in `0..=1`
+pub(crate) fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// see https://en.wikipedia.org/wiki/SRGB
+pub(crate) fn linear_rgb_to_xyz([r, g, b]: [f64; 3]) -> [f64; 3] {
+    [
+        0.4124564 * r + 0.3575761 * g + 0.1804375 * b,
+        0.2126729 * r + 0.7151522 * g + 0.0721750 * b,
+        0.0193339 * r + 0.1191920 * g + 0.9503041 * b,
+    ]
+}
+
+pub(crate) fn xyz_to_linear_rgb([x, y, z]: [f64; 3]) -> [f64; 3] {
+    [
+        3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+        -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+        0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+    ]
+}
+
+/// see https://en.wikipedia.org/wiki/CIELAB_color_space
+pub(crate) fn xyz_to_lab(xyz: [f64; 3]) -> [f64; 3] {
+    let delta: f64 = 6. / 29.;
+    let [fx, fy, fz] = [0, 1, 2].map(|i| {
+        let t = xyz[i] / D65_WHITE[i];
+        if t > delta.powi(3) {
+            t.cbrt()
+        } else {
+            t / (3. * delta * delta) + 4. / 29.
+        }
+    });
+    [116. * fy - 16., 500. * (fx - fy), 200. * (fy - fz)]
+}
+
+pub(crate) fn rgb_to_lab(rgb: &[u8; 3]) -> [f64; 3] {
+    xyz_to_lab(linear_rgb_to_xyz(rgb.map(srgb_to_linear)))
+}
+
+pub(crate) fn split_rgba_to_yuva<P: Subpixel>(
+    source: &RgbaBuffer<P>,
+    color_space: ColorSpace,
+) -> [GrayBuffer<P>; 4]
+where
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    let mut y = GrayBuffer::new(source.width(), source.height());
     let mut u = y.clone();
     let mut v = y.clone();
     let mut a = y.clone();
@@ -33,30 +172,86 @@ pub(crate) fn split_rgba_to_yuva(source: &RgbaImage) -> [GrayImage; 4] {
         source.pixels()
     )
     .for_each(|(y, u, v, a, rgba)| {
-        let rgba: [f32; 4] = rgba.0.map(|c| c as f32);
-        let yuv = rgb_to_yuv(&rgba[0..3].try_into().unwrap());
-        *y = Luma([yuv[0].clamp(0., 255.) as u8]);
-        *u = Luma([yuv[1].clamp(0., 255.) as u8]);
-        *v = Luma([yuv[2].clamp(0., 255.) as u8]);
-        *a = Luma([rgba[3] as u8]);
+        let rgb: [f32; 3] = [0, 1, 2].map(|c| rgba[c].into() as f32);
+        let yuv = color_space.decompose::<P>(rgb);
+        *y = Luma([P::from_clamped(yuv[0])]);
+        *u = Luma([P::from_clamped(yuv[1])]);
+        *v = Luma([P::from_clamped(yuv[2])]);
+        *a = Luma([rgba[3]]);
     });
 
     [y, u, v, a]
 }
 
-fn blend(c: u8, a: u8, c_b: u8) -> u8 {
-    let c = c as f32 / 255.;
-    let a = a as f32 / 255.;
-    let c_b = c_b as f32 / 255.;
+fn blend<P: Subpixel>(c: P, a: P, c_b: P) -> P {
+    let range = P::RANGE as f32;
+    let c = c.into() as f32 / range;
+    let a = a.into() as f32 / range;
+    let c_b = c_b.into() as f32 / range;
     let blended = (c * a) + (c_b * (1. - a));
-    (blended.clamp(0., 1.) * 255.) as u8
+    P::from_clamped(blended.clamp(0., 1.) * range)
+}
+
+/// Like [`blend`] but mixes linear light, the sRGB encoded colors are decoded before and encoded after
+fn blend_linear<P: Subpixel>(c: P, a: P, c_b: P) -> P {
+    let range = P::RANGE as f32;
+    let decode = |c: P| srgb_decode((c.into() as f32 / range).clamp(0., 1.) as f64) as f32;
+    let a = a.into() as f32 / range;
+    let blended = (decode(c) * a) + (decode(c_b) * (1. - a));
+    P::from_rounded(linear_to_srgb(blended.clamp(0., 1.)) * range)
+}
+
+/// Converts premultiplied to straight alpha, the color of fully transparent pixels becomes black
+pub(crate) fn unpremultiply<P: Subpixel>(image: &RgbaBuffer<P>) -> RgbaBuffer<P>
+where
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    let range = P::RANGE as f32;
+    let mut buffer = image.clone();
+    buffer.pixels_mut().for_each(|p| {
+        let alpha = p[3].into() as f32 / range;
+        for c in 0..3 {
+            p[c] = if alpha > 0. {
+                P::from_rounded(p[c].into() as f32 / alpha)
+            } else {
+                P::from_clamped(0.)
+            };
+        }
+    });
+    buffer
+}
+
+pub(crate) fn blend_alpha<P: Subpixel>(
+    image: &RgbaBuffer<P>,
+    color: Rgb<P>,
+    blending: Blending,
+) -> RgbBuffer<P>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    blend_alpha_with(image, |_, _| color, blending)
 }
 
-pub(crate) fn blend_alpha(image: &RgbaImage, color: Rgb<u8>) -> RgbImage {
+/// Blends the image over a background given per pixel by `background(x, y)`
+pub(crate) fn blend_alpha_with<P: Subpixel>(
+    image: &RgbaBuffer<P>,
+    background: impl Fn(u32, u32) -> Rgb<P>,
+    blending: Blending,
+) -> RgbBuffer<P>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+    Rgba<P>: Pixel<Subpixel = P>,
+{
+    let blend = match blending {
+        Blending::Encoded => blend,
+        Blending::Linear => blend_linear,
+    };
     let mut buffer = ImageBuffer::new(image.width(), image.height());
 
-    for (input, output) in image.pixels().zip(buffer.pixels_mut()) {
+    for ((x, y, input), output) in image.enumerate_pixels().zip(buffer.pixels_mut()) {
         let [r, g, b, a] = input.0;
+        let color = background(x, y);
 
         *output = Rgb([
             blend(r, a, color.0[0]),
@@ -67,14 +262,424 @@ pub(crate) fn blend_alpha(image: &RgbaImage, color: Rgb<u8>) -> RgbImage {
     buffer
 }
 
-pub trait Decompose {
-    fn split_channels(&self) -> [GrayImage; 3];
-    fn split_to_yuv(&self) -> [GrayImage; 3];
+/// A single-channel f64 typed image used for intermediate filter results
+pub(crate) type FloatImage = ImageBuffer<Luma<f64>, Vec<f64>>;
+
+pub(crate) fn to_float_image<P: Subpixel>(image: &GrayBuffer<P>) -> FloatImage {
+    let mut result = FloatImage::new(image.width(), image.height());
+    result
+        .pixels_mut()
+        .zip(image.pixels())
+        .for_each(|(f, p)| *f = Luma([p[0].into()]));
+    result
+}
+
+/// Like [`to_float_image`] but rescaled to the range of 8 bit images,
+/// for algorithms whose constants are tuned for 8 bit images
+pub(crate) fn to_8_bit_float_image<P: Subpixel>(image: &GrayBuffer<P>) -> FloatImage {
+    let scale = u8::RANGE / P::RANGE;
+    let mut result = to_float_image(image);
+    result.pixels_mut().for_each(|p| p[0] *= scale);
+    result
+}
+
+pub(crate) fn to_similarity_image(map: &FloatImage) -> GraySimilarityImage {
+    let mut image = GraySimilarityImage::new(map.width(), map.height());
+    image
+        .pixels_mut()
+        .zip(map.pixels())
+        .for_each(|(i, m)| *i = Luma([m[0] as f32]));
+    image
+}
+
+/// Normalized one-dimensional gaussian kernel with `2 * radius + 1` taps
+pub(crate) fn gaussian_kernel(sigma: f64, radius: u32) -> Vec<f64> {
+    let radius = radius as i64;
+    let kernel = (-radius..=radius)
+        .map(|x| (-((x * x) as f64) / (2. * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let sum = kernel.iter().sum::<f64>();
+    kernel.into_iter().map(|k| k / sum).collect()
+}
+
+/// Range of kernel offsets that stay inside `0..len` around `index`, and the sum of their weights
+fn kernel_support(kernel: &[f64], index: usize, len: usize) -> (std::ops::Range<usize>, f64) {
+    let radius = kernel.len() / 2;
+    let first = radius.saturating_sub(index);
+    let last = (radius + len - index).min(kernel.len());
+    (first..last, kernel[first..last].iter().sum())
+}
+
+/// Separable filtering of the image with a symmetric, normalized kernel.
+/// At the borders the kernel is cut off and renormalized, so no padding values are invented.
+pub(crate) fn filter_separable(image: &FloatImage, kernel: &[f64]) -> FloatImage {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let radius = kernel.len() / 2;
+
+    let mut horizontal = vec![0.; width * height];
+    horizontal
+        .par_chunks_mut(width)
+        .zip(image.as_raw().par_chunks(width))
+        .for_each(|(output, input)| {
+            output.iter_mut().enumerate().for_each(|(col, out)| {
+                let (support, weights) = kernel_support(kernel, col, width);
+                let sum = support
+                    .map(|k| kernel[k] * input[col + k - radius])
+                    .sum::<f64>();
+                *out = sum / weights;
+            })
+        });
+
+    let mut vertical = vec![0.; width * height];
+    vertical
+        .par_chunks_mut(width)
+        .enumerate()
+        .for_each(|(row, output)| {
+            let (support, weights) = kernel_support(kernel, row, height);
+            for k in support {
+                let input = &horizontal[(row + k - radius) * width..][..width];
+                let weight = kernel[k] / weights;
+                output
+                    .iter_mut()
+                    .zip(input)
+                    .for_each(|(out, value)| *out += weight * value);
+            }
+        });
+
+    FloatImage::from_raw(width as u32, height as u32, vertical)
+        .expect("Buffer size matches the image dimensions")
 }
 
-impl Decompose for RgbImage {
-    fn split_channels(&self) -> [GrayImage; 3] {
-        let mut red = GrayImage::new(self.width(), self.height());
+/// Separable correlation of the image with arbitrary kernels along rows and columns,
+/// the image is extended by repeating the border pixels
+pub(crate) fn convolve_separable(
+    image: &FloatImage,
+    horizontal: &[f64],
+    vertical: &[f64],
+) -> FloatImage {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let clamp =
+        |position: usize, radius: usize, len: usize| position.saturating_sub(radius).min(len - 1);
+
+    let radius = horizontal.len() / 2;
+    let mut rows = vec![0.; width * height];
+    rows.par_chunks_mut(width)
+        .zip(image.as_raw().par_chunks(width))
+        .for_each(|(output, input)| {
+            for (col, out) in output.iter_mut().enumerate() {
+                for (k, weight) in horizontal.iter().enumerate() {
+                    *out += weight * input[clamp(col + k, radius, width)];
+                }
+            }
+        });
+
+    let radius = vertical.len() / 2;
+    let mut columns = vec![0.; width * height];
+    columns
+        .par_chunks_mut(width)
+        .enumerate()
+        .for_each(|(row, output)| {
+            for (k, weight) in vertical.iter().enumerate() {
+                let input = &rows[clamp(row + k, radius, height) * width..][..width];
+                output
+                    .iter_mut()
+                    .zip(input)
+                    .for_each(|(out, value)| *out += weight * value);
+            }
+        });
+
+    FloatImage::from_raw(width as u32, height as u32, columns)
+        .expect("Buffer size matches the image dimensions")
+}
+
+/// Minimal complex number for the coefficients of quadrature filters
+#[derive(Clone, Copy, Default, Debug)]
+pub(crate) struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn from_polar(magnitude: f64, phase: f64) -> Complex {
+        Complex {
+            re: magnitude * phase.cos(),
+            im: magnitude * phase.sin(),
+        }
+    }
+
+    pub fn mul(self, other: Complex) -> Complex {
+        Complex {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+
+    pub fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn add(self, other: Complex) -> Complex {
+        Complex {
+            re: self.re + other.re,
+            im: self.im + other.im,
+        }
+    }
+
+    pub fn conj(self) -> Complex {
+        Complex {
+            re: self.re,
+            im: -self.im,
+        }
+    }
+
+    pub fn sub(self, other: Complex) -> Complex {
+        Complex {
+            re: self.re - other.re,
+            im: self.im - other.im,
+        }
+    }
+
+    pub fn scale(self, factor: f64) -> Complex {
+        Complex {
+            re: self.re * factor,
+            im: self.im * factor,
+        }
+    }
+}
+
+/// A fast fourier transform of a fixed length: radix-2 for powers of two,
+/// Bluestein's chirp z-transform on a radix-2 transform of at least twice the length for all other lengths
+struct Fft {
+    /// The twiddle factors `exp(-2πik / n)` of the radix-2 transform of length n
+    twiddles: Vec<Complex>,
+    /// Bluestein only: the chirp `exp(-πik² / len)` and the spectrum of its conjugate
+    chirp: Vec<Complex>,
+    chirp_spectrum: Vec<Complex>,
+}
+
+impl Fft {
+    fn new(len: usize) -> Fft {
+        let radix2_len = if len.is_power_of_two() {
+            len
+        } else {
+            (2 * len - 1).next_power_of_two()
+        };
+        let twiddles = (0..radix2_len / 2)
+            .map(|k| Complex::from_polar(1., -2. * PI * k as f64 / radix2_len as f64))
+            .collect();
+        let mut fft = Fft {
+            twiddles,
+            chirp: Vec::new(),
+            chirp_spectrum: Vec::new(),
+        };
+        if radix2_len != len {
+            // k² modulo 2 * len keeps the phase accurate for large k
+            fft.chirp = (0..len)
+                .map(|k| Complex::from_polar(1., -PI * ((k * k) % (2 * len)) as f64 / len as f64))
+                .collect();
+            let mut spectrum = vec![Complex::default(); radix2_len];
+            spectrum[0] = fft.chirp[0].conj();
+            for k in 1..len {
+                spectrum[k] = fft.chirp[k].conj();
+                spectrum[radix2_len - k] = fft.chirp[k].conj();
+            }
+            fft.radix2(&mut spectrum, false);
+            fft.chirp_spectrum = spectrum;
+        }
+        fft
+    }
+
+    /// Unnormalized transform in place, the inverse uses the conjugated twiddle factors
+    fn process(&self, data: &mut [Complex], inverse: bool) {
+        if self.chirp.is_empty() {
+            self.radix2(data, inverse);
+            return;
+        }
+        // the inverse transform is the conjugate of the forward transform of the conjugate
+        let conjugate = |c: Complex| if inverse { c.conj() } else { c };
+        let mut buffer = vec![Complex::default(); self.chirp_spectrum.len()];
+        izip!(buffer.iter_mut(), data.iter(), &self.chirp)
+            .for_each(|(b, x, w)| *b = conjugate(*x).mul(*w));
+        self.radix2(&mut buffer, false);
+        buffer
+            .iter_mut()
+            .zip(&self.chirp_spectrum)
+            .for_each(|(b, s)| *b = b.mul(*s));
+        self.radix2(&mut buffer, true);
+        let scale = 1. / buffer.len() as f64;
+        izip!(data.iter_mut(), &buffer, &self.chirp)
+            .for_each(|(x, b, w)| *x = conjugate(b.mul(*w).scale(scale)));
+    }
+
+    fn radix2(&self, data: &mut [Complex], inverse: bool) {
+        let len = data.len();
+        if len <= 1 {
+            return;
+        }
+        let bits = len.trailing_zeros();
+        for i in 0..len {
+            let j = i.reverse_bits() >> (usize::BITS - bits);
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+        let mut half = 1;
+        while half < len {
+            let stride = len / (2 * half);
+            for chunk in data.chunks_mut(2 * half) {
+                let (even, odd) = chunk.split_at_mut(half);
+                for (k, (e, o)) in even.iter_mut().zip(odd.iter_mut()).enumerate() {
+                    let twiddle = self.twiddles[k * stride];
+                    let twiddle = if inverse { twiddle.conj() } else { twiddle };
+                    let t = o.mul(twiddle);
+                    *o = e.sub(t);
+                    *e = e.add(t);
+                }
+            }
+            half *= 2;
+        }
+    }
+}
+
+/// Two-dimensional discrete fourier transform of a row-major buffer of any dimensions,
+/// the inverse is normalized by the number of elements like MATLAB's `ifft2`
+pub(crate) fn fft2(data: &mut [Complex], (width, height): (usize, usize), inverse: bool) {
+    let rows = Fft::new(width);
+    data.par_chunks_mut(width)
+        .for_each(|row| rows.process(row, inverse));
+    let columns = Fft::new(height);
+    let mut transposed = (0..width * height)
+        .map(|i| data[(i % height) * width + i / height])
+        .collect::<Vec<_>>();
+    transposed
+        .par_chunks_mut(height)
+        .for_each(|column| columns.process(column, inverse));
+    let scale = if inverse {
+        1. / (width * height) as f64
+    } else {
+        1.
+    };
+    data.iter_mut().enumerate().for_each(|(i, d)| {
+        *d = transposed[(i % width) * height + i / width].scale(scale);
+    });
+}
+
+/// One-dimensional gabor kernel: a gaussian envelope modulated with the given angular frequency.
+/// With `zero_mean` the DC response is removed, so flat regions produce no coefficients.
+fn gabor_kernel(sigma: f64, frequency: f64, zero_mean: bool) -> Vec<Complex> {
+    let radius = (3. * sigma).ceil() as u32;
+    let envelope = gaussian_kernel(sigma, radius);
+    let mut kernel = envelope
+        .iter()
+        .enumerate()
+        .map(|(k, g)| Complex::from_polar(*g, frequency * (k as f64 - radius as f64)))
+        .collect::<Vec<_>>();
+    if zero_mean {
+        let dc = kernel.iter().fold(Complex::default(), |sum, k| sum.add(*k));
+        kernel.iter_mut().zip(&envelope).for_each(|(k, g)| {
+            k.re -= g * dc.re;
+            k.im -= g * dc.im;
+        });
+    }
+    kernel
+}
+
+/// One-dimensional convolution along rows or columns, the image is extended by repeating the border pixels
+fn convolve(
+    input: &[Complex],
+    (width, height): (usize, usize),
+    kernel: &[Complex],
+    horizontal: bool,
+) -> Vec<Complex> {
+    let radius = kernel.len() / 2;
+    let clamp = |position: usize, len: usize| position.saturating_sub(radius).min(len - 1);
+    let accumulate = |sum: &mut Complex, weight: &Complex, value: Complex| {
+        let product = weight.mul(value);
+        sum.re += product.re;
+        sum.im += product.im;
+    };
+    let mut output = vec![Complex::default(); width * height];
+    output
+        .par_chunks_mut(width)
+        .enumerate()
+        .for_each(|(row, output)| {
+            if horizontal {
+                let input = &input[row * width..][..width];
+                for (col, out) in output.iter_mut().enumerate() {
+                    for (k, weight) in kernel.iter().enumerate() {
+                        accumulate(out, weight, input[clamp(col + k, width)]);
+                    }
+                }
+            } else {
+                for (k, weight) in kernel.iter().enumerate() {
+                    let input = &input[clamp(row + k, height) * width..][..width];
+                    for (out, value) in output.iter_mut().zip(input) {
+                        accumulate(out, weight, *value);
+                    }
+                }
+            }
+        });
+    output
+}
+
+/// Complex coefficients of the subband with the given orientation (in radians) of a quadrature gabor filter.
+/// Gabor filters are separable for any orientation, the real part is the even, the imaginary part the odd response.
+pub(crate) fn gabor_subband(
+    image: &[Complex],
+    dimensions: (usize, usize),
+    sigma: f64,
+    wavelength: f64,
+    orientation: f64,
+) -> Vec<Complex> {
+    let frequency = 2. * PI / wavelength;
+    let (horizontal, vertical) = (orientation.cos(), orientation.sin());
+    let horizontal_dominant = horizontal.abs() >= vertical.abs();
+    let rows = gabor_kernel(sigma, frequency * horizontal, horizontal_dominant);
+    let columns = gabor_kernel(sigma, frequency * vertical, !horizontal_dominant);
+    convolve(
+        &convolve(image, dimensions, &rows, true),
+        dimensions,
+        &columns,
+        false,
+    )
+}
+
+/// Reduces the resolution by averaging `factor` x `factor` pixel blocks, incomplete trailing blocks are averaged on their own
+pub(crate) fn downsample(image: &FloatImage, factor: u32) -> FloatImage {
+    let (width, height) = image.dimensions();
+    let mut result = FloatImage::new(width.div_ceil(factor), height.div_ceil(factor));
+    for (col, row, pixel) in result.enumerate_pixels_mut() {
+        let block = Window::new(
+            (col * factor, row * factor),
+            (
+                (col * factor + factor - 1).min(width - 1),
+                (row * factor + factor - 1).min(height - 1),
+            ),
+        );
+        let sum = block
+            .iter_pixels()
+            .map(|(x, y)| image.get_pixel(x, y)[0])
+            .sum::<f64>();
+        *pixel = Luma([sum / block.area() as f64]);
+    }
+    result
+}
+
+pub trait Decompose<P: Subpixel> {
+    fn split_channels(&self) -> [GrayBuffer<P>; 3];
+    #[allow(dead_code)]
+    fn split_to_yuv(&self) -> [GrayBuffer<P>; 3] {
+        self.split_to_color_space(ColorSpace::Bt601)
+    }
+    fn split_to_color_space(&self, color_space: ColorSpace) -> [GrayBuffer<P>; 3];
+}
+
+impl<P: Subpixel> Decompose<P> for RgbBuffer<P>
+where
+    Rgb<P>: Pixel<Subpixel = P>,
+{
+    fn split_channels(&self) -> [GrayBuffer<P>; 3] {
+        let mut red = GrayBuffer::new(self.width(), self.height());
         let mut green = red.clone();
         let mut blue = red.clone();
         izip!(
@@ -92,8 +697,8 @@ impl Decompose for RgbImage {
         [red, green, blue]
     }
 
-    fn split_to_yuv(&self) -> [GrayImage; 3] {
-        let mut y = GrayImage::new(self.width(), self.height());
+    fn split_to_color_space(&self, color_space: ColorSpace) -> [GrayBuffer<P>; 3] {
+        let mut y = GrayBuffer::new(self.width(), self.height());
         let mut u = y.clone();
         let mut v = y.clone();
         izip!(
@@ -103,10 +708,10 @@ impl Decompose for RgbImage {
             self.pixels()
         )
         .for_each(|(y, u, v, rgb)| {
-            let yuv = rgb_to_yuv(&rgb.0.map(|c| c as f32));
-            *y = Luma([yuv[0].clamp(0., 255.) as u8]);
-            *u = Luma([yuv[1].clamp(0., 255.) as u8]);
-            *v = Luma([yuv[2].clamp(0., 255.) as u8]);
+            let yuv = color_space.decompose::<P>(rgb.0.map(|c| c.into() as f32));
+            *y = Luma([P::from_clamped(yuv[0])]);
+            *u = Luma([P::from_clamped(yuv[1])]);
+            *v = Luma([P::from_clamped(yuv[2])]);
         });
 
         [y, u, v]
@@ -189,14 +794,29 @@ impl Window {
         result
     }
 
-    pub fn iter_pixels(&self) -> WindowIter {
+    /// A window of `size` x `size` pixels around `center`, clipped to `bounds`.
+    /// For even sizes the center is the pixel right below and right of the geometric center.
+    pub fn around(center: (u32, u32), size: u32, bounds: &Window) -> Window {
+        let half = size / 2;
+        let clip = |c: u32, min: u32, max: u32| {
+            (
+                c.saturating_sub(half).max(min),
+                (c + (size - half).saturating_sub(1)).min(max),
+            )
+        };
+        let (left, right) = clip(center.0, bounds.top_left.0, bounds.bottom_right.0);
+        let (top, bottom) = clip(center.1, bounds.top_left.1, bounds.bottom_right.1);
+        Window::new((left, top), (right, bottom))
+    }
+
+    pub fn iter_pixels(&self) -> WindowIter<'_> {
         WindowIter {
             window: self,
             current_index: 0,
         }
     }
 
-    pub fn from_image(image: &GrayImage) -> Window {
+    pub fn from_image<I: GenericImageView>(image: &I) -> Window {
         Window {
             top_left: (0, 0),
             bottom_right: (image.width() - 1, image.height() - 1),
@@ -204,7 +824,50 @@ impl Window {
     }
 }
 
-pub fn draw_window_to_image(window: &Window, image: &mut GraySimilarityImage, val: f32) {
+/// Summed-area table for O(1) sums over arbitrary windows.
+/// It is padded with a leading zero row and column, so entry `(x + 1, y + 1)` holds the sum over all pixels
+/// with coordinates `<= (x, y)`.
+pub struct SummedAreaTable {
+    width: usize,
+    data: Vec<f64>,
+}
+
+impl SummedAreaTable {
+    pub fn from_fn(width: u32, height: u32, value: impl Fn(u32, u32) -> f64) -> SummedAreaTable {
+        let stride = width as usize + 1;
+        let mut data = vec![0.; stride * (height as usize + 1)];
+        for row in 0..height {
+            let mut row_sum = 0.;
+            for col in 0..width {
+                row_sum += value(col, row);
+                let index = (row as usize + 1) * stride + col as usize + 1;
+                data[index] = data[index - stride] + row_sum;
+            }
+        }
+        SummedAreaTable {
+            width: width as usize,
+            data,
+        }
+    }
+
+    pub fn sum(&self, window: &Window) -> f64 {
+        let stride = self.width + 1;
+        let left = window.top_left.0 as usize;
+        let top = window.top_left.1 as usize;
+        let right = window.bottom_right.0 as usize + 1;
+        let bottom = window.bottom_right.1 as usize + 1;
+        self.data[bottom * stride + right]
+            - self.data[top * stride + right]
+            - self.data[bottom * stride + left]
+            + self.data[top * stride + left]
+    }
+}
+
+pub fn draw_window_to_image<T: Primitive>(
+    window: &Window,
+    image: &mut ImageBuffer<Luma<T>, Vec<T>>,
+    val: T,
+) {
     window
         .iter_pixels()
         .for_each(|current_pixel| image.put_pixel(current_pixel.0, current_pixel.1, Luma([val])));
@@ -213,7 +876,7 @@ pub fn draw_window_to_image(window: &Window, image: &mut GraySimilarityImage, va
 #[cfg(test)]
 mod tests {
     use super::*;
-    use image::Rgba;
+    use image::RgbaImage;
 
     #[test]
     fn window_test() {
@@ -261,6 +924,44 @@ mod tests {
         assert_eq!(windows.iter().map(|w| w.area()).sum::<u32>(), window.area());
     }
 
+    #[test]
+    fn window_around_test() {
+        let bounds = Window::new((0, 0), (20, 10));
+        let window = Window::around((10, 5), 8, &bounds);
+        assert_eq!(window.top_left, (6, 1));
+        assert_eq!(window.bottom_right, (13, 8));
+        assert_eq!(window.area(), 64);
+
+        let window = Window::around((10, 5), 1, &bounds);
+        assert_eq!(window.top_left, (10, 5));
+        assert_eq!(window.bottom_right, (10, 5));
+
+        let clipped = Window::around((0, 10), 8, &bounds);
+        assert_eq!(clipped.top_left, (0, 6));
+        assert_eq!(clipped.bottom_right, (3, 10));
+    }
+
+    #[test]
+    fn summed_area_table_test() {
+        let mut image = GrayImage::new(6, 5);
+        image
+            .enumerate_pixels_mut()
+            .for_each(|(col, row, p)| *p = Luma([(col * 3 + row * 7) as u8]));
+        let table = SummedAreaTable::from_fn(6, 5, |col, row| image.get_pixel(col, row)[0] as f64);
+        for window in [
+            Window::new((0, 0), (5, 4)),
+            Window::new((0, 0), (0, 0)),
+            Window::new((2, 1), (4, 3)),
+            Window::new((5, 4), (5, 4)),
+        ] {
+            let direct = window
+                .iter_pixels()
+                .map(|(col, row)| image.get_pixel(col, row)[0] as f64)
+                .sum::<f64>();
+            assert_eq!(table.sum(&window), direct);
+        }
+    }
+
     #[test]
     fn from_image_test() {
         let img = GrayImage::new(127, 244);
@@ -273,12 +974,12 @@ mod tests {
     fn rgb_to_yuv_test() {
         let white = [255., 255., 255.];
         let black = [0., 0., 0.];
-        let white_yuv = rgb_to_yuv(&white);
+        let white_yuv = rgb_to_yuv(&white, 128.);
         assert_eq!(white_yuv[0], 255.);
         assert_eq!(white_yuv[1], 128.);
         assert_eq!(white_yuv[2], 128.);
 
-        let black_yuv = rgb_to_yuv(&black);
+        let black_yuv = rgb_to_yuv(&black, 128.);
         assert_eq!(black_yuv[0], 0.);
         assert_eq!(black_yuv[1], 128.);
         assert_eq!(black_yuv[2], 128.);
@@ -299,23 +1000,187 @@ mod tests {
         assert_eq!(black[2], 0.);
     }
 
+    #[test]
+    fn split_to_yuv_16_bit_test() {
+        let image = RgbBuffer::<u16>::from_pixel(1, 1, Rgb([u16::MAX; 3]));
+        let [y, u, v] = image.split_to_yuv();
+        assert_eq!(y.get_pixel(0, 0)[0], u16::MAX);
+        assert_eq!(u.get_pixel(0, 0)[0], 32768);
+        assert_eq!(v.get_pixel(0, 0)[0], 32768);
+    }
+
+    #[test]
+    fn rgb_to_lab_test() {
+        let white = rgb_to_lab(&[255, 255, 255]);
+        assert!((white[0] - 100.).abs() < 1e-3);
+        assert!(white[1].abs() < 1e-3 && white[2].abs() < 1e-3);
+        assert_eq!(rgb_to_lab(&[0, 0, 0]), [0., 0., 0.]);
+        let red = rgb_to_lab(&[255, 0, 0]);
+        assert!((red[0] - 53.24).abs() < 0.01);
+        assert!((red[1] - 80.09).abs() < 0.01);
+        assert!((red[2] - 67.20).abs() < 0.01);
+    }
+
+    #[test]
+    fn convolve_separable_test() {
+        let image = FloatImage::from_fn(4, 3, |col, _| Luma([col as f64]));
+        let result = convolve_separable(&image, &[-0.5, 0., 0.5], &[1.]);
+        assert_eq!(result.get_pixel(0, 1)[0], 0.5);
+        assert_eq!(result.get_pixel(1, 1)[0], 1.);
+        assert_eq!(result.get_pixel(3, 2)[0], 0.5);
+        let result = convolve_separable(&image, &[1.], &[0.25, 0.5, 0.25]);
+        assert_eq!(result, image);
+    }
+
+    #[test]
+    fn gaussian_kernel_test() {
+        let kernel = gaussian_kernel(1.5, 5);
+        assert_eq!(kernel.len(), 11);
+        assert!((kernel.iter().sum::<f64>() - 1.).abs() < 1e-12);
+        assert_eq!(kernel[0], kernel[10]);
+        assert!(kernel[5] > kernel[4]);
+    }
+
+    #[test]
+    fn filter_separable_test() {
+        let kernel = gaussian_kernel(1.5, 5);
+        let constant = FloatImage::from_pixel(7, 4, Luma([3.]));
+        let filtered = filter_separable(&constant, &kernel);
+        assert!(filtered.pixels().all(|p| (p[0] - 3.).abs() < 1e-12));
+
+        let mut impulse = FloatImage::new(11, 11);
+        impulse.put_pixel(5, 5, Luma([1.]));
+        let filtered = filter_separable(&impulse, &kernel);
+        assert!((filtered.get_pixel(5, 5)[0] - kernel[5] * kernel[5]).abs() < 1e-12);
+        // the kernel is cut in half at the border and renormalized
+        let border_norm = kernel[5..].iter().sum::<f64>();
+        let expected = kernel[0] / border_norm * kernel[5];
+        assert!((filtered.get_pixel(0, 5)[0] - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn downsample_test() {
+        let mut image = FloatImage::new(5, 3);
+        image
+            .enumerate_pixels_mut()
+            .for_each(|(col, row, p)| *p = Luma([(col + 10 * row) as f64]));
+        let small = downsample(&image, 2);
+        assert_eq!(small.dimensions(), (3, 2));
+        assert_eq!(small.get_pixel(0, 0)[0], (0. + 1. + 10. + 11.) / 4.);
+        assert_eq!(small.get_pixel(2, 0)[0], (4. + 14.) / 2.);
+        assert_eq!(small.get_pixel(2, 1)[0], 24.);
+        let smaller = downsample(&image, 3);
+        assert_eq!(smaller.dimensions(), (2, 1));
+        assert_eq!(
+            smaller.get_pixel(1, 0)[0],
+            (3. + 4. + 13. + 14. + 23. + 24.) / 6.
+        );
+    }
+
     #[test]
     fn blend_test() {
         // black with white background but no alpha = white
-        assert_eq!(blend(0, 0, 255), 255);
+        assert_eq!(blend::<u8>(0, 0, 255), 255);
         // white with black background and full alpha = white
-        assert_eq!(blend(255, 255, 0), 255);
+        assert_eq!(blend::<u8>(255, 255, 0), 255);
         // white with black background and no alpha = black
-        assert_eq!(blend(255, 0, 0), 0);
+        assert_eq!(blend::<u8>(255, 0, 0), 0);
         // white with black background and half alpha = gray
-        assert_eq!(blend(255, 127, 0), 127);
+        assert_eq!(blend::<u8>(255, 127, 0), 127);
+    }
+
+    #[test]
+    fn blend_16_bit_test() {
+        assert_eq!(blend(u16::MAX, u16::MAX, 0), u16::MAX);
+        assert_eq!(blend(0u16, 0, u16::MAX), u16::MAX);
+        assert_eq!(blend(u16::MAX, 0, 0), 0);
     }
 
     #[test]
     fn blend_image_test() {
         let test_image = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 127, 127]));
-        let pre_mult = blend_alpha(&test_image, Rgb([255, 255, 255]));
+        let pre_mult = blend_alpha(&test_image, Rgb([255, 255, 255]), Blending::Encoded);
         let b_target = 127 + 127 / 2 + 1;
         assert_eq!(pre_mult.get_pixel(0, 0).0, [255u8, 127u8, b_target]);
     }
+
+    #[test]
+    fn unpremultiply_test() {
+        let image = RgbaImage::from_fn(3, 1, |x, _| match x {
+            0 => Rgba([20, 10, 0, 51]),
+            1 => Rgba([10, 20, 30, 255]),
+            _ => Rgba([10, 20, 30, 0]),
+        });
+        let straight = unpremultiply(&image);
+        assert_eq!(straight.get_pixel(0, 0).0, [100, 50, 0, 51]);
+        assert_eq!(straight.get_pixel(1, 0).0, [10, 20, 30, 255]);
+        assert_eq!(straight.get_pixel(2, 0).0, [0, 0, 0, 0]);
+        let image =
+            ImageBuffer::<Rgba<f32>, Vec<f32>>::from_pixel(1, 1, Rgba([0.25, 0.5, 0., 0.5]));
+        assert_eq!(unpremultiply(&image).get_pixel(0, 0).0, [0.5, 1., 0., 0.5]);
+    }
+
+    #[test]
+    fn blend_linear_test() {
+        assert_eq!(blend_linear::<u8>(0, 0, 255), 255);
+        assert_eq!(blend_linear::<u8>(255, 255, 0), 255);
+        assert_eq!(blend_linear::<u8>(255, 0, 0), 0);
+        // half transparent black over white is half the light - brighter than the encoded mix
+        assert_eq!(blend::<u8>(0, 128, 255), 126);
+        assert_eq!(blend_linear::<u8>(0, 128, 255), 187);
+        assert_eq!(blend_linear(0u16, 0, u16::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn fft_matches_dft() {
+        for len in [1, 2, 3, 5, 8, 12, 30, 37] {
+            let data = (0..len)
+                .map(|k| Complex {
+                    re: ((k * 7) % 5) as f64 - 2.,
+                    im: (k % 3) as f64,
+                })
+                .collect::<Vec<_>>();
+            let dft = (0..len)
+                .map(|f| {
+                    data.iter()
+                        .enumerate()
+                        .fold(Complex::default(), |sum, (k, x)| {
+                            let phase = -2. * PI * ((f * k) % len) as f64 / len as f64;
+                            sum.add(x.mul(Complex::from_polar(1., phase)))
+                        })
+                })
+                .collect::<Vec<_>>();
+            let mut transformed = data.clone();
+            fft2(&mut transformed, (len, 1), false);
+            for (a, b) in transformed.iter().zip(&dft) {
+                assert!(a.sub(*b).norm_sqr() < 1e-20, "{len}: {a:?} {b:?}");
+            }
+            let mut transformed = data.clone();
+            fft2(&mut transformed, (1, len), false);
+            fft2(&mut transformed, (1, len), true);
+            for (a, b) in transformed.iter().zip(&data) {
+                assert!(a.sub(*b).norm_sqr() < 1e-20, "{len}: {a:?} {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn fft2_round_trip() {
+        let (width, height) = (6, 5);
+        let data = (0..width * height)
+            .map(|k| Complex {
+                re: (k * k % 11) as f64,
+                im: 0.,
+            })
+            .collect::<Vec<_>>();
+        let mut transformed = data.clone();
+        fft2(&mut transformed, (width, height), false);
+        // the DC coefficient is the sum of all values
+        let sum = data.iter().map(|c| c.re).sum::<f64>();
+        assert!((transformed[0].re - sum).abs() < 1e-9);
+        fft2(&mut transformed, (width, height), true);
+        for (a, b) in transformed.iter().zip(&data) {
+            assert!(a.sub(*b).norm_sqr() < 1e-20);
+        }
+    }
 }
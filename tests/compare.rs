@@ -1,7 +1,11 @@
 use cucumber::{given, then, when, World};
-use image::DynamicImage;
+use image::{DynamicImage, RgbaImage};
 use image_compare::prelude::*;
-use image_compare::Metric;
+use image_compare::{
+    Background, BlendInput, Blending, ChannelCombination, ColorDifference, ColorSpace,
+    CompareOptions, ComparisonPath, DeltaE, FlipConfig, HybridConfig, Mask, Metric,
+    PixelmatchConfig, PixelmatchResult, Rectangle, SsimWindow,
+};
 extern crate image;
 
 // `World` is your shared, likely mutable state.
@@ -12,6 +16,11 @@ pub struct CompareWorld {
     comparison_result: Option<Similarity>,
     comparison_result_rgb: Option<Similarity>,
     comparison_result_rgba: Option<Similarity>,
+    pixelmatch_result: Option<PixelmatchResult>,
+    color_difference: Option<ColorDifference>,
+    comparison_path: Option<ComparisonPath>,
+    color_space: Option<ColorSpace>,
+    worst_background: Option<usize>,
 }
 
 #[given(expr = "the images {string} and {string} are loaded")]
@@ -66,6 +75,83 @@ fn compare_hist_corr(world: &mut CompareWorld, metric: String) {
     });
 }
 
+#[when(expr = "comparing the images using histogram {string} as 16 bit grayscale")]
+fn compare_hist_16_bit(world: &mut CompareWorld, metric: String) {
+    let metric = match metric.as_str() {
+        "correlation" => Metric::Correlation,
+        "intersection" => Metric::Intersection,
+        "hellinger distance" => Metric::Hellinger,
+        _ => panic!(),
+    };
+    world.comparison_result = Some(Similarity {
+        score: image_compare::gray_similarity_histogram(
+            metric,
+            &world.first.as_ref().unwrap().clone().into_luma16(),
+            &world.second.as_ref().unwrap().clone().into_luma16(),
+        )
+        .expect("Error comparing the two images!"),
+        image: GraySimilarityImage::new(0, 0).into(),
+    });
+}
+
+#[when(expr = "comparing the images using histogram {string} as float grayscale")]
+fn compare_hist_float(world: &mut CompareWorld, metric: String) {
+    let metric = match metric.as_str() {
+        "correlation" => Metric::Correlation,
+        "intersection" => Metric::Intersection,
+        "hellinger distance" => Metric::Hellinger,
+        _ => panic!(),
+    };
+    world.comparison_result = Some(Similarity {
+        score: image_compare::gray_similarity_histogram(
+            metric,
+            &world.first.as_ref().unwrap().to_luma32f(),
+            &world.second.as_ref().unwrap().to_luma32f(),
+        )
+        .expect("Error comparing the two images!"),
+        image: GraySimilarityImage::new(0, 0).into(),
+    });
+}
+
+#[when(
+    expr = "comparing the images using histogram {string} as grayscale ignoring a {int} by {int} pixel rectangle at {int}, {int}"
+)]
+fn compare_hist_masked(
+    world: &mut CompareWorld,
+    metric: String,
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+) {
+    let metric = match metric.as_str() {
+        "correlation" => Metric::Correlation,
+        "intersection" => Metric::Intersection,
+        "hellinger distance" => Metric::Hellinger,
+        _ => panic!(),
+    };
+    world.comparison_result = Some(Similarity {
+        score: image_compare::gray_similarity_histogram_masked(
+            metric,
+            &world.first.as_ref().unwrap().clone().into_luma8(),
+            &world.second.as_ref().unwrap().clone().into_luma8(),
+            &rectangle_mask(width, height, x, y),
+        )
+        .expect("Error comparing the two images!"),
+        image: GraySimilarityImage::new(0, 0).into(),
+    });
+}
+
+fn rectangle_mask(width: u32, height: u32, x: u32, y: u32) -> Mask {
+    vec![Rectangle {
+        x,
+        y,
+        width,
+        height,
+    }]
+    .into()
+}
+
 #[when(expr = "comparing the images using MSSIM as grayscale")]
 fn compare_mssim(world: &mut CompareWorld) {
     world.comparison_result = Some(
@@ -78,6 +164,179 @@ fn compare_mssim(world: &mut CompareWorld) {
     );
 }
 
+#[when(expr = "comparing the images using Gaussian MSSIM as grayscale")]
+fn compare_mssim_gaussian(world: &mut CompareWorld) {
+    world.comparison_result = Some(
+        image_compare::gray_similarity_structure(
+            &Algorithm::MSSIMGaussian,
+            &world.first.as_ref().unwrap().clone().into_luma8(),
+            &world.second.as_ref().unwrap().clone().into_luma8(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using sliding MSSIM with stride {int} as grayscale")]
+fn compare_mssim_sliding(world: &mut CompareWorld, stride: u32) {
+    world.comparison_result = Some(
+        image_compare::gray_similarity_structure(
+            &Algorithm::MSSIMSliding { stride },
+            &world.first.as_ref().unwrap().clone().into_luma8(),
+            &world.second.as_ref().unwrap().clone().into_luma8(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+fn parse_algorithm(name: &str) -> Algorithm {
+    match name {
+        "RMS" => Algorithm::RootMeanSquared,
+        "MSE" => Algorithm::MeanSquaredError,
+        "PSNR" => Algorithm::PeakSignalToNoiseRatio,
+        "MAE" => Algorithm::MeanAbsoluteError,
+        "peak error" => Algorithm::PeakAbsoluteError,
+        "pixels over 16 levels" => Algorithm::PixelsOverTolerance { tolerance: 16. },
+        "MSSIM" => Algorithm::MSSIMSimple,
+        "Gaussian MSSIM" => Algorithm::MSSIMGaussian,
+        "MS-SSIM" => Algorithm::MultiScaleSSIM(SsimConfig::gaussian()),
+        "SSIM 4px blocks" => Algorithm::SSIM(SsimConfig {
+            window: SsimWindow::Blocks { size: 4 },
+            ..Default::default()
+        }),
+        "CW-SSIM" => Algorithm::CWSSIM(CwSsimConfig::default()),
+        "GMSD" => Algorithm::GMSD,
+        "FSIM" => Algorithm::FSIM,
+        _ => unimplemented!(),
+    }
+}
+
+#[when(expr = "comparing the images using algorithm {string} as grayscale")]
+fn compare_algorithm(world: &mut CompareWorld, algorithm: String) {
+    world.comparison_result = Some(
+        image_compare::gray_similarity_structure(
+            &parse_algorithm(&algorithm),
+            &world.first.as_ref().unwrap().clone().into_luma8(),
+            &world.second.as_ref().unwrap().clone().into_luma8(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using algorithm {string} as rgb")]
+fn compare_algorithm_rgb(world: &mut CompareWorld, algorithm: String) {
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_similarity_structure(
+            &parse_algorithm(&algorithm),
+            &world.first.as_ref().unwrap().clone().into_rgb8(),
+            &world.second.as_ref().unwrap().clone().into_rgb8(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(
+    expr = "comparing the images using algorithm {string} as grayscale ignoring a {int} by {int} pixel rectangle at {int}, {int}"
+)]
+fn compare_algorithm_masked(
+    world: &mut CompareWorld,
+    algorithm: String,
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+) {
+    world.comparison_result = Some(
+        image_compare::gray_similarity_structure_masked(
+            &parse_algorithm(&algorithm),
+            &world.first.as_ref().unwrap().clone().into_luma8(),
+            &world.second.as_ref().unwrap().clone().into_luma8(),
+            &rectangle_mask(width, height, x, y),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(
+    expr = "comparing the images using algorithm {string} as rgb ignoring a {int} by {int} pixel rectangle at {int}, {int}"
+)]
+fn compare_algorithm_rgb_masked(
+    world: &mut CompareWorld,
+    algorithm: String,
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+) {
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_similarity_structure_masked(
+            &parse_algorithm(&algorithm),
+            &world.first.as_ref().unwrap().clone().into_rgb8(),
+            &world.second.as_ref().unwrap().clone().into_rgb8(),
+            &rectangle_mask(width, height, x, y),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using algorithm {string} as 16 bit grayscale")]
+fn compare_algorithm_16_bit(world: &mut CompareWorld, algorithm: String) {
+    world.comparison_result = Some(
+        image_compare::gray_similarity_structure(
+            &parse_algorithm(&algorithm),
+            &world.first.as_ref().unwrap().clone().into_luma16(),
+            &world.second.as_ref().unwrap().clone().into_luma16(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using algorithm {string} as 16 bit rgb")]
+fn compare_algorithm_rgb_16_bit(world: &mut CompareWorld, algorithm: String) {
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_similarity_structure(
+            &parse_algorithm(&algorithm),
+            &world.first.as_ref().unwrap().clone().into_rgb16(),
+            &world.second.as_ref().unwrap().clone().into_rgb16(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using algorithm {string} as float grayscale")]
+fn compare_algorithm_float(world: &mut CompareWorld, algorithm: String) {
+    world.comparison_result = Some(
+        image_compare::gray_similarity_structure(
+            &parse_algorithm(&algorithm),
+            &world.first.as_ref().unwrap().to_luma32f(),
+            &world.second.as_ref().unwrap().to_luma32f(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using FSIMc as rgb")]
+fn compare_fsimc_rgb(world: &mut CompareWorld) {
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_similarity_fsimc(
+            &world.first.as_ref().unwrap().clone().into_rgb8(),
+            &world.second.as_ref().unwrap().clone().into_rgb8(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using FLIP as rgb")]
+fn compare_flip_rgb(world: &mut CompareWorld) {
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_similarity_flip(
+            &world.first.as_ref().unwrap().clone().into_rgb8(),
+            &world.second.as_ref().unwrap().clone().into_rgb8(),
+            &FlipConfig::default(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
 #[when(expr = "comparing the images using MSSIM as rgb")]
 fn compare_mssim_rgb(world: &mut CompareWorld) {
     world.comparison_result_rgb = Some(
@@ -118,6 +377,138 @@ fn compare_hybrid_blended_rgba(world: &mut CompareWorld, color: String) {
     );
 }
 
+#[when(
+    expr = "comparing the images using the gamma-correct blended hybrid mode with {string} background"
+)]
+fn compare_hybrid_blended_linear(world: &mut CompareWorld, color: String) {
+    let background = match color.as_str() {
+        "black" => Rgb([0, 0, 0]),
+        "white" => Rgb([255, 255, 255]),
+        _ => unimplemented!(),
+    };
+    let config = HybridConfig {
+        blending: Blending::Linear,
+        ..Default::default()
+    };
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_blended_hybrid_compare_with_config(
+            (&world.first.as_ref().unwrap().clone().into_rgba8()).into(),
+            (&world.second.as_ref().unwrap().clone().into_rgba8()).into(),
+            &Background::Color(background),
+            &config,
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+#[when(
+    expr = "comparing the images using the blended hybrid mode over a checkerboard of {int} pixels"
+)]
+fn compare_hybrid_blended_checkerboard(world: &mut CompareWorld, size: u32) {
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_blended_hybrid_compare_with_config(
+            (&world.first.as_ref().unwrap().clone().into_rgba8()).into(),
+            (&world.second.as_ref().unwrap().clone().into_rgba8()).into(),
+            &Background::checkerboard(size),
+            &HybridConfig::default(),
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+#[when(
+    expr = "comparing the images using the blended hybrid mode over black, white and a checkerboard"
+)]
+fn compare_hybrid_blended_worst_case(world: &mut CompareWorld) {
+    let backgrounds = [
+        Background::Color(Rgb([0, 0, 0])),
+        Background::Color(Rgb([255, 255, 255])),
+        Background::checkerboard(8),
+    ];
+    let result = image_compare::rgba_blended_hybrid_compare_worst_case(
+        (&world.first.as_ref().unwrap().clone().into_rgba8()).into(),
+        (&world.second.as_ref().unwrap().clone().into_rgba8()).into(),
+        &backgrounds,
+        &HybridConfig::default(),
+    )
+    .expect("Error comparing the two images!");
+    world.worst_background = Some(result.background);
+    world.comparison_result_rgba = Some(result.worst.similarity);
+}
+
+#[then(expr = "the worst background is number {int}")]
+fn check_worst_background(world: &mut CompareWorld, background: usize) {
+    assert_eq!(world.worst_background.unwrap(), background);
+}
+
+fn premultiply(image: &DynamicImage) -> RgbaImage {
+    let mut image = image.to_rgba8();
+    image.pixels_mut().for_each(|p| {
+        let alpha = p[3] as f32 / 255.;
+        for c in 0..3 {
+            p[c] = (p[c] as f32 * alpha).round() as u8;
+        }
+    });
+    image
+}
+
+#[when(
+    expr = "comparing the images using the hybrid mode ignoring colors up to {float} alpha as rgba"
+)]
+fn compare_hybrid_rgba_transparent_threshold(world: &mut CompareWorld, threshold: f32) {
+    let config = HybridConfig {
+        transparent_threshold: Some(threshold),
+        ..Default::default()
+    };
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_hybrid_compare_with_config(
+            &world.first.as_ref().unwrap().clone().into_rgba8(),
+            &world.second.as_ref().unwrap().clone().into_rgba8(),
+            &config,
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+#[when(expr = "comparing the premultiplied images using the hybrid mode as rgba")]
+fn compare_hybrid_rgba_premultiplied(world: &mut CompareWorld) {
+    let config = HybridConfig {
+        premultiplied: true,
+        ..Default::default()
+    };
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_hybrid_compare_with_config(
+            &premultiply(world.first.as_ref().unwrap()),
+            &premultiply(world.second.as_ref().unwrap()),
+            &config,
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+#[when(
+    expr = "comparing the premultiplied images using the blended hybrid mode with {string} background"
+)]
+fn compare_hybrid_blended_premultiplied(world: &mut CompareWorld, color: String) {
+    let background = match color.as_str() {
+        "black" => Rgb([0, 0, 0]),
+        "white" => Rgb([255, 255, 255]),
+        _ => unimplemented!(),
+    };
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_blended_hybrid_compare(
+            BlendInput::Premultiplied(&premultiply(world.first.as_ref().unwrap())),
+            BlendInput::Premultiplied(&premultiply(world.second.as_ref().unwrap())),
+            background,
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
 #[when(expr = "comparing the images using the hybrid mode as rgb")]
 fn compare_hybrid_rgb(world: &mut CompareWorld) {
     world.comparison_result_rgb = Some(
@@ -129,19 +520,370 @@ fn compare_hybrid_rgb(world: &mut CompareWorld) {
     );
 }
 
+#[when(
+    expr = "comparing the images using the hybrid mode ignoring a {int} by {int} pixel rectangle at {int}, {int} as rgb"
+)]
+fn compare_hybrid_rgb_masked(world: &mut CompareWorld, width: u32, height: u32, x: u32, y: u32) {
+    let config = HybridConfig {
+        mask: rectangle_mask(width, height, x, y),
+        ..Default::default()
+    };
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_hybrid_compare_with_config(
+            &world.first.as_ref().unwrap().clone().into_rgb8(),
+            &world.second.as_ref().unwrap().clone().into_rgb8(),
+            &config,
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+#[when(expr = "comparing the images using the hybrid mode as 16 bit rgb")]
+fn compare_hybrid_rgb_16_bit(world: &mut CompareWorld) {
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_hybrid_compare(
+            &world.first.as_ref().unwrap().clone().into_rgb16(),
+            &world.second.as_ref().unwrap().clone().into_rgb16(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using the hybrid mode as 16 bit rgba")]
+fn compare_hybrid_rgba_16_bit(world: &mut CompareWorld) {
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_hybrid_compare(
+            &world.first.as_ref().unwrap().clone().into_rgba16(),
+            &world.second.as_ref().unwrap().clone().into_rgba16(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(
+    expr = "comparing the images using the blended hybrid mode with a white background as 16 bit"
+)]
+fn compare_hybrid_blended_rgba_16_bit(world: &mut CompareWorld) {
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_blended_hybrid_compare(
+            (&world.first.as_ref().unwrap().clone().into_rgba16()).into(),
+            (&world.second.as_ref().unwrap().clone().into_rgba16()).into(),
+            Rgb([u16::MAX; 3]),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using the hybrid mode as float rgb")]
+fn compare_hybrid_rgb_float(world: &mut CompareWorld) {
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_hybrid_compare(
+            &world.first.as_ref().unwrap().clone().into_rgb32f(),
+            &world.second.as_ref().unwrap().clone().into_rgb32f(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using the hybrid mode as float rgba")]
+fn compare_hybrid_rgba_float(world: &mut CompareWorld) {
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_hybrid_compare(
+            &world.first.as_ref().unwrap().clone().into_rgba32f(),
+            &world.second.as_ref().unwrap().clone().into_rgba32f(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[when(expr = "comparing the images using the hybrid mode with {string} on luma as rgb")]
+fn compare_hybrid_rgb_luma(world: &mut CompareWorld, algorithm: String) {
+    let config = HybridConfig {
+        luma: parse_algorithm(&algorithm),
+        ..Default::default()
+    };
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_hybrid_compare_with_config(
+            &world.first.as_ref().unwrap().clone().into_rgb8(),
+            &world.second.as_ref().unwrap().clone().into_rgb8(),
+            &config,
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+#[when(expr = "comparing the images using the hybrid mode with {string} on luma as rgba")]
+fn compare_hybrid_rgba_luma(world: &mut CompareWorld, algorithm: String) {
+    let config = HybridConfig {
+        luma: parse_algorithm(&algorithm),
+        ..Default::default()
+    };
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_hybrid_compare_with_config(
+            &world.first.as_ref().unwrap().clone().into_rgba8(),
+            &world.second.as_ref().unwrap().clone().into_rgba8(),
+            &config,
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+#[when(expr = "comparing the images using the hybrid mode with {string} on chroma as rgb")]
+fn compare_hybrid_rgb_chroma(world: &mut CompareWorld, algorithm: String) {
+    let config = HybridConfig {
+        chroma: parse_algorithm(&algorithm),
+        ..Default::default()
+    };
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_hybrid_compare_with_config(
+            &world.first.as_ref().unwrap().clone().into_rgb8(),
+            &world.second.as_ref().unwrap().clone().into_rgb8(),
+            &config,
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+#[when(
+    expr = "comparing the images using the hybrid mode with {string} on chroma and alpha as rgba"
+)]
+fn compare_hybrid_rgba_chroma_alpha(world: &mut CompareWorld, algorithm: String) {
+    let config = HybridConfig {
+        chroma: parse_algorithm(&algorithm),
+        alpha: parse_algorithm(&algorithm),
+        ..Default::default()
+    };
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_hybrid_compare_with_config(
+            &world.first.as_ref().unwrap().clone().into_rgba8(),
+            &world.second.as_ref().unwrap().clone().into_rgba8(),
+            &config,
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+fn parse_combination(name: &str) -> ChannelCombination {
+    match name {
+        "minimum" => ChannelCombination::Minimum,
+        "product" => ChannelCombination::Product,
+        "structure weighted" => ChannelCombination::WeightedMean {
+            structure: 3.,
+            color: 1.,
+            alpha: 1.,
+        },
+        "color weighted" => ChannelCombination::WeightedMean {
+            structure: 1.,
+            color: 3.,
+            alpha: 1.,
+        },
+        _ => panic!("Unknown channel combination: {name}"),
+    }
+}
+
+#[when(expr = "comparing the images using the hybrid mode with {string} combination as rgb")]
+fn compare_hybrid_rgb_combination(world: &mut CompareWorld, combination: String) {
+    let config = HybridConfig {
+        combination: parse_combination(&combination),
+        ..Default::default()
+    };
+    world.comparison_result_rgb = Some(
+        image_compare::rgb_hybrid_compare_with_config(
+            &world.first.as_ref().unwrap().clone().into_rgb8(),
+            &world.second.as_ref().unwrap().clone().into_rgb8(),
+            &config,
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+#[when(expr = "comparing the images using the hybrid mode with {string} combination as rgba")]
+fn compare_hybrid_rgba_combination(world: &mut CompareWorld, combination: String) {
+    let config = HybridConfig {
+        combination: parse_combination(&combination),
+        ..Default::default()
+    };
+    world.comparison_result_rgba = Some(
+        image_compare::rgba_hybrid_compare_with_config(
+            &world.first.as_ref().unwrap().clone().into_rgba8(),
+            &world.second.as_ref().unwrap().clone().into_rgba8(),
+            &config,
+        )
+        .expect("Error comparing the two images!")
+        .similarity,
+    );
+}
+
+fn parse_color_space(name: &str) -> ColorSpace {
+    match name {
+        "BT.601" => ColorSpace::Bt601,
+        "BT.709" => ColorSpace::Bt709,
+        "BT.2020" => ColorSpace::Bt2020,
+        "CIELAB" => ColorSpace::Lab,
+        "OKLab" => ColorSpace::OkLab,
+        _ => panic!("Unknown color space: {name}"),
+    }
+}
+
+#[when(expr = "comparing the images using the hybrid mode in {string} as rgb")]
+fn compare_hybrid_rgb_color_space(world: &mut CompareWorld, color_space: String) {
+    let config = HybridConfig {
+        color_space: parse_color_space(&color_space),
+        ..Default::default()
+    };
+    let result = image_compare::rgb_hybrid_compare_with_config(
+        &world.first.as_ref().unwrap().clone().into_rgb8(),
+        &world.second.as_ref().unwrap().clone().into_rgb8(),
+        &config,
+    )
+    .expect("Error comparing the two images!");
+    world.color_space = Some(result.color_space);
+    world.comparison_result_rgb = Some(result.similarity);
+}
+
+#[when(expr = "comparing the images using the hybrid mode in {string} as rgba")]
+fn compare_hybrid_rgba_color_space(world: &mut CompareWorld, color_space: String) {
+    let config = HybridConfig {
+        color_space: parse_color_space(&color_space),
+        ..Default::default()
+    };
+    let result = image_compare::rgba_hybrid_compare_with_config(
+        &world.first.as_ref().unwrap().clone().into_rgba8(),
+        &world.second.as_ref().unwrap().clone().into_rgba8(),
+        &config,
+    )
+    .expect("Error comparing the two images!");
+    world.color_space = Some(result.color_space);
+    world.comparison_result_rgba = Some(result.similarity);
+}
+
+#[when(expr = "comparing the images by their color types")]
+fn compare_dynamic(world: &mut CompareWorld) {
+    let result = image_compare::compare(
+        world.first.as_ref().unwrap(),
+        world.second.as_ref().unwrap(),
+        &CompareOptions::default(),
+    )
+    .expect("Error comparing the two images!");
+    world.comparison_path = Some(result.path);
+    world.comparison_result = Some(result.similarity);
+}
+
+#[then(expr = "the comparison path is {string}")]
+fn check_comparison_path(world: &mut CompareWorld, path: String) {
+    assert_eq!(world.comparison_path.unwrap().to_string(), path);
+}
+
+#[then(expr = "the images were decomposed in {string}")]
+fn check_color_space(world: &mut CompareWorld, color_space: String) {
+    assert_eq!(world.color_space.unwrap().to_string(), color_space);
+}
+
+#[when(expr = "comparing the images using pixelmatch")]
+fn compare_pixelmatch(world: &mut CompareWorld) {
+    world.pixelmatch_result = Some(
+        image_compare::rgba_pixelmatch_compare(
+            &world.first.as_ref().unwrap().clone().into_rgba8(),
+            &world.second.as_ref().unwrap().clone().into_rgba8(),
+            &PixelmatchConfig::default(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[then(expr = "the number of different pixels is {int}")]
+fn check_different_pixels(world: &mut CompareWorld, count: usize) {
+    assert_eq!(
+        world.pixelmatch_result.as_ref().unwrap().different_pixels,
+        count
+    );
+}
+
+#[then(expr = "the number of anti-aliased pixels is {int}")]
+fn check_anti_aliased_pixels(world: &mut CompareWorld, count: usize) {
+    assert_eq!(
+        world
+            .pixelmatch_result
+            .as_ref()
+            .unwrap()
+            .anti_aliased_pixels,
+        count
+    );
+}
+
+#[when(expr = "comparing the images by the color difference {string}")]
+fn compare_color_difference(world: &mut CompareWorld, formula: String) {
+    let formula = match formula.as_str() {
+        "CIE76" => DeltaE::CIE76,
+        "CIE94" => DeltaE::CIE94,
+        "CIEDE2000" => DeltaE::CIEDE2000,
+        _ => panic!("Unknown ΔE formula: {formula}"),
+    };
+    world.color_difference = Some(
+        image_compare::rgb_color_difference(
+            formula,
+            &world.first.as_ref().unwrap().clone().into_rgb8(),
+            &world.second.as_ref().unwrap().clone().into_rgb8(),
+        )
+        .expect("Error comparing the two images!"),
+    );
+}
+
+#[then(expr = "the mean color difference is {float}")]
+fn check_mean_color_difference(world: &mut CompareWorld, mean: f64) {
+    assert_eq!(world.color_difference.as_ref().unwrap().mean, mean);
+}
+
+#[then(expr = "the maximum color difference is {float}")]
+fn check_max_color_difference(world: &mut CompareWorld, max: f64) {
+    assert_eq!(world.color_difference.as_ref().unwrap().max, max);
+}
+
+#[then(expr = "the 95th percentile of the color difference is {float}")]
+fn check_percentile_color_difference(world: &mut CompareWorld, percentile: f64) {
+    assert_eq!(
+        world.color_difference.as_ref().unwrap().percentile(95.),
+        percentile
+    );
+}
+
 #[then(expr = "the similarity score is {float}")]
 fn check_result_score(world: &mut CompareWorld, score: f64) {
-    if world.comparison_result.is_some() {
-        assert_eq!(world.comparison_result.as_ref().unwrap().score, score);
-    } else if world.comparison_result_rgb.is_some() {
-        assert_eq!(world.comparison_result_rgb.as_ref().unwrap().score, score);
-    } else if world.comparison_result_rgba.is_some() {
-        assert_eq!(world.comparison_result_rgba.as_ref().unwrap().score, score);
+    if let Some(result) = &world.comparison_result {
+        assert_eq!(result.score, score);
+    } else if let Some(result) = &world.comparison_result_rgb {
+        assert_eq!(result.score, score);
+    } else if let Some(result) = &world.comparison_result_rgba {
+        assert_eq!(result.score, score);
     } else {
         panic!("No result calculated yet")
     }
 }
 
+#[then(expr = "the similarity image shows {int} masked pixels")]
+fn check_masked_pixels(world: &mut CompareWorld, count: usize) {
+    let result = world
+        .comparison_result
+        .as_ref()
+        .or(world.comparison_result_rgb.as_ref())
+        .or(world.comparison_result_rgba.as_ref())
+        .expect("No result calculated yet");
+    let masked = result
+        .image
+        .to_color_map()
+        .into_rgba8()
+        .pixels()
+        .filter(|p| p[3] == 0)
+        .count();
+    assert_eq!(masked, count);
+}
+
 #[then(expr = "the similarity image matches {string}")]
 fn check_result_image(world: &mut CompareWorld, reference: String) {
     let img = world
@@ -182,6 +924,27 @@ fn check_result_image_rgba(world: &mut CompareWorld, reference: String) {
     );
 }
 
+#[then(
+    expr = "the rgba similarity image shows {int} color differences where both images are transparent"
+)]
+fn check_transparent_color_differences(world: &mut CompareWorld, count: usize) {
+    let first = world.first.as_ref().unwrap().to_rgba8();
+    let second = world.second.as_ref().unwrap().to_rgba8();
+    let map = world
+        .comparison_result_rgba
+        .as_ref()
+        .unwrap()
+        .image
+        .to_color_map()
+        .into_rgba8();
+    let differences = map
+        .enumerate_pixels()
+        .filter(|(x, y, _)| first.get_pixel(*x, *y)[3] == 0 && second.get_pixel(*x, *y)[3] == 0)
+        .filter(|(_, _, p)| p[0] > 0 || p[1] > 0 || p[2] > 0)
+        .count();
+    assert_eq!(differences, count);
+}
+
 #[then(expr = "the rgb similarity image matches {string}")]
 fn check_result_image_rgb(world: &mut CompareWorld, reference: String) {
     let img = world
@@ -209,4 +972,7 @@ async fn main() {
     CompareWorld::run("tests/features/structure_rgb.feature").await;
     CompareWorld::run("tests/features/hybrid_rgb.feature").await;
     CompareWorld::run("tests/features/hybrid_rgba.feature").await;
+    CompareWorld::run("tests/features/pixelmatch.feature").await;
+    CompareWorld::run("tests/features/color_difference.feature").await;
+    CompareWorld::run("tests/features/dynamic.feature").await;
 }
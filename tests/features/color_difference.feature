@@ -0,0 +1,16 @@
+Feature: RGB image comparison by the color difference ΔE in CIELAB
+
+  Scenario Outline: Comparing a modified image to the original by ΔE
+    Given the images '<first_image>' and '<compare_image>' are loaded
+    When comparing the images by the color difference '<formula>'
+    Then the mean color difference is <mean>
+    And the maximum color difference is <max>
+    And the 95th percentile of the color difference is <percentile>
+
+    Examples:
+      | first_image                       | compare_image                             | formula   | mean              | max                | percentile         |
+      | tests/data/pad_gaprao.png         | tests/data/pad_gaprao.png                 | CIEDE2000 | 0.0               | 0.0                | 0.0                |
+      | tests/data/pad_gaprao.png         | tests/data/pad_gaprao_lighter.png         | CIE76     | 8.053722164077206 | 19.578994321021877 | 13.664996147155762 |
+      | tests/data/pad_gaprao.png         | tests/data/pad_gaprao_lighter.png         | CIE94     | 7.543228490869952 | 15.269080528136566 | 12.005727767944336 |
+      | tests/data/pad_gaprao.png         | tests/data/pad_gaprao_lighter.png         | CIEDE2000 | 6.090867620934583 | 16.322786840822918 | 9.422616958618164  |
+      | tests/data/colored_primitives.png | tests/data/colored_primitives_swapped.png | CIEDE2000 | 9.169669351222852 | 86.60823745353736  | 83.18587493896484  |
@@ -0,0 +1,14 @@
+Feature: Comparing images of any color type, choosing the comparison by the color types
+
+  Scenario Outline: Comparing images with the same or different color types
+    Given the images '<first_image>' and '<second_image>' are loaded
+    When comparing the images by their color types
+    Then the comparison path is '<path>'
+    And the similarity score is <result>
+
+    Examples:
+      | first_image                                   | second_image                                   | path                         | result                |
+      | tests/data/pad_gaprao.png                     | tests/data/pad_gaprao.png                      | rgb hybrid, 8 bit            | 1.0                   |
+      | tests/data/pad_gaprao.png                     | tests/data/pad_gaprao_gray_inverted.png        | rgb hybrid, 8 bit, promoted  | 3.2566565189821024e-5 |
+      | tests/data/pad_gaprao.png                     | tests/data/pad_gaprao_alpha.png                | rgba hybrid, 8 bit, promoted | 0.9540830736098154    |
+      | tests/data/pad_graparo_broken_rms_compare.png | tests/data/pad_graparo_broken_ssim_compare.png | gray structure, 8 bit        | 0.8751054694433287    |
@@ -45,3 +45,37 @@ Feature: Gray image comparison using histogram similarity with different metrics
       | tests/data/pad_gaprao_lighter.png       | 0.21725162902677742 |
       | tests/data/pad_gaprao_noise.png         | 0.41048980725794537 |
       | tests/data/pad_gaprao_gray_inverted.png | 0.22138675253275514 |
+
+  Scenario Outline: Comparing a modified image to the original using histogram intersection with 16 bit bins
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using histogram 'intersection' as 16 bit grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result   |
+      | tests/data/pad_gaprao.png               | 480000.0 |
+      | tests/data/pad_gaprao_lighter.png       | 391363.0 |
+      | tests/data/pad_gaprao_noise.png         | 312255.0 |
+      | tests/data/pad_gaprao_gray_inverted.png | 364095.0 |
+
+  Scenario Outline: Comparing float images using hellinger distance of histograms with 256 bins
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using histogram 'hellinger distance' as float grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result              |
+      | tests/data/pad_gaprao.png               | 0.0                 |
+      | tests/data/pad_gaprao_lighter.png       | 0.21725162902677742 |
+      | tests/data/pad_gaprao_noise.png         | 0.41048980725794537 |
+      | tests/data/pad_gaprao_gray_inverted.png | 0.22138675253275514 |
+
+  Scenario Outline: Comparing a broken image to the original excluding a region using hellinger distance
+    Given the images 'tests/data/pad_gaprao.png' and 'tests/data/pad_gaprao_broken.png' are loaded
+    When comparing the images using histogram 'hellinger distance' as grayscale ignoring a <width> by 405 pixel rectangle at 15, 36
+    Then the similarity score is <result>
+
+    Examples:
+      | width | result              |
+      | 688   | 0.0                 |
+      | 344   | 0.04996146025108686 |
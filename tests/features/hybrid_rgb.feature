@@ -1,4 +1,4 @@
-Feature: RGB image comparison using hybrid mode - MSSIM for for Y channel, RMS for U and V
+Feature: RGB image comparison using hybrid mode - MSSIM for Y channel, RMS for U and V
 
   Scenario: Comparing an image to the original with hybrid mode and checking the difference image
   Given the images 'tests/data/colored_primitives.png' and 'tests/data/colored_primitives_swapped.png' are loaded
@@ -18,3 +18,109 @@ Examples:
 | tests/data/pad_gaprao_noise.png         | 0.13009783371684705   |
 | tests/data/pad_gaprao_gray_inverted.png | 3.2566565189821024e-5 |
 | tests/data/pad_gaprao_color_filters.png | 0.9876923700357477    |
+
+Scenario Outline: Comparing a modified image to the original using hybrid mode with MS-SSIM on luma
+Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+When comparing the images using the hybrid mode with 'MS-SSIM' on luma as rgb
+Then the similarity score is <result>
+
+Examples:
+| compare_image                           | result                |
+| tests/data/pad_gaprao.png               | 1.0                   |
+| tests/data/pad_gaprao_lighter.png       | 0.9869916577295711    |
+| tests/data/pad_gaprao_color_filters.png | 0.9931584660381079    |
+
+Scenario Outline: Comparing a modified image to the original using hybrid mode with a faster luma algorithm
+Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+When comparing the images using the hybrid mode with '<algorithm>' on luma as rgb
+Then the similarity score is <result>
+
+Examples:
+| compare_image                           | algorithm | result             |
+| tests/data/pad_gaprao_lighter.png       | RMS       | 0.9274116873331368 |
+| tests/data/pad_gaprao_lighter.png       | GMSD      | 0.9883450855046821 |
+| tests/data/pad_gaprao_color_filters.png | GMSD      | 0.9953180650314238 |
+
+Scenario Outline: Comparing a modified image to the original using hybrid mode with a custom chroma algorithm
+Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+When comparing the images using the hybrid mode with '<algorithm>' on chroma as rgb
+Then the similarity score is <result>
+
+Examples:
+| compare_image                           | algorithm | result             |
+| tests/data/pad_gaprao.png               | MSSIM     | 1.0                |
+| tests/data/pad_gaprao_lighter.png       | MSSIM     | 0.9489048334769905 |
+| tests/data/pad_gaprao_lighter.png       | RMS       | 0.9514066504143178 |
+| tests/data/pad_gaprao_color_filters.png | MSSIM     | 0.9739762734713654 |
+| tests/data/pad_gaprao_color_filters.png | GMSD      | 0.9876795348240683 |
+
+Scenario Outline: Comparing a modified image to the original using hybrid mode with another channel combination
+Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+When comparing the images using the hybrid mode with '<combination>' combination as rgb
+Then the similarity score is <result>
+
+Examples:
+| compare_image                           | combination        | result             |
+| tests/data/pad_gaprao.png               | structure weighted | 1.0                |
+| tests/data/pad_gaprao_lighter.png       | minimum            | 0.9514066504143178 |
+| tests/data/pad_gaprao_lighter.png       | product            | 0.9434835398401948 |
+| tests/data/pad_gaprao_lighter.png       | structure weighted | 0.9614490779648845 |
+| tests/data/pad_gaprao_lighter.png       | color weighted     | 0.9815339126572012 |
+| tests/data/pad_gaprao_color_filters.png | structure weighted | 0.9832287040898265 |
+| tests/data/pad_gaprao_color_filters.png | color weighted     | 0.9743013518319775 |
+| tests/data/pad_gaprao_color_filters.png | minimum            | 0.9876923700357477 |
+| tests/data/pad_gaprao_color_filters.png | product            | 0.9579317336305044 |
+
+Scenario Outline: Comparing a modified image to the original using hybrid mode in another color space
+Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+When comparing the images using the hybrid mode in '<color_space>' as rgb
+Then the similarity score is <result>
+And the images were decomposed in '<color_space>'
+
+Examples:
+| compare_image                           | color_space | result             |
+| tests/data/pad_gaprao.png               | OKLab       | 1.0                |
+| tests/data/pad_gaprao_lighter.png       | BT.601      | 0.9514066504143178 |
+| tests/data/pad_gaprao_lighter.png       | BT.709      | 0.9465646912763516 |
+| tests/data/pad_gaprao_lighter.png       | BT.2020     | 0.9467167198854188 |
+| tests/data/pad_gaprao_lighter.png       | CIELAB      | 0.948525712932398  |
+| tests/data/pad_gaprao_lighter.png       | OKLab       | 0.9579499811075628 |
+| tests/data/pad_gaprao_color_filters.png | BT.709      | 0.9885494438352684 |
+| tests/data/pad_gaprao_color_filters.png | OKLab       | 0.986765560682118  |
+
+Scenario Outline: Comparing a modified 16 bit image to the original using hybrid mode algorithm
+Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+When comparing the images using the hybrid mode as 16 bit rgb
+Then the similarity score is <result>
+
+Examples:
+| compare_image                           | result                 |
+| tests/data/pad_gaprao.png               | 1.0                    |
+| tests/data/pad_gaprao_lighter.png       | 0.9529171940890451     |
+| tests/data/pad_gaprao_noise.png         | 0.13007098458792243    |
+| tests/data/pad_gaprao_gray_inverted.png | 3.0111656648417312e-5  |
+| tests/data/pad_gaprao_color_filters.png | 0.9903988882792493     |
+
+Scenario Outline: Comparing a modified float image to the original using hybrid mode algorithm
+Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+When comparing the images using the hybrid mode as float rgb
+Then the similarity score is <result>
+
+Examples:
+| compare_image                           | result                 |
+| tests/data/pad_gaprao.png               | 1.0                    |
+| tests/data/pad_gaprao_lighter.png       | 0.9529145617929597     |
+| tests/data/pad_gaprao_noise.png         | 0.13007062969023173    |
+| tests/data/pad_gaprao_gray_inverted.png | 3.0059762919942537e-5  |
+| tests/data/pad_gaprao_color_filters.png | 0.9903994828479985     |
+
+Scenario Outline: Comparing a modified image to the original using hybrid mode excluding a region
+Given the images 'tests/data/colored_primitives.png' and 'tests/data/colored_primitives_swapped.png' are loaded
+When comparing the images using the hybrid mode ignoring a <width> by 386 pixel rectangle at 40, 93 as rgb
+Then the similarity score is <result>
+And the similarity image shows <masked> masked pixels
+
+Examples:
+| width | result             | masked |
+| 364   | 1.0                | 140504 |
+| 182   | 0.9695384290035348 | 70252  |
@@ -1,4 +1,4 @@
-Feature: RGBA image comparison using hybrid mode - MSSIM for for Y, Alpha channels, RMS for U and V
+Feature: RGBA image comparison using hybrid mode - MSSIM for Y channel, RMS for U, V and alpha channels
 
   Scenario: Comparing an image to the original with hybrid mode and checking the difference image
     Given the images 'tests/data/colored_primitives_swapped_alpha.png' and 'tests/data/colored_primitives_alpha.png' are loaded
@@ -21,6 +21,82 @@ Feature: RGBA image comparison using hybrid mode - MSSIM for for Y, Alpha channe
     When comparing the images using the blended hybrid mode with 'white' background
     Then the similarity score is 0.6303176177525529
 
+  Scenario: Hidden colors of transparent pixels show up as differences by default
+    Given the images 'tests/data/100/typed_alpha.png' and 'tests/data/100/typed_color_changed.png' are loaded
+    When comparing the images using the hybrid mode as rgba
+    Then the rgba similarity image shows 2292 color differences where both images are transparent
+
+  Scenario Outline: Ignoring the colors of transparent pixels
+    Given the images 'tests/data/100/typed_alpha.png' and 'tests/data/100/typed_color_changed.png' are loaded
+    When comparing the images using the hybrid mode ignoring colors up to <threshold> alpha as rgba
+    Then the similarity score is <result>
+    And the rgba similarity image shows 0 color differences where both images are transparent
+
+    Examples:
+      | threshold | result             |
+      | 0.0       | 0.9748343863750435 |
+      | 0.1       | 0.9748631421533034 |
+      | 0.5       | 0.9751097819587571 |
+
+  Scenario Outline: Comparing premultiplied images converts them to straight alpha
+    Given the images '<first_image>' and '<second_image>' are loaded
+    When comparing the premultiplied images using the hybrid mode as rgba
+    Then the similarity score is <result>
+
+    Examples:
+      | first_image                    | second_image                           | result             |
+      | tests/data/100/hand_white.png  | tests/data/100/typed_alpha.png         | 0.0063582524267797 |
+      | tests/data/100/typed_alpha.png | tests/data/100/typed_color_changed.png | 0.974826575266043  |
+      | tests/data/pad_gaprao.png      | tests/data/pad_gaprao_alpha.png        | 0.9539276563885302 |
+
+  Scenario Outline: Comparing premultiplied images in front of a background
+    Given the images '<first_image>' and '<second_image>' are loaded
+    When comparing the premultiplied images using the blended hybrid mode with '<background>' background
+    Then the similarity score is <result>
+
+    Examples:
+      | first_image                   | second_image                    | background | result              |
+      | tests/data/100/hand_white.png | tests/data/100/typed_alpha.png  | black      | 0.00744751294504923 |
+      | tests/data/100/hand_white.png | tests/data/100/typed_alpha.png  | white      | 0.6303174444835946  |
+      | tests/data/pad_gaprao.png     | tests/data/pad_gaprao_alpha.png | black      | 0.9070298431990901  |
+      | tests/data/pad_gaprao.png     | tests/data/pad_gaprao_alpha.png | white      | 0.9991823065757751  |
+
+  Scenario Outline: Comparing transparent images blended gamma-correct in front of a background
+    Given the images '<first_image>' and '<second_image>' are loaded
+    When comparing the images using the gamma-correct blended hybrid mode with '<background>' background
+    Then the similarity score is <result>
+
+    Examples:
+      | first_image                    | second_image                           | background | result               |
+      | tests/data/100/hand_white.png  | tests/data/100/typed_alpha.png         | black      | 0.007438025239920907 |
+      | tests/data/100/hand_white.png  | tests/data/100/typed_alpha.png         | white      | 0.6306810653894672   |
+      | tests/data/100/typed_alpha.png | tests/data/100/typed_color_changed.png | black      | 0.9559251205937471   |
+      | tests/data/pad_gaprao.png      | tests/data/pad_gaprao_alpha.png        | black      | 0.9197300454057442   |
+      | tests/data/pad_gaprao.png      | tests/data/pad_gaprao_alpha.png        | white      | 0.999119954776764    |
+
+  Scenario Outline: Comparing two images where one is transparent in front of a checkerboard
+    Given the images 'tests/data/100/hand_white.png' and 'tests/data/100/typed_alpha.png' are loaded
+    When comparing the images using the blended hybrid mode over a checkerboard of <size> pixels
+    Then the similarity score is <result>
+
+    Examples:
+      | size | result              |
+      | 1    | 0.12540716462018686 |
+      | 8    | 0.6208063129875683  |
+      | 16   | 0.620598868056824   |
+
+  Scenario Outline: Comparing transparent images in front of several backgrounds reports the worst case
+    Given the images '<first_image>' and '<second_image>' are loaded
+    When comparing the images using the blended hybrid mode over black, white and a checkerboard
+    Then the similarity score is <result>
+    And the worst background is number <background>
+
+    Examples:
+      | first_image                    | second_image                           | result               | background |
+      | tests/data/100/hand_white.png  | tests/data/100/typed_alpha.png         | 0.007447309291571148 | 0          |
+      | tests/data/100/typed_alpha.png | tests/data/100/typed_color_changed.png | 0.9540775528184895   | 0          |
+      | tests/data/pad_gaprao.png      | tests/data/pad_gaprao_alpha.png        | 0.9070446547819302   | 0          |
+
 
 
 
@@ -43,3 +119,69 @@ Feature: RGBA image comparison using hybrid mode - MSSIM for for Y, Alpha channe
       | tests/data/pad_gaprao_gray_inverted.png | 3.2566565189821024e-5 |
       | tests/data/pad_gaprao_color_filters.png | 0.9876923700357477    |
       | tests/data/pad_gaprao_alpha.png         | 0.9540830736098154    |
+
+  Scenario Outline: Comparing a modified image to the original using hybrid mode with a custom luma algorithm
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using the hybrid mode with '<algorithm>' on luma as rgba
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | algorithm       | result                |
+      | tests/data/pad_gaprao.png               | SSIM 4px blocks | 1.0                   |
+      | tests/data/pad_gaprao_lighter.png       | SSIM 4px blocks | 0.945532225450625     |
+      | tests/data/pad_gaprao_alpha.png         | SSIM 4px blocks | 0.9543263904226199    |
+      | tests/data/pad_gaprao_alpha.png         | MS-SSIM         | 0.968927833939918     |
+
+  Scenario Outline: Comparing a modified image to the original using hybrid mode with a custom chroma and alpha algorithm
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using the hybrid mode with '<algorithm>' on chroma and alpha as rgba
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                     | algorithm | result             |
+      | tests/data/pad_gaprao_lighter.png | MSSIM     | 0.9489048334769905 |
+      | tests/data/pad_gaprao_alpha.png   | MSSIM     | 0.8578670053043949 |
+      | tests/data/pad_gaprao_alpha.png   | RMS       | 0.9540830736098154 |
+      | tests/data/pad_gaprao_alpha.png   | GMSD      | 0.923843341330897  |
+
+  Scenario Outline: Comparing a modified image to the original using hybrid mode with another channel combination
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using the hybrid mode with '<combination>' combination as rgba
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                     | combination        | result             |
+      | tests/data/pad_gaprao_alpha.png   | minimum            | 0.9540830736098154 |
+      | tests/data/pad_gaprao_alpha.png   | product            | 0.9499602897984089 |
+      | tests/data/pad_gaprao_alpha.png   | structure weighted | 0.9761467048890888 |
+      | tests/data/pad_gaprao_alpha.png   | color weighted     | 0.9967720845455925 |
+      | tests/data/pad_gaprao_lighter.png | structure weighted | 0.9691592622393121 |
+
+  Scenario Outline: Comparing a modified image to the original using hybrid mode in another color space
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using the hybrid mode in '<color_space>' as rgba
+    Then the similarity score is <result>
+    And the images were decomposed in '<color_space>'
+
+    Examples:
+      | compare_image                   | color_space | result             |
+      | tests/data/pad_gaprao_alpha.png | BT.601      | 0.9540830736098154 |
+      | tests/data/pad_gaprao_alpha.png | BT.709      | 0.9548546101101049 |
+      | tests/data/pad_gaprao_alpha.png | BT.2020     | 0.9537536683993696 |
+      | tests/data/pad_gaprao_alpha.png | CIELAB      | 0.9543805995060421 |
+      | tests/data/pad_gaprao_alpha.png | OKLab       | 0.9541172423998316 |
+
+  Scenario: Comparing two 16 bit images where one is transparent and one is not
+    Given the images 'tests/data/100/hand_white.png' and 'tests/data/100/typed_alpha.png' are loaded
+    When comparing the images using the hybrid mode as 16 bit rgba
+    Then the similarity score is 0.0064023479784048164
+
+  Scenario: Comparing two 16 bit images where one is transparent in front of white background
+    Given the images 'tests/data/100/hand_white.png' and 'tests/data/100/typed_alpha.png' are loaded
+    When comparing the images using the blended hybrid mode with a white background as 16 bit
+    Then the similarity score is 0.6303899718935497
+
+  Scenario: Comparing two float images where one is transparent and one is not
+    Given the images 'tests/data/100/hand_white.png' and 'tests/data/100/typed_alpha.png' are loaded
+    When comparing the images using the hybrid mode as float rgba
+    Then the similarity score is 0.006402493958292092
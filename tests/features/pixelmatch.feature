@@ -0,0 +1,14 @@
+Feature: RGBA image comparison modeled on pixelmatch
+
+  Scenario Outline: Comparing a modified image to the original using pixelmatch
+    Given the images '<first_image>' and '<compare_image>' are loaded
+    When comparing the images using pixelmatch
+    Then the number of different pixels is <different>
+    And the number of anti-aliased pixels is <anti_aliased>
+
+    Examples:
+      | first_image                        | compare_image                              | different | anti_aliased |
+      | tests/data/pad_gaprao.png          | tests/data/pad_gaprao.png                  | 0         | 0            |
+      | tests/data/pad_gaprao.png          | tests/data/pad_gaprao_broken.png           | 15788     | 2            |
+      | tests/data/pad_gaprao.png          | tests/data/pad_gaprao_lighter.png          | 115706    | 234          |
+      | tests/data/colored_primitives.png  | tests/data/colored_primitives_swapped.png  | 33727     | 0            |
@@ -26,6 +26,123 @@ Feature: Gray image comparison using MSSIM and RMS
       | tests/data/pad_gaprao_gray_inverted.png | -0.6559340036804088 |
       | tests/data/pad_gaprao_color_filters.png | 0.9885149408030369  |
 
+  Scenario Outline: Comparing a modified image to the original using gaussian weighted MSSIM algorithm
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using Gaussian MSSIM as grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result              |
+      | tests/data/pad_gaprao.png               | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | 0.9715913327287858  |
+      | tests/data/pad_gaprao_noise.png         | 0.1193789557404212  |
+      | tests/data/pad_gaprao_gray_inverted.png | -0.3249499309787582 |
+      | tests/data/pad_gaprao_color_filters.png | 0.9909547136861488  |
+
+  Scenario Outline: Comparing a modified image to the original using sliding window MSSIM algorithm
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using sliding MSSIM with stride <stride> as grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | stride | result              |
+      | tests/data/pad_gaprao.png               | 1      | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | 1      | 0.946806476169151   |
+      | tests/data/pad_gaprao_noise.png         | 1      | 0.12552320187062607 |
+      | tests/data/pad_gaprao_gray_inverted.png | 1      | -0.655477953751623  |
+      | tests/data/pad_gaprao_color_filters.png | 1      | 0.9881808270905611  |
+      | tests/data/pad_gaprao_lighter.png       | 8      | 0.9465500206208791  |
+      | tests/data/pad_gaprao_lighter.png       | 3      | 0.9468459528330747  |
+
+  Scenario Outline: Comparing a modified image to the original using multi-scale SSIM algorithm
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm 'MS-SSIM' as grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result              |
+      | tests/data/pad_gaprao.png               | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | 0.9857421695198622  |
+      | tests/data/pad_gaprao_noise.png         | 0.5266118702376292  |
+      | tests/data/pad_gaprao_gray_inverted.png | 0.0                 |
+      | tests/data/pad_gaprao_color_filters.png | 0.9923274797962266  |
+
+  Scenario Outline: Comparing a modified image to the original using SSIM with a custom configuration
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm '<algorithm>' as grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | algorithm       | result              |
+      | tests/data/pad_gaprao.png               | SSIM 4px blocks | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | SSIM 4px blocks | 0.9406811783143995  |
+      | tests/data/pad_gaprao_noise.png         | SSIM 4px blocks | 0.08156155678909352 |
+
+  Scenario Outline: Comparing a modified image to the original using complex wavelet SSIM algorithm
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm 'CW-SSIM' as grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result              |
+      | tests/data/pad_gaprao.png               | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | 0.992551557981108   |
+      | tests/data/pad_gaprao_noise.png         | 0.47406731406790387 |
+      | tests/data/pad_gaprao_gray_inverted.png | 0.9991416100257791  |
+      | tests/data/pad_gaprao_color_filters.png | 0.9956101322003303  |
+
+  Scenario Outline: Comparing a modified image to the original using GMSD algorithm
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm 'GMSD' as grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result              |
+      | tests/data/pad_gaprao.png               | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | 0.9601778449775289  |
+      | tests/data/pad_gaprao_noise.png         | 0.7039253512587494  |
+      | tests/data/pad_gaprao_gray_inverted.png | 0.9943560203654168  |
+      | tests/data/pad_gaprao_color_filters.png | 0.9663826316658009  |
+
+  Scenario Outline: Comparing a modified image to the original using FSIM algorithm
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm 'FSIM' as grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result              |
+      | tests/data/pad_gaprao.png               | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | 0.9838606097333777  |
+      | tests/data/pad_gaprao_noise.png         | 0.6852419836158478  |
+      | tests/data/pad_gaprao_gray_inverted.png | 0.9877642904156135  |
+      | tests/data/pad_gaprao_color_filters.png | 0.9898630595349064  |
+
+  Scenario Outline: Comparing a modified image to the original using raw MSE and PSNR
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm '<algorithm>' as grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | algorithm | result              |
+      | tests/data/pad_gaprao_lighter.png       | MSE       | 414.38848333333334  |
+      | tests/data/pad_gaprao_noise.png         | MSE       | 4023.8996354166666  |
+      | tests/data/pad_gaprao_lighter.png       | PSNR      | 21.95672683856325   |
+      | tests/data/pad_gaprao_noise.png         | PSNR      | 12.084332209095777  |
+
+  Scenario Outline: Comparing a modified image to the original using absolute errors
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm '<algorithm>' as grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                     | algorithm                | result              |
+      | tests/data/pad_gaprao_lighter.png | MAE                      | 18.63               |
+      | tests/data/pad_gaprao_broken.png  | MAE                      | 2.879183333333333   |
+      | tests/data/pad_gaprao_lighter.png | peak error               | 36.0                |
+      | tests/data/pad_gaprao_broken.png  | peak error               | 252.0               |
+      | tests/data/pad_gaprao_lighter.png | pixels over 16 levels    | 0.5106125           |
+      | tests/data/pad_gaprao_broken.png  | pixels over 16 levels    | 0.03761875          |
+
     Scenario: Comparing an image to the original with RMS and checking the difference image
       Given the images 'tests/data/pad_gaprao.png' and 'tests/data/pad_gaprao_broken.png' are loaded
       When comparing the images using RMS as grayscale
@@ -35,3 +152,54 @@ Feature: Gray image comparison using MSSIM and RMS
       Given the images 'tests/data/pad_gaprao.png' and 'tests/data/pad_gaprao_broken.png' are loaded
       When comparing the images using MSSIM as grayscale
       Then the similarity image matches 'tests/data/pad_graparo_broken_ssim_compare.png'
+
+  Scenario Outline: Comparing 16 bit images yields the same results as for 8 bit images
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm '<algorithm>' as 16 bit grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | algorithm | result              |
+      | tests/data/pad_gaprao.png               | RMS       | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | RMS       | 0.9201704590012584  |
+      | tests/data/pad_gaprao_noise.png         | RMS       | 0.7512383697679271  |
+      | tests/data/pad_gaprao_gray_inverted.png | RMS       | 0.497502556580533   |
+      | tests/data/pad_gaprao.png               | MSSIM     | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | MSSIM     | 0.9465500206208792  |
+      | tests/data/pad_gaprao_noise.png         | MSSIM     | 0.1260665609278695  |
+      | tests/data/pad_gaprao_gray_inverted.png | MSSIM     | -0.6559340036804089 |
+      | tests/data/pad_gaprao_lighter.png       | PSNR      | 21.95672683856325   |
+      | tests/data/pad_gaprao_noise.png         | PSNR      | 12.084332209095777  |
+
+  Scenario Outline: Comparing float images yields about the same results as for 8 bit images
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm '<algorithm>' as float grayscale
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | algorithm | result              |
+      | tests/data/pad_gaprao.png               | RMS       | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | RMS       | 0.9201704572176641  |
+      | tests/data/pad_gaprao_noise.png         | RMS       | 0.7512383672102567  |
+      | tests/data/pad_gaprao_gray_inverted.png | RMS       | 0.49750255456035486 |
+      | tests/data/pad_gaprao.png               | MSSIM     | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | MSSIM     | 0.9465500229589601  |
+      | tests/data/pad_gaprao_noise.png         | MSSIM     | 0.1260665599095845  |
+      | tests/data/pad_gaprao_gray_inverted.png | MSSIM     | -0.6559340052158504 |
+
+  Scenario Outline: Comparing a broken image to the original excluding a region
+    Given the images 'tests/data/pad_gaprao.png' and 'tests/data/pad_gaprao_broken.png' are loaded
+    When comparing the images using algorithm '<algorithm>' as grayscale ignoring a <width> by 405 pixel rectangle at 15, 36
+    Then the similarity score is <result>
+    And the similarity image shows <masked> masked pixels
+
+    Examples:
+      | algorithm      | width | result             | masked |
+      | RMS            | 688   | 1.0                | 278640 |
+      | MSE            | 688   | 0.0                | 278640 |
+      | MSSIM          | 688   | 1.0                | 278640 |
+      | Gaussian MSSIM | 688   | 1.0                | 278640 |
+      | RMS            | 344   | 0.9493296416863344 | 139320 |
+      | MSE            | 344   | 166.95071914993542 | 139320 |
+      | MSSIM          | 344   | 0.9686865208241194 | 139320 |
+      | Gaussian MSSIM | 344   | 0.9690574187873705 | 139320 |
@@ -26,6 +26,62 @@ Feature: RGB image comparison using RMS and MSSIM
       | tests/data/pad_gaprao_gray_inverted.png | -0.6520514023141206 |
       | tests/data/pad_gaprao_color_filters.png | 0.9481089021470664  |
 
+  Scenario Outline: Comparing a modified image to the original using multi-scale SSIM algorithm
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm 'MS-SSIM' as rgb
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result              |
+      | tests/data/pad_gaprao.png               | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | 0.9771924526760574  |
+      | tests/data/pad_gaprao_color_filters.png | 0.9818122801474138  |
+
+  Scenario Outline: Comparing a modified image to the original using raw MSE and PSNR
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm '<algorithm>' as rgb
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | algorithm | result              |
+      | tests/data/pad_gaprao_lighter.png       | MSE       | 412.4123465277778   |
+      | tests/data/pad_gaprao_color_filters.png | MSE       | 183.04226944444446  |
+      | tests/data/pad_gaprao_lighter.png       | PSNR      | 21.977487024411516  |
+      | tests/data/pad_gaprao_color_filters.png | PSNR      | 25.50528969133645   |
+
+  Scenario Outline: Comparing a modified image to the original counting pixels over a tolerance
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using algorithm 'pixels over 16 levels' as rgb
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result              |
+      | tests/data/pad_gaprao_broken.png        | 0.0380625           |
+      | tests/data/pad_gaprao_color_filters.png | 0.43776041666666665 |
+
+  Scenario Outline: Comparing a modified image to the original using FSIMc
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using FSIMc as rgb
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result              |
+      | tests/data/pad_gaprao.png               | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | 0.9839812664969037  |
+      | tests/data/pad_gaprao_color_filters.png | 0.9784110233858362  |
+
+  Scenario Outline: Comparing a modified image to the original using FLIP
+    Given the images 'tests/data/pad_gaprao.png' and '<compare_image>' are loaded
+    When comparing the images using FLIP as rgb
+    Then the similarity score is <result>
+
+    Examples:
+      | compare_image                           | result              |
+      | tests/data/pad_gaprao.png               | 1.0                 |
+      | tests/data/pad_gaprao_lighter.png       | 0.6704055699372927  |
+      | tests/data/pad_gaprao_broken.png        | 0.9613390816572505  |
+      | tests/data/pad_gaprao_color_filters.png | 0.7882940996738376  |
+
   Scenario: Comparing an image to the original with RMS and checking the difference image
     Given the images 'tests/data/pad_gaprao.png' and 'tests/data/pad_gaprao_broken.png' are loaded
     When comparing the images using RMS as rgb
@@ -46,3 +102,16 @@ Feature: RGB image comparison using RMS and MSSIM
     When comparing the images using MSSIM as rgb
     Then the rgb similarity image matches 'tests/data/pad_gaprao_color_filters_ssim_compare_rgb.png'
 
+
+  Scenario Outline: Comparing a broken image to the original excluding a region
+    Given the images 'tests/data/pad_gaprao.png' and 'tests/data/pad_gaprao_broken.png' are loaded
+    When comparing the images using algorithm '<algorithm>' as rgb ignoring a <width> by 405 pixel rectangle at 15, 36
+    Then the similarity score is <result>
+    And the similarity image shows <masked> masked pixels
+
+    Examples:
+      | algorithm | width | result             | masked |
+      | RMS       | 688   | 1.0                | 278640 |
+      | MSSIM     | 688   | 1.0                | 278640 |
+      | RMS       | 344   | 0.9441383688501316 | 139320 |
+      | MSSIM     | 344   | 0.9670248255819026 | 139320 |